@@ -0,0 +1,338 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use colored::Colorize;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::events::{self, ScanEvent};
+use crate::output::triage::TriageFinding;
+
+// the address the controller listens on without --listen, only the agents
+// on the same machine or behind a ssh tunnel reach it
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:7700";
+
+// how long the controller waits for the hello of a new connection
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+// the longest hello the controller reads, with its newline. the hello is read
+// before the token is checked so anyone reaching the port can send it
+const MAX_HELLO_LEN: u64 = 4096;
+
+// the Hello struct is the first json line an agent sends, the token must be
+// the --token of the controller
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Hello {
+    #[serde(default)]
+    pub token: String,
+}
+
+// the Assignment struct is the part of the scan the controller pushes to an
+// agent when the scan starts, as the first json line of the connection
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Assignment {
+    // the --shard of the agent, eg `2/3`
+    pub shard: String,
+    pub urls: Vec<String>,
+}
+
+// the AgentConnection struct is the connection of an agent to its controller,
+// the findings are sent back over it as json lines as they are found
+#[derive(Debug)]
+pub struct AgentConnection {
+    stream: BufReader<TcpStream>,
+}
+
+// connects to the controller, says hello with the token and waits for the
+// assignment of the agent.
+pub async fn connect(
+    controller: &str,
+    token: &str,
+) -> Result<(AgentConnection, Assignment), String> {
+    let stream = match TcpStream::connect(controller).await {
+        Ok(stream) => stream,
+        Err(e) => return Err(format!("failed to connect to the controller: {}", e)),
+    };
+    let mut stream = BufReader::new(stream);
+    let hello = Hello {
+        token: token.to_string(),
+    };
+    let mut line = match serde_json::to_string(&hello) {
+        Ok(line) => line,
+        Err(e) => return Err(format!("failed to encode the hello: {}", e)),
+    };
+    line.push('\n');
+    if let Err(e) = stream.get_mut().write_all(line.as_bytes()).await {
+        return Err(format!("failed to send the hello: {}", e));
+    }
+    let mut line = String::new();
+    match stream.read_line(&mut line).await {
+        Ok(0) => return Err("the controller refused the agent, check --agent-token".to_string()),
+        Ok(_) => {}
+        Err(e) => return Err(format!("failed to read the assignment: {}", e)),
+    }
+    let assignment = match serde_json::from_str(&line) {
+        Ok(assignment) => assignment,
+        Err(e) => return Err(format!("invalid assignment from the controller: {}", e)),
+    };
+    Ok((AgentConnection { stream }, assignment))
+}
+
+// the finding the controller is sent for the event, the events that aren't
+// findings are left out.
+fn event_finding(event: ScanEvent) -> Option<TriageFinding> {
+    match event {
+        ScanEvent::Finding {
+            kind,
+            url,
+            payload,
+            status,
+            tags,
+            evidence,
+            family,
+            ..
+        } => Some(TriageFinding {
+            kind: kind.name().to_string(),
+            url,
+            family,
+            status: status.to_string(),
+            detail: payload,
+            page: String::new(),
+            evidence,
+            decision: Default::default(),
+            tags,
+        }),
+        ScanEvent::RouteDiscovered {
+            url,
+            content_length,
+            tags,
+            ..
+        } => Some(TriageFinding {
+            kind: "discovery".to_string(),
+            url,
+            family: String::new(),
+            status: "200".to_string(),
+            detail: content_length,
+            page: String::new(),
+            evidence: String::new(),
+            decision: Default::default(),
+            tags,
+        }),
+        _ => None,
+    }
+}
+
+impl AgentConnection {
+    // streams the findings and discoveries to the controller as the workers
+    // find them, the task ends with the events and gives the connection back
+    // to be closed.
+    pub fn stream_findings(mut self) -> JoinHandle<AgentConnection> {
+        let mut events = Box::pin(events::subscribe());
+        tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                if let Some(finding) = event_finding(event) {
+                    if let Err(e) = self.send(&finding).await {
+                        tracing::warn!(url = %finding.url, error = %e, "failed to stream the finding");
+                    }
+                }
+            }
+            self
+        })
+    }
+
+    // sends the finding to the controller as a json line.
+    async fn send(&mut self, finding: &TriageFinding) -> Result<(), String> {
+        let mut line = match serde_json::to_string(finding) {
+            Ok(line) => line,
+            Err(e) => return Err(format!("failed to encode the finding: {}", e)),
+        };
+        line.push('\n');
+        match self.stream.get_mut().write_all(line.as_bytes()).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("failed to report to the controller: {}", e)),
+        }
+    }
+
+    // closes the connection once the scan is over.
+    pub async fn close(mut self) {
+        let _ = self.stream.get_mut().shutdown().await;
+    }
+}
+
+// compares the tokens without leaking where they differ through the timing.
+fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// reads the hello of the connection and checks its token.
+async fn accept_hello(stream: &mut TcpStream, token: &str) -> Result<(), String> {
+    let mut reader = BufReader::new(stream).take(MAX_HELLO_LEN);
+    let mut line = String::new();
+    match tokio::time::timeout(HELLO_TIMEOUT, reader.read_line(&mut line)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => return Err(format!("failed to read the hello: {}", e)),
+        Err(_) => return Err("no hello".to_string()),
+    }
+    // a hello cut at the limit doesn't end with its newline.
+    if line.len() as u64 == MAX_HELLO_LEN && !line.ends_with('\n') {
+        return Err("hello too long".to_string());
+    }
+    // the hello is the only line sent before the assignment, nothing is left
+    // in the buffer of the reader.
+    let hello: Hello = match serde_json::from_str(&line) {
+        Ok(hello) => hello,
+        Err(_) => return Err("invalid hello".to_string()),
+    };
+    if !tokens_match(token, &hello.token) {
+        return Err("invalid token".to_string());
+    }
+    Ok(())
+}
+
+// returns true when only the local machine can reach the address.
+fn is_loopback(listen: &str) -> bool {
+    match listen.parse::<SocketAddr>() {
+        Ok(addr) => addr.ip().is_loopback(),
+        Err(_) => listen.starts_with("localhost:"),
+    }
+}
+
+// forwards the findings an agent reports to the controller, tagged with the
+// address of the agent.
+async fn read_findings(stream: TcpStream, addr: SocketAddr, tx: mpsc::Sender<serde_json::Value>) {
+    let mut lines = BufReader::new(stream).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::warn!(agent = %addr, error = %e, "agent connection lost");
+                break;
+            }
+        };
+        let mut finding: serde_json::Value = match serde_json::from_str(&line) {
+            Ok(finding) => finding,
+            Err(e) => {
+                tracing::debug!(agent = %addr, error = %e, "invalid finding from agent");
+                continue;
+            }
+        };
+        if let Some(finding) = finding.as_object_mut() {
+            finding.insert("agent".to_string(), addr.to_string().into());
+        }
+        if tx.send(finding).await.is_err() {
+            break;
+        }
+    }
+}
+
+// waits for the agents, pushes a shard of the urls to each of them and prints
+// the findings they report until every agent is done. the findings are also
+// appended to the out file as json lines, the count is returned. the agents
+// must send the token, which can only be left empty on a loopback address.
+pub async fn run_controller(
+    listen: &str,
+    token: &str,
+    agents: usize,
+    urls: Vec<String>,
+    out: Option<&str>,
+) -> Result<usize, String> {
+    if token.is_empty() && !is_loopback(listen) {
+        return Err(format!(
+            "listening on {} needs --token, anyone reaching it could take the urls and push findings",
+            listen
+        ));
+    }
+    let listener = match TcpListener::bind(listen).await {
+        Ok(listener) => listener,
+        Err(e) => return Err(format!("failed to listen on {}: {}", listen, e)),
+    };
+    let mut connections = vec![];
+    while connections.len() < agents {
+        let (mut stream, addr) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => return Err(format!("failed to accept an agent: {}", e)),
+        };
+        if let Err(e) = accept_hello(&mut stream, token).await {
+            println!(
+                "{} {} {}",
+                "agent ::".bold().green(),
+                addr.to_string().bold().blue(),
+                format!("refused ({})", e).red(),
+            );
+            continue;
+        }
+        println!(
+            "{} {} {}",
+            "agent ::".bold().green(),
+            addr.to_string().bold().blue(),
+            format!("connected ({}/{})", connections.len() + 1, agents).white(),
+        );
+        connections.push((stream, addr));
+    }
+
+    let (tx, mut rx) = mpsc::channel::<serde_json::Value>(100);
+    for (index, (mut stream, addr)) in connections.into_iter().enumerate() {
+        let assignment = Assignment {
+            shard: format!("{}/{}", index + 1, agents),
+            urls: urls.clone(),
+        };
+        let mut line = match serde_json::to_string(&assignment) {
+            Ok(line) => line,
+            Err(e) => return Err(format!("failed to encode the assignment: {}", e)),
+        };
+        line.push('\n');
+        if let Err(e) = stream.write_all(line.as_bytes()).await {
+            return Err(format!("failed to send the assignment to {}: {}", addr, e));
+        }
+        tokio::spawn(read_findings(stream, addr, tx.clone()));
+    }
+    drop(tx);
+
+    let mut outfile = match out {
+        Some(out) => match std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(out)
+        {
+            Ok(outfile) => Some(outfile),
+            Err(e) => return Err(format!("failed to open output file: {:?}", e)),
+        },
+        None => None,
+    };
+    let mut count = 0;
+    while let Some(finding) = rx.recv().await {
+        let field = |name: &str| {
+            finding
+                .get(name)
+                .and_then(|value| value.as_str())
+                .unwrap_or("")
+                .to_string()
+        };
+        println!(
+            "{} {} {} {} {}",
+            format!("{} ::", field("agent")).bold().green(),
+            field("kind").bold().purple(),
+            field("url").bold().blue(),
+            "::".bold().green(),
+            field("status").bold().white(),
+        );
+        if let Some(outfile) = &mut outfile {
+            use std::io::Write;
+            if let Err(e) = writeln!(outfile, "{}", finding) {
+                tracing::warn!(error = %e, "failed to save finding");
+            }
+        }
+        count += 1;
+    }
+    Ok(count)
+}
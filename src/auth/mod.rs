@@ -0,0 +1,358 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex, RwLock,
+};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use regex::Regex;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{Method, Response, Url};
+
+use crate::client::Client;
+
+pub mod ntlm;
+
+// a session whose refresh didn't help isn't refreshed again for this
+// interval, the statuses of an expired session are often the answer of the
+// target to the path itself
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+// the markers of a raw request replaced by the traversal payload and the word
+pub const PAYLOAD_MARKER: &str = "§PAYLOAD§";
+pub const WORD_MARKER: &str = "§WORD§";
+
+// the RawRequest struct holds a captured http request, eg exported from burp
+#[derive(Clone, Debug)]
+pub struct RawRequest {
+    pub method: Method,
+    // the url without its markers
+    pub url: Url,
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    pub body: String,
+    // the url in front of and behind the §PAYLOAD§ of the path, the traversal
+    // is injected there instead of the injection points of the url
+    pub injection: Option<(String, String)>,
+}
+
+// the Session struct holds the credentials captured from the login request
+// and shared by all the clients for the rest of the scan
+#[derive(Debug, Default)]
+pub struct Session {
+    pub login_request: Option<RawRequest>,
+    authorization: RwLock<Option<HeaderValue>>,
+    ntlm: Option<ntlm::Credentials>,
+    expired_statuses: Vec<u16>,
+    expired_regex: Option<Regex>,
+    urls: RwLock<Vec<String>>,
+    generation: AtomicU64,
+    refresh_lock: tokio::sync::Mutex<()>,
+    // when a request retried after a refresh came back expired again
+    throttled_at: Mutex<Option<Instant>>,
+}
+
+impl Session {
+    pub fn new(
+        login_request: Option<RawRequest>,
+        authorization: Option<HeaderValue>,
+        expired_statuses: Vec<u16>,
+        expired_regex: Option<Regex>,
+    ) -> Session {
+        Session {
+            login_request,
+            authorization: RwLock::new(authorization),
+            expired_statuses,
+            expired_regex,
+            ..Default::default()
+        }
+    }
+
+    // the requests are authenticated with the --auth-ntlm account, see
+    // Client::execute_ntlm.
+    pub fn with_ntlm(mut self, ntlm: Option<ntlm::Credentials>) -> Session {
+        self.ntlm = ntlm;
+        self
+    }
+
+    pub fn ntlm(&self) -> Option<&ntlm::Credentials> {
+        self.ntlm.as_ref()
+    }
+
+    // the session can only be refreshed when we know how to log in again.
+    pub fn can_refresh(&self) -> bool {
+        self.login_request.is_some()
+    }
+
+    // the generation is bumped on every refresh of the session.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    // checks the response against the session expired condition, the body is only
+    // read when a regex is configured, in which case the response is rebuilt from it.
+    pub async fn is_expired(&self, response: Response) -> Result<(bool, Response), reqwest::Error> {
+        if self.expired_statuses.contains(&response.status().as_u16()) {
+            return Ok((true, response));
+        }
+        let expired_regex = match &self.expired_regex {
+            Some(expired_regex) => expired_regex,
+            None => return Ok((false, response)),
+        };
+
+        let status = response.status();
+        let version = response.version();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+        let expired = expired_regex.is_match(&String::from_utf8_lossy(&body));
+
+        // the parts are moved over as they are, unlike the builder this can't fail.
+        let mut rebuilt = http::Response::new(body);
+        *rebuilt.status_mut() = status;
+        *rebuilt.version_mut() = version;
+        *rebuilt.headers_mut() = headers;
+        Ok((expired, Response::from(rebuilt)))
+    }
+
+    // replays the login request, unless another worker already refreshed
+    // the session after our request was sent. once a retried request came
+    // back expired the session isn't refreshed for MIN_REFRESH_INTERVAL, the
+    // target refused the path and not the session.
+    pub async fn refresh(&self, client: &Client, seen_generation: u64) -> bool {
+        if self.generation() != seen_generation {
+            return true;
+        }
+        if self.throttled() {
+            return false;
+        }
+        let _refreshing = self.refresh_lock.lock().await;
+        if self.generation() != seen_generation {
+            return true;
+        }
+        if self.throttled() {
+            return false;
+        }
+        let urls = self.urls.read().unwrap().clone();
+        let refreshed = login(client, self, &urls).await.is_ok();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        refreshed
+    }
+
+    // called when the request retried after a refresh came back expired.
+    pub fn throttle(&self) {
+        *self.throttled_at.lock().unwrap() = Some(Instant::now());
+    }
+
+    fn throttled(&self) -> bool {
+        self.throttled_at
+            .lock()
+            .unwrap()
+            .is_some_and(|throttled_at| throttled_at.elapsed() < MIN_REFRESH_INTERVAL)
+    }
+
+    pub fn authorization(&self) -> Option<HeaderValue> {
+        self.authorization.read().unwrap().clone()
+    }
+
+    fn set_authorization(&self, authorization: HeaderValue) {
+        *self.authorization.write().unwrap() = Some(authorization);
+    }
+}
+
+// builds the authorization header for --auth-basic user:pass.
+pub fn basic_authorization(credentials: &str) -> Result<HeaderValue, String> {
+    if !credentials.contains(':') {
+        return Err("--auth-basic expects user:pass".to_string());
+    }
+    let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+    sensitive_header(&format!("Basic {}", encoded))
+}
+
+// builds the authorization header for --auth-bearer token.
+pub fn bearer_authorization(token: &str) -> Result<HeaderValue, String> {
+    sensitive_header(&format!("Bearer {}", token.trim()))
+}
+
+fn sensitive_header(value: &str) -> Result<HeaderValue, String> {
+    match HeaderValue::from_str(value) {
+        Ok(mut value) => {
+            value.set_sensitive(true);
+            Ok(value)
+        }
+        Err(_) => Err("invalid authorization credentials".to_string()),
+    }
+}
+
+// parses a raw http request, the request line may hold an absolute url,
+// otherwise the url is built from the Host header using https. the §PAYLOAD§
+// and §WORD§ markers of the headers and the body are kept for the jobs.
+pub fn parse_raw_request(contents: &str) -> Result<RawRequest, String> {
+    let contents = contents.replace("\r\n", "\n");
+    let (head, body) = match contents.split_once("\n\n") {
+        Some((head, body)) => (head, body),
+        None => (contents.as_str(), ""),
+    };
+    let mut lines = head.lines();
+    let request_line = match lines.next() {
+        Some(request_line) => request_line,
+        None => return Err("empty raw request".to_string()),
+    };
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method, target),
+        _ => return Err(format!("invalid request line: {}", request_line)),
+    };
+    let method = match Method::from_bytes(method.as_bytes()) {
+        Ok(method) => method,
+        Err(_) => return Err(format!("invalid request method: {}", method)),
+    };
+
+    let mut host = String::from("");
+    let mut headers = vec![];
+    for line in lines {
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        if key.eq_ignore_ascii_case("host") {
+            host = value.to_string();
+            continue;
+        }
+        // reqwest computes these itself
+        if key.eq_ignore_ascii_case("content-length") || key.eq_ignore_ascii_case("connection") {
+            continue;
+        }
+        let key = match HeaderName::from_bytes(key.as_bytes()) {
+            Ok(key) => key,
+            Err(_) => return Err(format!("invalid header name: {}", key)),
+        };
+        let value = match HeaderValue::from_str(value) {
+            Ok(value) => value,
+            Err(_) => return Err(format!("invalid header value: {}", value)),
+        };
+        headers.push((key, value));
+    }
+
+    let url = if target.starts_with("http://") || target.starts_with("https://") {
+        target.to_string()
+    } else if !host.is_empty() {
+        format!("https://{}{}", host, target)
+    } else {
+        return Err("raw request has neither an absolute url nor a Host header".to_string());
+    };
+    let (url, injection) = match url.split_once(PAYLOAD_MARKER) {
+        Some((base, suffix)) => {
+            // the url of the target leaves the markers out, `/api/§PAYLOAD§/orders`
+            // is the target /api/orders.
+            let suffix_without_word = suffix.replace(WORD_MARKER, "");
+            let url = match (base.ends_with('/'), suffix_without_word.strip_prefix('/')) {
+                (true, Some(suffix)) => format!("{}{}", base, suffix),
+                _ => format!("{}{}", base, suffix_without_word),
+            };
+            // the payloads end with a slash of their own.
+            let suffix = suffix.strip_prefix('/').unwrap_or(suffix);
+            (url, Some((base.to_string(), suffix.to_string())))
+        }
+        None if url.contains(WORD_MARKER) => {
+            return Err(format!(
+                "{} in the url needs a {}",
+                WORD_MARKER, PAYLOAD_MARKER
+            ))
+        }
+        None => (url, None),
+    };
+    let url = match Url::parse(&url) {
+        Ok(url) => url,
+        Err(e) => return Err(format!("invalid raw request url: {:?}", e)),
+    };
+
+    Ok(RawRequest {
+        method,
+        url,
+        headers,
+        body: body.trim_end_matches('\n').to_string(),
+        injection,
+    })
+}
+
+// reads and parses the raw request file.
+pub fn load_raw_request(path: &str) -> Result<RawRequest, String> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => parse_raw_request(&contents),
+        Err(e) => Err(format!("failed to open raw request file: {:?}", e)),
+    }
+}
+
+// reads the raw request file, or every file of the directory in the order of
+// their names.
+pub fn load_raw_requests(path: &str) -> Result<Vec<RawRequest>, String> {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(vec![load_raw_request(path)?]),
+    };
+    let mut paths: Vec<std::path::PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+    let mut raw_requests = vec![];
+    for path in paths {
+        let path = path.to_string_lossy();
+        match load_raw_request(&path) {
+            Ok(raw_request) => raw_requests.push(raw_request),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        }
+    }
+    if raw_requests.is_empty() {
+        return Err(format!("no raw requests found in {}", path));
+    }
+    Ok(raw_requests)
+}
+
+// sends the login request of the session and captures the cookies and the
+// authorization header it hands out so every target request carries them.
+pub async fn login(client: &Client, session: &Session, urls: &[String]) -> Result<u16, String> {
+    let login_request = match &session.login_request {
+        Some(login_request) => login_request,
+        None => return Err("no login request configured".to_string()),
+    };
+    *session.urls.write().unwrap() = urls.to_vec();
+
+    let mut request = client.request(login_request.method.clone(), login_request.url.as_str());
+    for (key, value) in &login_request.headers {
+        request = request.header(key.clone(), value.clone());
+    }
+    if !login_request.body.is_empty() {
+        request = request.body(login_request.body.clone());
+    }
+    let request = match request.build() {
+        Ok(request) => request,
+        Err(e) => return Err(format!("failed to build login request: {:?}", e)),
+    };
+    let response = match client.send(request).await {
+        Ok(response) => response,
+        Err(e) => return Err(format!("login request failed: {:?}", e)),
+    };
+
+    // the login host may differ from the targets, so the cookies are
+    // stored for every target without their domain/path attributes.
+    for set_cookie in response.headers().get_all(reqwest::header::SET_COOKIE) {
+        let set_cookie = match set_cookie.to_str() {
+            Ok(set_cookie) => set_cookie,
+            Err(_) => continue,
+        };
+        let cookie = match set_cookie.split(';').next() {
+            Some(cookie) => cookie.trim(),
+            None => continue,
+        };
+        for url in urls {
+            if let Ok(url) = Url::parse(url) {
+                client.cookie_jar().add_cookie_str(cookie, &url);
+            }
+        }
+    }
+    if let Some(authorization) = response.headers().get(reqwest::header::AUTHORIZATION) {
+        session.set_authorization(authorization.clone());
+    }
+
+    Ok(response.status().as_u16())
+}
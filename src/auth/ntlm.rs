@@ -0,0 +1,354 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use md4::{Digest, Md4};
+use md5::Md5;
+use rand::Rng;
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+
+// unicode, request target, ntlm, always sign, extended session security,
+// target info, 128 and 56 bit.
+const NEGOTIATE_FLAGS: u32 = 0xa088_8205;
+// the key exchange is left out, the session key is never used over http.
+const NEGOTIATE_KEY_EXCH: u32 = 0x4000_0000;
+
+// the AvId of the timestamp in the target info of the challenge
+const AV_TIMESTAMP: u16 = 7;
+// the 100ns intervals between 1601 and 1970
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+// the Credentials struct holds the --auth-ntlm account, the domain is
+// optional and given as DOMAIN\user
+#[derive(Clone, Default)]
+pub struct Credentials {
+    domain: String,
+    user: String,
+    password: String,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Credentials({}\\{})", self.domain, self.user)
+    }
+}
+
+// the parts of the challenge message the response is computed from
+struct Challenge {
+    flags: u32,
+    server_challenge: [u8; 8],
+    target_info: Vec<u8>,
+}
+
+// parses the --auth-ntlm value, DOMAIN\user:pass or user:pass.
+pub fn parse_credentials(credentials: &str) -> Result<Credentials, String> {
+    let (account, password) = match credentials.split_once(':') {
+        Some((account, password)) if !account.is_empty() => (account, password),
+        _ => return Err("--auth-ntlm expects DOMAIN\\user:pass or user:pass".to_string()),
+    };
+    let (domain, user) = match account.split_once('\\') {
+        Some((domain, user)) => (domain, user),
+        None => ("", account),
+    };
+    Ok(Credentials {
+        domain: domain.to_string(),
+        user: user.to_string(),
+        password: password.to_string(),
+    })
+}
+
+// the challenge of a `WWW-Authenticate: NTLM <challenge>` header, None when the
+// header only offers ntlm.
+pub fn challenge_header(value: &str) -> Option<&str> {
+    let (scheme, challenge) = value.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("ntlm") {
+        return None;
+    }
+    Some(challenge.trim())
+}
+
+// whether the header offers ntlm, with or without a challenge.
+pub fn offers_ntlm(value: &str) -> bool {
+    let scheme = value.trim().split(' ').next().unwrap_or("");
+    scheme.eq_ignore_ascii_case("ntlm")
+}
+
+// the negotiate message that starts the handshake, base64 encoded.
+pub fn negotiate_message() -> String {
+    let mut message = SIGNATURE.to_vec();
+    message.extend_from_slice(&1u32.to_le_bytes());
+    message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+    // the domain and workstation are left empty.
+    message.extend_from_slice(&[0; 16]);
+    base64::engine::general_purpose::STANDARD.encode(message)
+}
+
+// the authenticate message answering the base64 challenge with an ntlmv2 response.
+pub fn authenticate_message(credentials: &Credentials, challenge: &str) -> Result<String, String> {
+    let challenge = match base64::engine::general_purpose::STANDARD.decode(challenge) {
+        Ok(challenge) => parse_challenge(&challenge)?,
+        Err(_) => return Err("invalid ntlm challenge".to_string()),
+    };
+    let message = authenticate_bytes(
+        credentials,
+        &challenge,
+        &rand::thread_rng().gen(),
+        filetime_now(),
+    );
+    Ok(base64::engine::general_purpose::STANDARD.encode(message))
+}
+
+// the authenticate message for the client challenge, the time is only used
+// when the server didn't send its own timestamp.
+fn authenticate_bytes(
+    credentials: &Credentials,
+    challenge: &Challenge,
+    client_challenge: &[u8; 8],
+    now: u64,
+) -> Vec<u8> {
+    let timestamp = av_timestamp(&challenge.target_info);
+
+    let ntowf = ntowf_v2(credentials);
+    let nt_response = nt_response(
+        &ntowf,
+        challenge,
+        client_challenge,
+        timestamp.unwrap_or(now),
+    );
+    // the lmv2 response is zeroed once the server sent its own timestamp.
+    let lm_response = match timestamp {
+        Some(_) => vec![0; 24],
+        None => {
+            let mut lm_response =
+                hmac_md5(&ntowf, &[&challenge.server_challenge, client_challenge]).to_vec();
+            lm_response.extend_from_slice(client_challenge);
+            lm_response
+        }
+    };
+
+    let fields = [
+        lm_response,
+        nt_response,
+        utf16le(&credentials.domain),
+        utf16le(&credentials.user),
+        vec![],
+        vec![],
+    ];
+    let mut message = SIGNATURE.to_vec();
+    message.extend_from_slice(&3u32.to_le_bytes());
+    let mut offset = 12 + fields.len() * 8 + 4;
+    for field in &fields {
+        message.extend_from_slice(&(field.len() as u16).to_le_bytes());
+        message.extend_from_slice(&(field.len() as u16).to_le_bytes());
+        message.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += field.len();
+    }
+    message.extend_from_slice(&(challenge.flags & !NEGOTIATE_KEY_EXCH).to_le_bytes());
+    for field in &fields {
+        message.extend_from_slice(field);
+    }
+    message
+}
+
+// the ntlmv2 one-way function of the password, user and domain.
+fn ntowf_v2(credentials: &Credentials) -> [u8; 16] {
+    let mut nt_hash = Md4::new();
+    nt_hash.update(utf16le(&credentials.password));
+    let identity = format!("{}{}", credentials.user.to_uppercase(), credentials.domain);
+    hmac_md5(&nt_hash.finalize(), &[&utf16le(&identity)])
+}
+
+// the ntlmv2 response, the proof followed by the blob it was computed over.
+// the target info of the challenge is echoed back in the blob.
+fn nt_response(
+    ntowf: &[u8; 16],
+    challenge: &Challenge,
+    client_challenge: &[u8; 8],
+    timestamp: u64,
+) -> Vec<u8> {
+    let mut blob = vec![1, 1, 0, 0, 0, 0, 0, 0];
+    blob.extend_from_slice(&timestamp.to_le_bytes());
+    blob.extend_from_slice(client_challenge);
+    blob.extend_from_slice(&[0; 4]);
+    blob.extend_from_slice(&challenge.target_info);
+    blob.extend_from_slice(&[0; 4]);
+    let mut nt_response = hmac_md5(ntowf, &[&challenge.server_challenge, &blob]).to_vec();
+    nt_response.extend_from_slice(&blob);
+    nt_response
+}
+
+fn parse_challenge(message: &[u8]) -> Result<Challenge, String> {
+    if message.len() < 48 || &message[..8] != SIGNATURE || u32_at(message, 8) != 2 {
+        return Err("invalid ntlm challenge".to_string());
+    }
+    let length = u16::from_le_bytes([message[40], message[41]]) as usize;
+    let offset = u32_at(message, 44) as usize;
+    let target_info = match offset
+        .checked_add(length)
+        .and_then(|end| message.get(offset..end))
+    {
+        Some(target_info) => target_info.to_vec(),
+        None => return Err("invalid ntlm challenge target info".to_string()),
+    };
+    let mut server_challenge = [0; 8];
+    server_challenge.copy_from_slice(&message[24..32]);
+    Ok(Challenge {
+        flags: u32_at(message, 20),
+        server_challenge,
+        target_info,
+    })
+}
+
+// the MsvAvTimestamp pair of the target info, a list of id, length and value.
+fn av_timestamp(target_info: &[u8]) -> Option<u64> {
+    let mut rest = target_info;
+    while rest.len() >= 4 {
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let length = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+        let value = rest.get(4..4 + length)?;
+        if id == AV_TIMESTAMP && length == 8 {
+            return Some(u64::from_le_bytes(value.try_into().ok()?));
+        }
+        rest = &rest[4 + length..];
+    }
+    None
+}
+
+fn filetime_now() -> u64 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    FILETIME_UNIX_EPOCH + since_epoch.as_nanos() as u64 / 100
+}
+
+fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> [u8; 16] {
+    let mut mac = Hmac::<Md5>::new_from_slice(key).expect("hmac accepts any key length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+fn u32_at(message: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        message[offset],
+        message[offset + 1],
+        message[offset + 2],
+        message[offset + 3],
+    ])
+}
+
+fn utf16le(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the values of MS-NLMP 4.2.4, the ntlmv2 authentication example
+    const SERVER_CHALLENGE: [u8; 8] = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+    const CLIENT_CHALLENGE: [u8; 8] = [0xaa; 8];
+    const CHALLENGE_MESSAGE: &str = "4e544c4d53535000020000000c000c003800000033828ae20123456789abcdef00000000000000002400240044000000060070170000000f53006500720076006500720002000c0044006f006d00610069006e0001000c0053006500720076006500720000000000";
+
+    fn credentials() -> Credentials {
+        parse_credentials("Domain\\User:Password").unwrap()
+    }
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // the payload of the security buffer at the offset of the message header.
+    fn field(message: &[u8], header: usize) -> &[u8] {
+        let length = u16::from_le_bytes([message[header], message[header + 1]]) as usize;
+        let offset = u32_at(message, header + 4) as usize;
+        &message[offset..offset + length]
+    }
+
+    #[test]
+    fn ntowf_v2_matches_the_spec() {
+        assert_eq!(
+            ntowf_v2(&credentials()).to_vec(),
+            hex("0c868a403bfd7a93a3001ef22ef02e3f")
+        );
+    }
+
+    #[test]
+    fn parses_the_spec_challenge() {
+        let challenge = parse_challenge(&hex(CHALLENGE_MESSAGE)).unwrap();
+        assert_eq!(challenge.flags, 0xe28a8233);
+        assert_eq!(challenge.server_challenge, SERVER_CHALLENGE);
+        assert_eq!(challenge.target_info.len(), 36);
+        assert_eq!(av_timestamp(&challenge.target_info), None);
+    }
+
+    #[test]
+    fn authenticate_message_matches_the_spec() {
+        let challenge = parse_challenge(&hex(CHALLENGE_MESSAGE)).unwrap();
+        let message = authenticate_bytes(&credentials(), &challenge, &CLIENT_CHALLENGE, 0);
+
+        assert_eq!(&message[..8], SIGNATURE);
+        assert_eq!(u32_at(&message, 8), 3);
+        let mut lm_response = hex("86c35097ac9cec102554764a57cccc19");
+        lm_response.extend_from_slice(&CLIENT_CHALLENGE);
+        assert_eq!(field(&message, 12), lm_response);
+        let nt_response = field(&message, 20);
+        assert_eq!(
+            nt_response[..16].to_vec(),
+            hex("68cd0ab851e51c96aabc927bebef6a1c")
+        );
+        assert_eq!(&nt_response[16..], &nt_response_blob(&challenge));
+        assert_eq!(field(&message, 28), utf16le("Domain"));
+        assert_eq!(field(&message, 36), utf16le("User"));
+        assert_eq!(u32_at(&message, 60), 0xa28a8233);
+    }
+
+    fn nt_response_blob(challenge: &Challenge) -> Vec<u8> {
+        let mut blob = vec![1, 1, 0, 0, 0, 0, 0, 0];
+        blob.extend_from_slice(&[0; 8]);
+        blob.extend_from_slice(&CLIENT_CHALLENGE);
+        blob.extend_from_slice(&[0; 4]);
+        blob.extend_from_slice(&challenge.target_info);
+        blob.extend_from_slice(&[0; 4]);
+        blob
+    }
+
+    #[test]
+    fn rejects_malformed_challenges() {
+        let message = hex(CHALLENGE_MESSAGE);
+        for length in [0, 8, 12, 47] {
+            assert!(parse_challenge(&message[..length]).is_err());
+        }
+        // the target info runs past the end of the message.
+        assert!(parse_challenge(&message[..message.len() - 1]).is_err());
+
+        let mut signature = message.clone();
+        signature[0] = b'X';
+        assert!(parse_challenge(&signature).is_err());
+
+        let mut message_type = message.clone();
+        message_type[8] = 3;
+        assert!(parse_challenge(&message_type).is_err());
+
+        let mut offset = message.clone();
+        offset[44..48].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_challenge(&offset).is_err());
+
+        assert!(authenticate_message(&credentials(), "not base64!").is_err());
+    }
+
+    #[test]
+    fn truncated_target_info_has_no_timestamp() {
+        assert_eq!(av_timestamp(&[7, 0, 8, 0, 1, 2, 3]), None);
+        assert_eq!(av_timestamp(&[7, 0]), None);
+        let mut target_info = vec![7, 0, 8, 0];
+        target_info.extend_from_slice(&42u64.to_le_bytes());
+        assert_eq!(av_timestamp(&target_info), Some(42));
+    }
+}
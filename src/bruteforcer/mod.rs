@@ -1,14 +1,27 @@
-use std::{error::Error, process::exit, time::Duration};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
 
 use colored::Colorize;
 use differ::{Differ, Tag};
 use governor::{Quota, RateLimiter};
 use indicatif::ProgressBar;
 use itertools::iproduct;
-use reqwest::{redirect, Proxy};
-use tokio::{fs::File, io::AsyncWriteExt, sync::mpsc};
+use reqwest::{Method, StatusCode};
+use tokio::{
+    fs::File,
+    io::AsyncWriteExt,
+    sync::{mpsc, OnceCell},
+};
+use tokio_util::sync::CancellationToken;
 
-use crate::utils;
+use crate::client::{self, Client, ClientSettings};
+use crate::events::{self, ScanEvent};
+use crate::observer;
+use crate::output::silent::LineFormat;
+use crate::scope::Scope;
+use crate::tags::TargetTags;
+use crate::utils::{self, Similarity};
 
 // the BruteResult struct which will be used as jobs
 // to save the data to a file
@@ -16,6 +29,104 @@ use crate::utils;
 pub struct BruteResult {
     pub data: String,
     pub rs: String,
+    // the size, words and lines of the body, counted as ffuf does
+    pub size: utils::ResponseSize,
+    // the Content-Encoding the route was served with, empty without one
+    pub content_encoding: String,
+}
+
+// the responses of the web root fetched per host to measure its natural variance
+pub const CALIBRATION_SAMPLES: usize = 5;
+
+// the Calibration struct holds the similarity of each host calibrated on the
+// responses of its web root, shared by the workers so a host is sampled once
+#[derive(Debug, Default)]
+pub struct Calibration {
+    similarities: Mutex<HashMap<String, Arc<OnceCell<Arc<Similarity>>>>>,
+}
+
+impl Calibration {
+    // the calibrated similarity of the host, the web root is sampled the first
+    // time the host is seen. the workers reaching a new host while it is
+    // sampled wait for its similarity instead of sampling it again.
+    async fn similarity(
+        &self,
+        pb: &ProgressBar,
+        client: &Client,
+        web_root: &str,
+    ) -> Arc<Similarity> {
+        let cell = self
+            .similarities
+            .lock()
+            .unwrap()
+            .entry(web_root.to_string())
+            .or_default()
+            .clone();
+        cell.get_or_init(|| sample(pb, client, web_root))
+            .await
+            .clone()
+    }
+}
+
+// samples the web root and calibrates the similarity on the natural variance
+// of its responses.
+async fn sample(pb: &ProgressBar, client: &Client, web_root: &str) -> Arc<Similarity> {
+    let mut samples = vec![];
+    for _ in 0..CALIBRATION_SAMPLES {
+        let req = match client.get(web_root).build() {
+            Ok(req) => req,
+            Err(_) => break,
+        };
+        let resp = match client.execute(req).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                tracing::debug!(error = %e, "request failed");
+                continue;
+            }
+        };
+        samples.push(client::text(resp).await.unwrap_or_default());
+    }
+    let (similarity, variances) = client.similarity().calibrated(&samples);
+    for (algorithm, variance) in variances
+        .into_iter()
+        .filter(|(_, variance)| *variance > 0.0)
+    {
+        pb.println(format!(
+            "{} {} {}{} {}{}",
+            "calibrated ::".bold().green(),
+            web_root.bold().blue(),
+            "[".bold().white(),
+            algorithm.name().bold().purple(),
+            variance.to_string().bold().cyan(),
+            "]".bold().white(),
+        ));
+    }
+    Arc::new(similarity)
+}
+
+// the status and Content-Length of a HEAD request. the length is read from the
+// header, the body of a HEAD response is always empty.
+async fn head(client: &Client, url: &str) -> Option<(StatusCode, Option<u64>)> {
+    let req = match client.request(Method::HEAD, url).build() {
+        Ok(req) => req,
+        Err(e) => {
+            tracing::debug!(error = %e, "failed to build the request");
+            return None;
+        }
+    };
+    let resp = match client.execute(req).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::debug!(error = %e, "request failed");
+            return None;
+        }
+    };
+    let length = resp
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.trim().parse().ok());
+    Some((resp.status(), length))
 }
 
 // the Job struct which will be used as jobs for directory bruteforcing
@@ -25,6 +136,24 @@ pub struct BruteJob {
     pub word: Option<String>,
 }
 
+// whether the word may be requested, under the internal root as well as under
+// the web root of the host it is compared with.
+pub fn in_scope(scope: &Scope, url: &str, word: &str) -> bool {
+    if scope.is_empty() {
+        return true;
+    }
+    let web_root_url = match reqwest::Url::parse(url) {
+        Ok(parsed) => format!(
+            "{}://{}/{}",
+            parsed.scheme(),
+            parsed.host_str().unwrap_or(""),
+            word
+        ),
+        Err(_) => return false,
+    };
+    scope.allows(&format!("{}{}", url, word)) && scope.allows(&web_root_url)
+}
+
 // this asynchronous function will send the results to another set of workers
 // for each worker to perform a directory brute force operation on each url.
 pub async fn send_word_to_url(
@@ -32,12 +161,20 @@ pub async fn send_word_to_url(
     urls: Vec<String>,
     wordlists: Vec<String>,
     rate: u32,
+    scope: Arc<Scope>,
+    cancel: CancellationToken,
 ) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
     //set rate limit
     let lim = RateLimiter::direct(Quota::per_second(std::num::NonZeroU32::new(rate).unwrap()));
 
     // start the scan
     for (word, url) in iproduct!(wordlists, urls) {
+        if cancel.is_cancelled() {
+            break;
+        }
+        if !in_scope(&scope, &url, &word) {
+            continue;
+        }
         let url_cp = url.clone();
         let msg = BruteJob {
             url: Some(url_cp),
@@ -56,47 +193,11 @@ pub async fn run_bruteforcer(
     pb: ProgressBar,
     rx: spmc::Receiver<BruteJob>,
     tx: mpsc::Sender<BruteResult>,
-    timeout: usize,
-    http_proxy: String,
+    client_settings: ClientSettings,
+    head_first: bool,
+    calibration: Option<Arc<Calibration>>,
 ) -> BruteResult {
-    let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert(
-        reqwest::header::USER_AGENT,
-        reqwest::header::HeaderValue::from_static(
-            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:95.0) Gecko/20100101 Firefox/95.0",
-        ),
-    );
-
-    let client;
-    if http_proxy.is_empty() {
-        //no certs
-        client = reqwest::Client::builder()
-            .default_headers(headers)
-            .redirect(redirect::Policy::none())
-            .timeout(Duration::from_secs(timeout.try_into().unwrap()))
-            .danger_accept_invalid_hostnames(true)
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap();
-    } else {
-        let proxy = match Proxy::all(http_proxy) {
-            Ok(proxy) => proxy,
-            Err(e) => {
-                pb.println(format!("Could not setup proxy, err: {:?}", e));
-                exit(1);
-            }
-        };
-        //no certs
-        client = reqwest::Client::builder()
-            .default_headers(headers)
-            .redirect(redirect::Policy::none())
-            .timeout(Duration::from_secs(timeout.try_into().unwrap()))
-            .danger_accept_invalid_hostnames(true)
-            .danger_accept_invalid_certs(true)
-            .proxy(proxy)
-            .build()
-            .unwrap();
-    }
+    let client = client::build_client(&pb, &client_settings, 0);
 
     while let Ok(job) = rx.recv() {
         let job_url = job.url.unwrap();
@@ -107,7 +208,8 @@ pub async fn run_bruteforcer(
         let mut internal_web_root_url: String = String::from(job_url);
         let url = match reqwest::Url::parse(&job_url_new) {
             Ok(url) => url,
-            Err(_) => {
+            Err(e) => {
+                tracing::debug!(error = %e, "invalid url");
                 continue;
             }
         };
@@ -134,59 +236,93 @@ pub async fn run_bruteforcer(
             internal_url.bold().blue(),
         ));
 
+        // a HEAD request tells us whether the word exists before we pay for
+        // the bodies, only a 200 can be reported so anything else is skipped.
+        // a 200 of the same length as the word under the web root is the same page
+        // or a soft-404, the GETs would find no change between them.
+        if head_first {
+            let (status, length) = match head(&client, &internal_web_url).await {
+                Some(head) => head,
+                None => continue,
+            };
+            match status {
+                StatusCode::OK => {
+                    if length.is_some()
+                        && head(&client, &web_root_url).await == Some((status, length))
+                    {
+                        continue;
+                    }
+                }
+                StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED => {}
+                _ => continue,
+            }
+        }
+
         let internal_url = internal_web_url.clone();
-        let get = client.get(internal_web_url);
-        let internal_get = client.get(internal_web_root_url);
-        let public_get = client.get(web_root_url);
+        let get = client.get(&internal_web_url);
+        let internal_get = client.get(&internal_web_root_url);
+        let public_get = client.get(&web_root_url);
 
         let public_req = match public_get.build() {
             Ok(req) => req,
-            Err(_) => {
+            Err(e) => {
+                tracing::debug!(error = %e, "failed to build the request");
                 continue;
             }
         };
 
         let internal_req = match internal_get.build() {
             Ok(req) => req,
-            Err(_) => {
+            Err(e) => {
+                tracing::debug!(error = %e, "failed to build the request");
                 continue;
             }
         };
 
         let public_resp = match client.execute(public_req).await {
             Ok(public_resp) => public_resp,
-            Err(_) => {
+            Err(e) => {
+                tracing::debug!(error = %e, "request failed");
                 continue;
             }
         };
 
         let internal_resp = match client.execute(internal_req).await {
             Ok(internal_resp) => internal_resp,
-            Err(_) => {
+            Err(e) => {
+                tracing::debug!(error = %e, "request failed");
                 continue;
             }
         };
 
-        let public_resp_text = match public_resp.text().await {
+        let public_resp_text = match client::text(public_resp).await {
             Ok(public_resp_text) => public_resp_text,
-            Err(_) => continue,
+            Err(e) => {
+                tracing::debug!(error = %e, "failed to read the response body");
+                continue;
+            }
         };
 
-        let internal_resp_text = match internal_resp.text().await {
+        let internal_resp_text = match client::text(internal_resp).await {
             Ok(internal_resp_text) => internal_resp_text,
-            Err(_) => continue,
+            Err(e) => {
+                tracing::debug!(error = %e, "failed to read the response body");
+                continue;
+            }
         };
 
         let req = match get.build() {
             Ok(req) => req,
-            Err(_) => {
+            Err(e) => {
+                tracing::debug!(error = %e, "failed to build the request");
                 continue;
             }
         };
 
         let resp = match client.execute(req).await {
             Ok(resp) => resp,
-            Err(_) => {
+            Err(e) => {
+                tracing::debug!(error = %e, "request failed");
                 continue;
             }
         };
@@ -196,9 +332,29 @@ pub async fn run_bruteforcer(
             None => "".to_string(),
         };
 
+        // with --calibrate the threshold of the host is above its natural variance.
+        let calibrated = match &calibration {
+            Some(calibration) => {
+                let web_root = format!("{}://{}/", schema, host);
+                Some(calibration.similarity(&pb, &client, &web_root).await)
+            }
+            None => None,
+        };
+        let similarity = match &calibrated {
+            Some(similarity) => similarity,
+            None => client.similarity(),
+        };
         let (ok, distance_between_responses) =
-            utils::get_response_change(&internal_resp_text, &public_resp_text);
+            utils::get_response_change(&internal_resp_text, &public_resp_text, similarity);
         if ok && resp.status().as_str() == "200" {
+            let content_encoding = client::content_encoding(resp.headers());
+            let size = match resp.bytes().await {
+                Ok(body) => utils::ResponseSize::of(&body),
+                Err(e) => {
+                    tracing::debug!(error = %e, "failed to read the response body");
+                    continue;
+                }
+            };
             let internal_resp_text_lines = internal_resp_text.lines().collect::<Vec<_>>();
             let public_resp_text_lines = public_resp_text.lines().collect::<Vec<_>>();
             let character_differences =
@@ -254,6 +410,8 @@ pub async fn run_bruteforcer(
             let result_msg = BruteResult {
                 data: internal_url.to_owned(),
                 rs: content_length,
+                size,
+                content_encoding,
             };
             let result = result_msg.clone();
             if let Err(_) = tx.send(result_msg).await {
@@ -266,20 +424,50 @@ pub async fn run_bruteforcer(
     return BruteResult {
         data: "".to_string(),
         rs: "".to_string(),
+        size: utils::ResponseSize::default(),
+        content_encoding: String::new(),
     };
 }
 
 // Saves the output to a file
 pub async fn save_discoveries(
     _: ProgressBar,
-    mut outfile: File,
+    mut outfile: Option<File>,
     mut brx: mpsc::Receiver<BruteResult>,
+    silent: Option<LineFormat>,
+    tags: Arc<TargetTags>,
 ) {
     while let Some(result) = brx.recv().await {
-        let mut outbuf = result.data.as_bytes().to_owned();
-        outbuf.extend_from_slice(b"\n");
-        if let Err(_) = outfile.write(&outbuf).await {
-            continue;
+        let result_tags = tags.tags(&result.data);
+        observer::finding("discovery", &result.data);
+        events::publish(ScanEvent::RouteDiscovered {
+            url: result.data.clone(),
+            content_length: result.rs.clone(),
+            size: result.size,
+            tags: result_tags.clone(),
+            content_encoding: result.content_encoding.clone(),
+        });
+        if let Some(format) = silent {
+            let mut details = serde_json::json!({
+                "content_length": result.rs,
+                "size": result.size.size,
+                "words": result.size.words,
+                "lines": result.size.lines,
+            });
+            if !result.content_encoding.is_empty() {
+                details["content_encoding"] = serde_json::json!(result.content_encoding);
+            }
+            println!(
+                "{}",
+                format.line("discovery", &result.data, details, &result_tags)
+            );
+        }
+        if let Some(outfile) = &mut outfile {
+            let mut outbuf = result.data.as_bytes().to_owned();
+            outbuf.extend_from_slice(b"\n");
+            if let Err(e) = outfile.write(&outbuf).await {
+                tracing::warn!(error = %e, discovery = %result.data, "failed to write the output file");
+            }
         }
     }
 }
@@ -0,0 +1,34 @@
+use std::process::exit;
+
+use colored::Colorize;
+use indicatif::ProgressBar;
+use tokio_util::sync::CancellationToken;
+
+// the exit code of a scan stopped with ctrl-c, once its partial results are saved
+pub const CANCELLED_EXIT_CODE: i32 = 130;
+
+// cancels the scan of the token on the first ctrl-c: no job is dispatched
+// anymore, the workers finish the queued ones and the partial results are
+// saved and reported. the second ctrl-c exits right away.
+pub fn cancel_on_ctrl_c(pb: ProgressBar, cancel: CancellationToken) {
+    tokio::spawn(async move {
+        loop {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            if cancel.is_cancelled() {
+                exit(CANCELLED_EXIT_CODE);
+            }
+            cancel.cancel();
+            pb.suspend(|| {
+                eprintln!(
+                    "{}{}{} {}",
+                    "[".bold().white(),
+                    "WRN".bold().yellow(),
+                    "]".bold().white(),
+                    "cancelling the scan, the queued jobs finish and the results are saved, press ctrl-c again to exit now".white(),
+                )
+            });
+        }
+    });
+}
@@ -0,0 +1,746 @@
+use std::{
+    collections::HashMap,
+    fmt, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    process::exit,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_compression::tokio::bufread::{BrotliDecoder, GzipDecoder, ZlibDecoder};
+use futures::StreamExt;
+use indicatif::ProgressBar;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use reqwest::cookie::Jar;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{
+    redirect, tls, Body, Certificate, Identity, Method, Proxy, Request, RequestBuilder, Response,
+    ResponseBuilderExt,
+};
+use tokio_util::io::{ReaderStream, StreamReader};
+
+use crate::auth;
+use crate::dns;
+use crate::import::TargetHeaders;
+use crate::observer;
+use crate::scope::Scope;
+use crate::utils::{self, Similarity};
+
+// the default user agent sent with each request
+pub const DEFAULT_USER_AGENT: &str =
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:95.0) Gecko/20100101 Firefox/95.0";
+
+// the user agents we rotate through with --random-agent
+const USER_AGENTS: &[&str] = &[
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:95.0) Gecko/20100101 Firefox/95.0",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:121.0) Gecko/20100101 Firefox/121.0",
+    "Mozilla/5.0 (X11; Linux x86_64; rv:120.0) Gecko/20100101 Firefox/120.0",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.0.0 Safari/537.36",
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0",
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Safari/605.1.15",
+    "Mozilla/5.0 (iPhone; CPU iPhone OS 17_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.1 Mobile/15E148 Safari/604.1",
+    "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+];
+
+// the ClientSettings struct which will be used to configure the http clients
+// used by the detector and the bruteforcer
+#[derive(Clone, Debug)]
+pub struct ClientSettings {
+    pub timeout: usize,
+    pub http_proxy: String,
+    pub proxy_auth: String,
+    pub http3: bool,
+    pub resolve_overrides: Vec<(String, SocketAddr)>,
+    pub dns_resolver: Arc<dns::Resolver>,
+    pub address_family: dns::AddressFamily,
+    pub identity: Option<Identity>,
+    pub insecure: bool,
+    pub ca_certs: Vec<Certificate>,
+    pub tls_min: Option<tls::Version>,
+    pub tls_max: Option<tls::Version>,
+    pub user_agent: String,
+    pub random_agent: bool,
+    pub headers: Vec<(HeaderName, String)>,
+    pub cookie_jar: Arc<Jar>,
+    pub session: Arc<auth::Session>,
+    // builds the reqwest clients instead of the settings above when set
+    pub client_factory: Option<ClientFactory>,
+    // the redirects out of scope aren't followed
+    pub scope: Arc<Scope>,
+    // the headers of the targets imported from burp or a har file
+    pub target_headers: Arc<TargetHeaders>,
+    // the range of the random --delay slept before each request
+    pub delay: Option<(Duration, Duration)>,
+    // the Accept-Encoding of --accept-encoding, identity with --no-compression.
+    // gzip, deflate and brotli bodies are decompressed before they are compared
+    pub accept_encoding: Option<HeaderValue>,
+    // how the workers compare the responses, --similarity
+    pub similarity: Arc<Similarity>,
+}
+
+// the defaults of the cli flags, without proxy, custom tls or login
+impl Default for ClientSettings {
+    fn default() -> ClientSettings {
+        ClientSettings {
+            timeout: 10,
+            http_proxy: String::new(),
+            proxy_auth: String::new(),
+            http3: false,
+            resolve_overrides: vec![],
+            dns_resolver: Arc::new(dns::Resolver::new(None, dns::AddressFamily::Any)),
+            address_family: dns::AddressFamily::Any,
+            identity: None,
+            insecure: false,
+            ca_certs: vec![],
+            tls_min: None,
+            tls_max: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            random_agent: false,
+            headers: vec![],
+            cookie_jar: Arc::new(Jar::default()),
+            session: Arc::default(),
+            client_factory: None,
+            scope: Arc::default(),
+            target_headers: Arc::default(),
+            delay: None,
+            accept_encoding: None,
+            similarity: Arc::default(),
+        }
+    }
+}
+
+// the ClientFactory struct hands out the reqwest client for a max_redirects,
+// eg a pre-configured client with its own tls stack or connection pool. the
+// proxy, tls, dns, cookie and timeout settings are then up to the factory,
+// the headers, the session and --random-agent still apply on top of it.
+#[derive(Clone)]
+pub struct ClientFactory(Arc<dyn Fn(usize) -> reqwest::Client + Send + Sync>);
+
+impl ClientFactory {
+    pub fn new<F>(factory: F) -> ClientFactory
+    where
+        F: Fn(usize) -> reqwest::Client + Send + Sync + 'static,
+    {
+        ClientFactory(Arc::new(factory))
+    }
+
+    fn client(&self, max_redirects: usize) -> reqwest::Client {
+        (self.0)(max_redirects)
+    }
+}
+
+impl fmt::Debug for ClientFactory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ClientFactory")
+    }
+}
+
+// the factory behind --shared-pool, the workers of a stage share one client
+// and so one connection pool instead of opening their own.
+pub fn shared_factory(pb: &ProgressBar, settings: &ClientSettings) -> ClientFactory {
+    let pb = pb.clone();
+    let settings = settings.clone();
+    let clients: Mutex<HashMap<usize, reqwest::Client>> = Mutex::new(HashMap::new());
+    ClientFactory::new(move |max_redirects| {
+        let mut clients = clients.lock().unwrap();
+        clients
+            .entry(max_redirects)
+            .or_insert_with(|| build_reqwest_client(&pb, &settings, max_redirects, false))
+            .clone()
+    })
+}
+
+// the Client struct wraps the reqwest client so we can fall back
+// to h2/h1 when the experimental http3 transport fails
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: reqwest::Client,
+    fallback: Option<reqwest::Client>,
+    random_agent: bool,
+    headers: Vec<(HeaderName, String)>,
+    target_headers: Arc<TargetHeaders>,
+    cookie_jar: Arc<Jar>,
+    session: Arc<auth::Session>,
+    delay: Option<(Duration, Duration)>,
+    similarity: Arc<Similarity>,
+    // the connection the --auth-ntlm handshakes run on
+    ntlm: Option<Arc<NtlmConnection>>,
+}
+
+impl Client {
+    pub fn get<U: AsRef<str>>(&self, url: U) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub fn request<U: AsRef<str>>(&self, method: Method, url: U) -> RequestBuilder {
+        let url = url.as_ref();
+        let mut builder = self.inner.request(method, url);
+        // a --header replaces the session authorization, the random user agent
+        // and the browsed headers, the builder would send both values.
+        let overridden = |key: &HeaderName| self.headers.iter().any(|(header, _)| header == key);
+        let authorization = self
+            .session
+            .authorization()
+            .filter(|_| !overridden(&reqwest::header::AUTHORIZATION));
+        // the headers the target was browsed with, unless --header or the
+        // session sets them.
+        for (key, value) in self.target_headers.headers(url) {
+            if overridden(key) || (authorization.is_some() && key == reqwest::header::AUTHORIZATION)
+            {
+                continue;
+            }
+            builder = builder.header(key.clone(), value.as_str());
+        }
+        if let Some(authorization) = authorization {
+            builder = builder.header(reqwest::header::AUTHORIZATION, authorization);
+        }
+        if self.random_agent && !overridden(&reqwest::header::USER_AGENT) {
+            if let Some(user_agent) = USER_AGENTS.choose(&mut rand::thread_rng()) {
+                builder = builder.header(reqwest::header::USER_AGENT, *user_agent);
+            }
+        }
+        // placeholders are expanded per request so each one can carry its own values.
+        for (key, value) in &self.headers {
+            let value = utils::expand_placeholders(value, url);
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                builder = builder.header(key.clone(), value);
+            }
+        }
+        builder
+    }
+
+    pub fn cookie_jar(&self) -> &Jar {
+        &self.cookie_jar
+    }
+
+    pub fn similarity(&self) -> &Similarity {
+        &self.similarity
+    }
+
+    // executes the request, when the response shows the session expired the
+    // login request is replayed and the request is retried once. a retry
+    // still expired throttles the refreshes of the session.
+    pub async fn execute(&self, req: Request) -> Result<Response, reqwest::Error> {
+        if !self.session.can_refresh() {
+            return self.send_authenticated(req).await;
+        }
+
+        let generation = self.session.generation();
+        let retry_req = req.try_clone();
+        let response = self.send_authenticated(req).await?;
+        let (expired, response) = self.session.is_expired(response).await?;
+        let mut retry_req = match (expired, retry_req) {
+            (true, Some(retry_req)) => retry_req,
+            _ => return Ok(response),
+        };
+        if !self.session.refresh(self, generation).await {
+            return Ok(response);
+        }
+        if let Some(authorization) = self.session.authorization() {
+            retry_req
+                .headers_mut()
+                .insert(reqwest::header::AUTHORIZATION, authorization);
+        }
+        let response = self.send_authenticated(retry_req).await?;
+        let (expired, response) = self.session.is_expired(response).await?;
+        if expired {
+            self.session.throttle();
+        }
+        Ok(response)
+    }
+
+    // sends the request over the ntlm connection with --auth-ntlm.
+    async fn send_authenticated(&self, req: Request) -> Result<Response, reqwest::Error> {
+        match (&self.ntlm, self.session.ntlm()) {
+            (Some(ntlm), Some(credentials)) => self.execute_ntlm(req, ntlm, credentials).await,
+            _ => self.send(req).await,
+        }
+    }
+
+    // executes the request over the connection of its origin. ntlm
+    // authenticates the connection and not the request, so the requests of an
+    // origin are sent one at a time over its only pooled connection. the
+    // negotiate message goes out with the first request of the origin and
+    // again whenever the server asks for ntlm, the requests in between are
+    // sent as they are on the authenticated connection.
+    async fn execute_ntlm(
+        &self,
+        mut req: Request,
+        ntlm: &NtlmConnection,
+        credentials: &auth::ntlm::Credentials,
+    ) -> Result<Response, reqwest::Error> {
+        let connection = ntlm.connection(req.url());
+        let mut authenticated = connection.lock().await;
+        if *authenticated {
+            let retry_req = req.try_clone();
+            let response = self.dispatch(&ntlm.client, None, req).await?;
+            req = match retry_req {
+                Some(retry_req) if offers_ntlm(&response) => retry_req,
+                _ => return Ok(response),
+            };
+            // the connection was closed or its authentication dropped.
+            *authenticated = false;
+            response.bytes().await?;
+        }
+
+        let mut authenticate_req = match req.try_clone() {
+            Some(authenticate_req) => authenticate_req,
+            None => return self.dispatch(&ntlm.client, None, req).await,
+        };
+        if let Ok(negotiate) =
+            HeaderValue::from_str(&format!("NTLM {}", auth::ntlm::negotiate_message()))
+        {
+            req.headers_mut()
+                .insert(reqwest::header::AUTHORIZATION, negotiate);
+        }
+        let response = self.dispatch(&ntlm.client, None, req).await?;
+        let challenge = response
+            .headers()
+            .get_all(reqwest::header::WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(auth::ntlm::challenge_header)
+            .map(str::to_string);
+        let authenticate = match challenge
+            .map(|challenge| auth::ntlm::authenticate_message(credentials, &challenge))
+        {
+            Some(Ok(authenticate)) => authenticate,
+            Some(Err(e)) => {
+                tracing::debug!(error = %e, "ntlm handshake failed");
+                return Ok(response);
+            }
+            // the origin doesn't ask for ntlm, its requests are sent without the handshake.
+            None => {
+                *authenticated = response.status() != reqwest::StatusCode::UNAUTHORIZED;
+                return Ok(response);
+            }
+        };
+        response.bytes().await?;
+
+        if let Ok(authenticate) = HeaderValue::from_str(&format!("NTLM {}", authenticate)) {
+            authenticate_req
+                .headers_mut()
+                .insert(reqwest::header::AUTHORIZATION, authenticate);
+        }
+        let response = self.dispatch(&ntlm.client, None, authenticate_req).await?;
+        *authenticated = response.status() != reqwest::StatusCode::UNAUTHORIZED;
+        Ok(response)
+    }
+
+    // sends the request, retrying it over h2/h1 if the http3 attempt failed.
+    pub async fn send(&self, req: Request) -> Result<Response, reqwest::Error> {
+        self.dispatch(&self.inner, self.fallback.as_ref(), req)
+            .await
+    }
+
+    async fn dispatch(
+        &self,
+        client: &reqwest::Client,
+        fallback: Option<&reqwest::Client>,
+        req: Request,
+    ) -> Result<Response, reqwest::Error> {
+        // a random pause within the --delay spreads the requests out.
+        if let Some((min, max)) = self.delay {
+            let delay = rand::thread_rng().gen_range(min..=max);
+            tokio::time::sleep(delay).await;
+        }
+        let url = req.url().to_string();
+        observer::request(req.method(), &url);
+        let fallback_req = match fallback {
+            Some(_) => req.try_clone(),
+            None => None,
+        };
+        let result = match client.execute(req).await {
+            Ok(resp) => Ok(resp),
+            Err(e) => match (fallback, fallback_req) {
+                (Some(fallback), Some(req)) => fallback.execute(req).await,
+                _ => Err(e),
+            },
+        };
+        match &result {
+            Ok(resp) => observer::response(&url, resp.status()),
+            Err(e) => observer::error(&url, &e.to_string()),
+        }
+        result.map(decode)
+    }
+}
+
+// the NtlmConnection struct holds the http/1 client of a worker the ntlm
+// handshakes run on, it keeps a single connection per origin. the lock of an
+// origin holds whether its connection is authenticated.
+#[derive(Debug)]
+struct NtlmConnection {
+    client: reqwest::Client,
+    connections: Mutex<HashMap<String, Arc<tokio::sync::Mutex<bool>>>>,
+}
+
+impl NtlmConnection {
+    fn connection(&self, url: &reqwest::Url) -> Arc<tokio::sync::Mutex<bool>> {
+        let origin = url.origin().ascii_serialization();
+        self.connections
+            .lock()
+            .unwrap()
+            .entry(origin)
+            .or_default()
+            .clone()
+    }
+}
+
+// whether the response asks for ntlm authentication.
+fn offers_ntlm(response: &Response) -> bool {
+    response.status() == reqwest::StatusCode::UNAUTHORIZED
+        && response
+            .headers()
+            .get_all(reqwest::header::WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(auth::ntlm::offers_ntlm)
+}
+
+// the Content-Encoding the response was served with, empty without one.
+pub fn content_encoding(headers: &HeaderMap) -> String {
+    headers
+        .get(reqwest::header::CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_lowercase())
+        .unwrap_or_default()
+}
+
+// decompresses the body of a gzip, deflate or brotli response as it is read,
+// so the bodies negotiated with --accept-encoding are compared decoded. the
+// Content-Encoding is kept for the reports, the Content-Length is dropped as
+// it counted the compressed body. other encodings are left as served.
+fn decode(resp: Response) -> Response {
+    let encoding = content_encoding(resp.headers());
+    if !matches!(encoding.as_str(), "gzip" | "x-gzip" | "deflate" | "br") {
+        return resp;
+    }
+    let mut headers = resp.headers().clone();
+    headers.remove(reqwest::header::CONTENT_LENGTH);
+    let mut builder = http::Response::builder()
+        .status(resp.status())
+        .version(resp.version())
+        .url(resp.url().clone());
+    if let Some(builder_headers) = builder.headers_mut() {
+        *builder_headers = headers;
+    }
+    let parts = match builder.body(()) {
+        Ok(rebuilt) => rebuilt.into_parts().0,
+        Err(e) => {
+            tracing::debug!(error = %e, "failed to rebuild the response to decode it");
+            return resp;
+        }
+    };
+    let reader = StreamReader::new(
+        resp.bytes_stream()
+            .map(|chunk| chunk.map_err(io::Error::other)),
+    );
+    let body = match encoding.as_str() {
+        "br" => Body::wrap_stream(ReaderStream::new(BrotliDecoder::new(reader))),
+        "deflate" => Body::wrap_stream(ReaderStream::new(ZlibDecoder::new(reader))),
+        _ => Body::wrap_stream(ReaderStream::new(GzipDecoder::new(reader))),
+    };
+    Response::from(http::Response::from_parts(parts, body))
+}
+
+// reads the body of the response as text in its charset, see utils::decode_body.
+pub async fn text(resp: Response) -> Result<String, reqwest::Error> {
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(str::to_string);
+    let body = resp.bytes().await?;
+    Ok(utils::decode_body(content_type.as_deref(), &body))
+}
+
+// loads the client certificate used for mutual tls, either a pkcs12 bundle
+// (.p12/.pfx) or a pem certificate with its pkcs8 pem key.
+pub fn load_identity(cert_path: &str, key_path: &str, password: &str) -> Result<Identity, String> {
+    let cert = match std::fs::read(cert_path) {
+        Ok(cert) => cert,
+        Err(e) => return Err(format!("failed to read client certificate: {:?}", e)),
+    };
+    let is_pkcs12 = cert_path.ends_with(".p12") || cert_path.ends_with(".pfx");
+    let identity = if is_pkcs12 {
+        Identity::from_pkcs12_der(&cert, password)
+    } else {
+        if key_path.is_empty() {
+            return Err("--client-key is required for pem client certificates".to_string());
+        }
+        let key = match std::fs::read(key_path) {
+            Ok(key) => key,
+            Err(e) => return Err(format!("failed to read client key: {:?}", e)),
+        };
+        Identity::from_pkcs8_pem(&cert, &key)
+    };
+    let identity = match identity {
+        Ok(identity) => identity,
+        Err(e) => return Err(format!("invalid client certificate: {:?}", e)),
+    };
+
+    // building a client is what catches a key that doesn't belong to the certificate.
+    if let Err(e) = reqwest::Client::builder()
+        .identity(identity.clone())
+        .build()
+    {
+        return Err(format!("client certificate and key do not match: {:?}", e));
+    }
+    Ok(identity)
+}
+
+// loads the extra root certificates from a pem bundle, eg for corporate mitm proxies.
+pub fn load_ca_certs(ca_path: &str) -> Result<Vec<Certificate>, String> {
+    let bundle = match std::fs::read(ca_path) {
+        Ok(bundle) => bundle,
+        Err(e) => return Err(format!("failed to read ca bundle: {:?}", e)),
+    };
+    match Certificate::from_pem_bundle(&bundle) {
+        Ok(ca_certs) => Ok(ca_certs),
+        Err(e) => Err(format!("invalid ca bundle: {:?}", e)),
+    }
+}
+
+// parses a header in the Key: Value format, the value may contain colons itself
+// as well as placeholders which are expanded per request.
+pub fn parse_header(line: &str) -> Result<(HeaderName, String), String> {
+    let (key, value) = match line.split_once(':') {
+        Some((key, value)) => (key.trim(), value.trim()),
+        None => return Err(format!("invalid header, expected Key: Value: {}", line)),
+    };
+    let key = match HeaderName::from_bytes(key.as_bytes()) {
+        Ok(key) => key,
+        Err(_) => return Err(format!("invalid header name: {}", key)),
+    };
+    if HeaderValue::from_str(value).is_err() {
+        return Err(format!("invalid header value: {}", value));
+    }
+    Ok((key, value.to_string()))
+}
+
+// adds the --cookie pairs (eg a=b; c=d) to the jar for each of the targets.
+pub fn add_cookies(cookie_jar: &Jar, cookies: &str, urls: &[String]) {
+    for url in urls {
+        let url = match reqwest::Url::parse(url) {
+            Ok(url) => url,
+            Err(_) => continue,
+        };
+        for cookie in cookies.split(';') {
+            let cookie = cookie.trim();
+            if !cookie.is_empty() {
+                cookie_jar.add_cookie_str(cookie, &url);
+            }
+        }
+    }
+}
+
+// parses a --delay given as a duration or a range of them, eg 250ms, 1.5s or
+// 100-400ms. the unit of the upper bound applies to a lower bound without one,
+// a number without a unit is in milliseconds.
+pub fn parse_delay(value: &str) -> Option<(Duration, Duration)> {
+    let value = value.trim();
+    let (min, max) = value.split_once('-').unwrap_or((value, value));
+    let default_seconds = !max.ends_with("ms") && max.ends_with('s');
+    let parse = |part: &str| {
+        let part = part.trim();
+        let (number, seconds) = if let Some(number) = part.strip_suffix("ms") {
+            (number, false)
+        } else if let Some(number) = part.strip_suffix('s') {
+            (number, true)
+        } else {
+            (part, default_seconds)
+        };
+        let number = number.trim().parse::<f64>().ok()?;
+        if !number.is_finite() || number < 0.0 {
+            return None;
+        }
+        if seconds {
+            Some(Duration::from_secs_f64(number))
+        } else {
+            Some(Duration::from_secs_f64(number / 1000.0))
+        }
+    };
+    let (min, max) = (parse(min)?, parse(max)?);
+    if min > max {
+        return None;
+    }
+    Some((min, max))
+}
+
+// parses a tls version given as 1.0, 1.1 or 1.2. native-tls has no way to pin
+// tls 1.3, it is negotiated when the server supports it and no max is set.
+pub fn parse_tls_version(value: &str) -> Option<tls::Version> {
+    match value.trim() {
+        "1.0" => Some(tls::Version::TLS_1_0),
+        "1.1" => Some(tls::Version::TLS_1_1),
+        "1.2" => Some(tls::Version::TLS_1_2),
+        _ => None,
+    }
+}
+
+// builds the http client, a max_redirects of 0 disables following redirects.
+pub fn build_client(pb: &ProgressBar, settings: &ClientSettings, max_redirects: usize) -> Client {
+    let (inner, fallback) = match &settings.client_factory {
+        Some(client_factory) => (client_factory.client(max_redirects), None),
+        None if settings.http3 => (
+            build_reqwest_client(pb, settings, max_redirects, true),
+            Some(build_reqwest_client(pb, settings, max_redirects, false)),
+        ),
+        None => (
+            build_reqwest_client(pb, settings, max_redirects, false),
+            None,
+        ),
+    };
+    // the handshake authenticates a connection, so it runs on a client of its
+    // own with a single http/1 connection per origin, even with a factory.
+    let ntlm = settings.session.ntlm().map(|_| {
+        let client = reqwest_client_builder(pb, settings, max_redirects, false)
+            .http1_only()
+            .pool_max_idle_per_host(1)
+            .build()
+            .unwrap();
+        Arc::new(NtlmConnection {
+            client,
+            connections: Mutex::new(HashMap::new()),
+        })
+    });
+    Client {
+        inner,
+        fallback,
+        random_agent: settings.random_agent,
+        headers: settings.headers.clone(),
+        target_headers: settings.target_headers.clone(),
+        cookie_jar: settings.cookie_jar.clone(),
+        session: settings.session.clone(),
+        delay: settings.delay,
+        similarity: settings.similarity.clone(),
+        ntlm,
+    }
+}
+
+fn build_reqwest_client(
+    pb: &ProgressBar,
+    settings: &ClientSettings,
+    max_redirects: usize,
+    http3: bool,
+) -> reqwest::Client {
+    reqwest_client_builder(pb, settings, max_redirects, http3)
+        .build()
+        .unwrap()
+}
+
+fn reqwest_client_builder(
+    pb: &ProgressBar,
+    settings: &ClientSettings,
+    max_redirects: usize,
+    http3: bool,
+) -> reqwest::ClientBuilder {
+    let mut headers = reqwest::header::HeaderMap::new();
+    let user_agent = match reqwest::header::HeaderValue::from_str(&settings.user_agent) {
+        Ok(user_agent) => user_agent,
+        Err(e) => {
+            pb.println(format!("Invalid user agent, err: {:?}", e));
+            exit(1);
+        }
+    };
+    headers.insert(reqwest::header::USER_AGENT, user_agent);
+    // without it the server may pick any encoding, --header still overrides it.
+    if let Some(accept_encoding) = &settings.accept_encoding {
+        headers.insert(reqwest::header::ACCEPT_ENCODING, accept_encoding.clone());
+    }
+
+    let redirect_policy = if max_redirects == 0 {
+        redirect::Policy::none()
+    } else if settings.scope.is_empty() {
+        redirect::Policy::limited(max_redirects)
+    } else {
+        let scope = settings.scope.clone();
+        redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= max_redirects {
+                attempt.error("too many redirects")
+            } else if !scope.allows(attempt.url().as_str()) {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        })
+    };
+
+    let mut builder = reqwest::Client::builder()
+        .default_headers(headers)
+        .redirect(redirect_policy)
+        .timeout(Duration::from_secs(settings.timeout.try_into().unwrap()));
+
+    // certificates are verified unless --insecure is given
+    if settings.insecure {
+        builder = builder
+            .danger_accept_invalid_hostnames(true)
+            .danger_accept_invalid_certs(true);
+    }
+    for ca_cert in &settings.ca_certs {
+        builder = builder.add_root_certificate(ca_cert.clone());
+    }
+
+    if !settings.http_proxy.is_empty() {
+        let mut proxy = match Proxy::all(&settings.http_proxy) {
+            Ok(proxy) => proxy,
+            Err(e) => {
+                pb.println(format!("Could not setup proxy, err: {:?}", e));
+                exit(1);
+            }
+        };
+        // credentials inside the proxy url are picked up by reqwest,
+        // --proxy-auth takes precedence over them.
+        if !settings.proxy_auth.is_empty() {
+            let (username, password) = match settings.proxy_auth.split_once(':') {
+                Some((username, password)) => (username, password),
+                None => (settings.proxy_auth.as_str(), ""),
+            };
+            proxy = proxy.basic_auth(username, password);
+        }
+        builder = builder.proxy(proxy);
+    }
+
+    builder = builder.dns_resolver(settings.dns_resolver.clone());
+    for (host, addr) in &settings.resolve_overrides {
+        builder = builder.resolve(host, *addr);
+    }
+    // the jar is shared by every client so sessions survive across the stages.
+    builder = builder.cookie_provider(settings.cookie_jar.clone());
+    // the peer certificate is kept on the responses for the fingerprinting
+    builder = builder.tls_info(true);
+
+    if let Some(identity) = &settings.identity {
+        builder = builder.identity(identity.clone());
+    }
+    // binding to the unspecified address of a family also rules out ip literals of the other.
+    match settings.address_family {
+        dns::AddressFamily::Any => (),
+        dns::AddressFamily::Ipv4 => {
+            builder = builder.local_address(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        }
+        dns::AddressFamily::Ipv6 => {
+            builder = builder.local_address(IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+        }
+    }
+
+    // quic is only available over rustls in reqwest, which can't speak the legacy
+    // tls versions, so the version bounds only apply to the h2/h1 client.
+    #[cfg(feature = "http3")]
+    if http3 {
+        builder = builder.use_rustls_tls().http3_prior_knowledge();
+    }
+    if !http3 {
+        if let Some(tls_min) = settings.tls_min {
+            builder = builder.min_tls_version(tls_min);
+        }
+        if let Some(tls_max) = settings.tls_max {
+            builder = builder.max_tls_version(tls_max);
+        }
+    }
+
+    builder
+}
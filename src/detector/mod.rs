@@ -1,20 +1,256 @@
-use std::{error::Error, process::exit, str::FromStr, time::Duration};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::sync::{Arc, Mutex};
 
 use colored::Colorize;
 use governor::{Quota, RateLimiter};
 use indicatif::ProgressBar;
 use itertools::iproduct;
-use regex::Regex;
-use reqwest::{redirect, Proxy};
+use rand::Rng;
+use reqwest::{
+    header::{HeaderMap, CONTENT_TYPE},
+    Method, RequestBuilder, StatusCode,
+};
 use tokio::{fs::File, io::AsyncWriteExt, sync::mpsc};
+use tokio_util::sync::CancellationToken;
+
+use crate::auth::{RawRequest, PAYLOAD_MARKER, WORD_MARKER};
+use crate::client::{self, Client, ClientSettings};
+use crate::events::{self, ScanEvent};
+use crate::fingerprint::{self, Fingerprints, Tech};
+use crate::html::{self, Token};
+use crate::import;
+use crate::notify::{Finding, FindingKind, Notifier};
+use crate::observer;
+use crate::plugin::{self, Inspection, PluginInstance};
+use crate::scope::Scope;
+use crate::script::{Script, ScriptRequest};
+use crate::tags::TargetTags;
+use crate::transform::{FamilyStats, Outcome, SEGMENT_SUFFIXES, TOMCAT_FAMILY, TOMCAT_PAYLOADS};
+use crate::utils;
+use crate::waf::{self, Escalation};
+use crate::wordlist::WordLearner;
+
+// the headers some reverse proxies route on instead of the request path
+const REWRITE_HEADERS: [&str; 3] = ["X-Original-URL", "X-Rewrite-URL", "X-Forwarded-Path"];
+
+// the headers some access rules trust for the client address
+const SPOOF_IP_HEADERS: [&str; 4] = [
+    "X-Forwarded-For",
+    "X-Real-IP",
+    "X-Client-IP",
+    "True-Client-IP",
+];
+const SPOOF_IP_VALUES: [&str; 2] = ["127.0.0.1", "localhost"];
+
+// the methods we ask the backend to override the request method with
+const OVERRIDE_METHODS: [&str; 4] = ["GET", "POST", "PUT", "DELETE"];
+
+// the tomcat manager apps probed behind the `..;/` traversal
+const TOMCAT_MANAGER_PATHS: [&str; 2] = ["/manager/html", "/host-manager/html"];
+
+// the spring boot actuators probed behind an internal doc root
+const SPRING_ACTUATOR_PATHS: [&str; 3] = ["/actuator", "/actuator/env", "/actuator/heapdump"];
+
+// the characters allowed in the guessed part of an 8.3 short name
+const IIS_SHORTNAME_CHARS: &str = "abcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// the static looking suffixes appended to an internal route to trick caches,
+// {} is replaced by a random name so every probe starts with a cold cache
+const CACHE_DECEPTION_SUFFIXES: [&str; 4] = ["/{}.css", "/{}.js", ";{}.css", "%2f{}.css"];
 
 // the Job struct which will be used to define our settings for the detection jobs
 #[derive(Clone, Debug)]
 pub struct JobSettings {
-    int_status: String,
-    pub_status: String,
-    drop_after_fail: String,
-    skip_validation: bool,
+    pub int_status: String,
+    pub pub_status: String,
+    pub drop_after_fail: String,
+    pub skip_validation: bool,
+    pub rewrite_headers: bool,
+    pub spoof_ip: bool,
+    pub method_override: bool,
+    pub body: String,
+    pub content_type: String,
+    pub inject_at: InjectAt,
+    pub traversal_strategy: TraversalStrategy,
+    pub cache_deception: bool,
+    // the segment_suffix family was selected, the 401/403 targets get their
+    // own segments suffixed
+    pub segment_suffixes: bool,
+    pub nginx_alias: bool,
+    pub iis_shortname: bool,
+    pub fingerprints: Arc<Fingerprints>,
+    pub escalation: Arc<Escalation>,
+    pub family_stats: Arc<FamilyStats>,
+    pub word_learner: Option<Arc<WordLearner>>,
+    pub notifier: Arc<Notifier>,
+    pub shard: Option<Shard>,
+    pub plugins: Arc<Vec<plugin::Plugin>>,
+    pub script: Option<Arc<Script>>,
+    // the headers of the --raw-request the job was anchored on and the ones
+    // the --script on_request hook added to the job
+    pub script_headers: Vec<(String, String)>,
+    pub scope: Arc<Scope>,
+    pub tags: Arc<TargetTags>,
+    // the --raw-request anchors by url
+    pub raw_requests: Arc<HashMap<String, Vec<RawRequest>>>,
+    // the methods of the --routes by url
+    pub route_methods: Arc<HashMap<String, Vec<Method>>>,
+    // the jobs of a target are dropped once its internal doc root is confirmed
+    pub stop_on_root: bool,
+    pub confirmed_roots: Arc<ConfirmedRoots>,
+    // no job is dispatched anymore once the scan is cancelled
+    pub cancel: CancellationToken,
+}
+
+// the ConfirmedRoots struct holds the targets whose internal doc root was
+// confirmed, shared by send_url and the workers so the remaining jobs of such
+// a target are neither rate limited nor sent
+#[derive(Debug, Default)]
+pub struct ConfirmedRoots {
+    urls: Mutex<HashSet<String>>,
+}
+
+impl ConfirmedRoots {
+    pub fn confirm(&self, url: &str) {
+        self.urls.lock().unwrap().insert(url.to_string());
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.urls.lock().unwrap().contains(url)
+    }
+}
+
+impl JobSettings {
+    // whether the jobs of the target are dropped, its doc root was confirmed.
+    pub fn root_confirmed(&self, url: &str) -> bool {
+        self.stop_on_root && self.confirmed_roots.contains(url)
+    }
+}
+
+// the defaults of the cli flags, the state shared with the workers starts empty
+impl Default for JobSettings {
+    fn default() -> JobSettings {
+        JobSettings {
+            int_status: "404,500".to_string(),
+            pub_status: "400".to_string(),
+            drop_after_fail: "302,301".to_string(),
+            skip_validation: false,
+            rewrite_headers: false,
+            spoof_ip: false,
+            method_override: false,
+            body: String::new(),
+            content_type: "application/x-www-form-urlencoded".to_string(),
+            inject_at: InjectAt::End,
+            traversal_strategy: TraversalStrategy::Linear,
+            cache_deception: false,
+            segment_suffixes: false,
+            nginx_alias: false,
+            iis_shortname: false,
+            fingerprints: Arc::default(),
+            escalation: Arc::default(),
+            family_stats: Arc::default(),
+            word_learner: None,
+            notifier: Arc::new(Notifier::new()),
+            shard: None,
+            plugins: Arc::default(),
+            script: None,
+            script_headers: vec![],
+            scope: Arc::default(),
+            tags: Arc::default(),
+            raw_requests: Arc::default(),
+            route_methods: Arc::default(),
+            stop_on_root: false,
+            confirmed_roots: Arc::default(),
+            cancel: CancellationToken::new(),
+        }
+    }
+}
+
+// the Shard struct selects the part of the url x payload x word jobs a machine
+// sends with --shard, the shards of a scan never overlap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shard {
+    // starts at 1
+    pub index: u64,
+    pub total: u64,
+}
+
+impl Shard {
+    // parses the --shard value, eg `2/5` for the second of five shards.
+    pub fn parse(value: &str) -> Option<Shard> {
+        let (index, total) = value.split_once('/')?;
+        let index = index.trim().parse::<u64>().ok()?;
+        let total = total.trim().parse::<u64>().ok()?;
+        if index == 0 || index > total {
+            return None;
+        }
+        Some(Shard { index, total })
+    }
+
+    // whether the job of the url, payload and word belongs to the shard. the
+    // fnv-1a hash is the same on every machine and release, unlike the std hasher.
+    pub fn owns(&self, url: &str, payload: &str, word: &str) -> bool {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for part in [url, payload, word] {
+            for byte in part.bytes().chain(std::iter::once(0)) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        }
+        hash % self.total == self.index - 1
+    }
+}
+
+// whether the job is sent by this machine, every job is without --shard.
+pub fn owns_job(job_settings: &JobSettings, url: &str, payload: &str, word: &str) -> bool {
+    match job_settings.shard {
+        Some(shard) => shard.owns(url, payload, word),
+        None => true,
+    }
+}
+
+// the InjectAt enum selects after which path segments the traversal is inserted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InjectAt {
+    End,
+    EachSegment,
+    Index(usize),
+}
+
+impl InjectAt {
+    // parses the --inject-at value: end, each-segment or index:N.
+    pub fn parse(value: &str) -> Option<InjectAt> {
+        match value {
+            "end" => Some(InjectAt::End),
+            "each-segment" => Some(InjectAt::EachSegment),
+            _ => {
+                let index = value.strip_prefix("index:")?.parse::<usize>().ok()?;
+                Some(InjectAt::Index(index))
+            }
+        }
+    }
+}
+
+// the TraversalStrategy enum selects how the depth of the traversals is found
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraversalStrategy {
+    // every depth from one payload to the length of the path plus 5
+    Linear,
+    // a binary search for the first depth answered with the --pub-status, the
+    // internal doc root is then only checked behind it
+    Smart,
+}
+
+impl TraversalStrategy {
+    // parses the --traversal-strategy value: linear or smart.
+    pub fn parse(value: &str) -> Option<TraversalStrategy> {
+        match value {
+            "linear" => Some(TraversalStrategy::Linear),
+            "smart" => Some(TraversalStrategy::Smart),
+            _ => None,
+        }
+    }
 }
 
 // the Job struct will be used as jobs for the detection phase
@@ -22,148 +258,511 @@ pub struct JobSettings {
 pub struct Job {
     settings: Option<JobSettings>,
     url: Option<String>,
+    method: Option<Method>,
     word: Option<String>,
     payload: Option<String>,
-    header: Option<String>,
+    param: Option<String>,
+    position: Option<usize>,
+    url_stages: bool,
 }
 
 // the JobResult struct which will be used as jobs
 // to save the data to a file
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct JobResult {
     pub data: String,
+    // the payload family and the status of the internal doc root
+    pub family: String,
+    pub status: String,
+    pub meta: JobResultMeta,
+    // what the follow up stages found behind the internal doc root
+    pub follow_ups: Vec<Finding>,
+}
+
+// the JobResultMeta struct holds what the page of the internal doc root says
+// about itself, the triage shows it next to the finding
+#[derive(Clone, Debug, Default)]
+pub struct JobResultMeta {
+    pub title: String,
+    pub h1: String,
+    // the content of the <meta name="generator"> tag, eg the cms and its version
+    pub generator: String,
+}
+
+impl JobResultMeta {
+    // reads the first <title> and <h1> of the page and its generator, the
+    // text of the elements inside them is kept and the whitespace collapsed.
+    pub fn extract(content: &str) -> JobResultMeta {
+        let mut meta = JobResultMeta::default();
+        // the element being read, title or h1, and its text so far.
+        let mut reading: Option<(String, String)> = None;
+        for token in html::Tokenizer::new(content) {
+            match token {
+                Token::Start { name, attributes } => match name.as_str() {
+                    "title" | "h1" if reading.is_none() && meta.field(&name).is_empty() => {
+                        reading = Some((name, String::new()));
+                    }
+                    "meta" if meta.generator.is_empty() => {
+                        let generator = attributes.iter().any(|(attribute, value)| {
+                            attribute == "name" && value.trim().eq_ignore_ascii_case("generator")
+                        });
+                        if let Some((_, content)) = attributes
+                            .iter()
+                            .find(|(attribute, _)| generator && attribute == "content")
+                        {
+                            meta.generator = html::collapse_whitespace(content);
+                        }
+                    }
+                    _ => {}
+                },
+                Token::Text(text) => {
+                    if let Some((_, read)) = &mut reading {
+                        read.push_str(text);
+                    }
+                }
+                Token::End(name) => {
+                    if reading
+                        .as_ref()
+                        .is_some_and(|(element, _)| *element == name)
+                    {
+                        if let Some((element, read)) = reading.take() {
+                            meta.set(&element, &read);
+                        }
+                    }
+                }
+            }
+            if !meta.title.is_empty() && !meta.h1.is_empty() && !meta.generator.is_empty() {
+                break;
+            }
+        }
+        // an element left open runs to the end of the page.
+        if let Some((element, read)) = reading {
+            meta.set(&element, &read);
+        }
+        meta
+    }
+
+    fn field(&self, element: &str) -> &str {
+        if element == "title" {
+            &self.title
+        } else {
+            &self.h1
+        }
+    }
+
+    fn set(&mut self, element: &str, text: &str) {
+        let text = html::collapse_whitespace(&html::decode(text));
+        if element == "title" {
+            self.title = text;
+        } else {
+            self.h1 = text;
+        }
+    }
+
+    // the meta as `key: value` pairs for the triage, the empty ones are left out.
+    pub fn summary(&self) -> String {
+        [
+            ("title", &self.title),
+            ("h1", &self.h1),
+            ("generator", &self.generator),
+        ]
+        .iter()
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(key, value)| format!("{}: {}", key, value))
+        .collect::<Vec<String>>()
+        .join(", ")
+    }
 }
 
 // this asynchronous function will send the url as jobs to all the workers
 // each worker will perform tests to detect path normalization misconfigurations.
+// the urls are sent as they arrive until the channel is closed.
 pub async fn send_url(
     mut tx: spmc::Sender<Job>,
-    urls: Vec<String>,
+    mut urls: mpsc::UnboundedReceiver<String>,
     payloads: Vec<String>,
+    methods: Vec<Method>,
     wordlists: Vec<String>,
     rate: u32,
-    int_status: String,
-    pub_status: String,
-    drop_after_fail: String,
-    skip_validation: bool,
-    header: String,
+    job_settings: JobSettings,
 ) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
     //set rate limit
     let lim = RateLimiter::direct(Quota::per_second(std::num::NonZeroU32::new(rate).unwrap()));
 
-    // the job settings
-    let job_settings = JobSettings {
-        int_status: int_status.to_string(),
-        pub_status: pub_status.to_string(),
-        drop_after_fail: drop_after_fail,
-        skip_validation: skip_validation,
-    };
+    loop {
+        let url = tokio::select! {
+            url = urls.recv() => url,
+            _ = job_settings.cancel.cancelled() => None,
+        };
+        let url = match url {
+            Some(url) => url,
+            None => break,
+        };
+        // the payloads the profile of the target rules out are never sent.
+        let mut url_payloads = allowed_payloads(&job_settings, &url, &payloads);
+        // the stages that only depend on the url run with the first job of each url.
+        // the first payload is taken before the reordering below, which depends on
+        // the stats of this machine, so exactly one shard owns the url stages.
+        let first_payload = url_payloads.first().cloned();
+        // the families that already bypassed the target go first.
+        if let Ok(parsed) = reqwest::Url::parse(&url) {
+            let host = parsed.host_str().unwrap_or("");
+            job_settings
+                .family_stats
+                .prioritize(host, &mut url_payloads);
+        }
 
-    println!("{}", header);
+        let methods = url_methods(&job_settings, &url, &methods);
+        let first_method = methods.first().cloned();
+        let first_word = wordlists.first().cloned();
 
-    if skip_validation {
-        // send the jobs
-        for (url, payload, word) in iproduct!(urls, payloads, wordlists) {
-            let msg = Job {
-                settings: Some(job_settings.clone()),
-                url: Some(url.clone()),
-                word: Some(word.clone()),
-                payload: Some(payload.clone()),
-                header: Some(header.clone()),
-            };
-            if let Err(_) = tx.send(msg) {
-                continue;
+        if job_settings.skip_validation {
+            // send the jobs
+            for (payload, method, word) in iproduct!(&url_payloads, &methods, &wordlists) {
+                if job_settings.cancel.is_cancelled() {
+                    return Ok(());
+                }
+                if !owns_job(&job_settings, &url, payload, word) {
+                    continue;
+                }
+                for position in url_injection_points(&job_settings, &url) {
+                    let msg = Job {
+                        settings: Some(job_settings.clone()),
+                        url: Some(url.clone()),
+                        method: Some(method.clone()),
+                        word: Some(word.clone()),
+                        payload: Some(payload.clone()),
+                        param: None,
+                        position,
+                        url_stages: position.is_none()
+                            && first_payload.as_ref() == Some(payload)
+                            && first_method.as_ref() == Some(method)
+                            && first_word.as_ref() == Some(word),
+                    };
+                    if let Err(_) = tx.send(msg) {
+                        continue;
+                    }
+                    // the workers drop the jobs of a confirmed target without
+                    // sending them, they don't wait for the rate limit.
+                    if !job_settings.root_confirmed(&url) {
+                        lim.until_ready().await;
+                    }
+                }
             }
-            lim.until_ready().await;
-        }
-    } else {
-        // send the jobs
-        for (url, payload) in iproduct!(urls, payloads) {
-            let msg = Job {
-                settings: Some(job_settings.clone()),
-                url: Some(url.clone()),
-                word: Some("".to_string()),
-                payload: Some(payload.clone()),
-                header: Some(header.clone()),
-            };
-            if let Err(_) = tx.send(msg) {
-                continue;
+        } else {
+            // send the jobs
+            for (payload, method) in iproduct!(&url_payloads, &methods) {
+                if job_settings.cancel.is_cancelled() {
+                    return Ok(());
+                }
+                if !owns_job(&job_settings, &url, payload, "") {
+                    continue;
+                }
+                // a job for each injection point in the path followed by
+                // a job for each query parameter of the url
+                let positions = url_injection_points(&job_settings, &url)
+                    .into_iter()
+                    .map(|position| (None, position));
+                let params = query_params(&url)
+                    .into_iter()
+                    .map(|param| (Some(param), None));
+                for (param, position) in positions.chain(params) {
+                    let url_stages = position.is_none()
+                        && param.is_none()
+                        && first_payload.as_ref() == Some(payload)
+                        && first_method.as_ref() == Some(method);
+                    let msg = Job {
+                        settings: Some(job_settings.clone()),
+                        url: Some(url.clone()),
+                        method: Some(method.clone()),
+                        word: Some("".to_string()),
+                        payload: Some(payload.clone()),
+                        param,
+                        position,
+                        url_stages,
+                    };
+                    if let Err(_) = tx.send(msg) {
+                        continue;
+                    }
+                    // the workers drop the jobs of a confirmed target without
+                    // sending them, they don't wait for the rate limit.
+                    if !job_settings.root_confirmed(&url) {
+                        lim.until_ready().await;
+                    }
+                }
             }
-            lim.until_ready().await;
         }
     }
     Ok(())
 }
 
+// returns the payloads worth sending to the url according to its profile, the
+// targets fingerprinted as tomcat get the `..;/` family first.
+pub fn allowed_payloads(job_settings: &JobSettings, url: &str, payloads: &[String]) -> Vec<String> {
+    let fingerprint = match job_settings.fingerprints.get(url) {
+        Some(fingerprint) => fingerprint,
+        None => return payloads.to_vec(),
+    };
+    let tomcat: Vec<String> = match fingerprint.tech {
+        Some(Tech::Tomcat) => TOMCAT_PAYLOADS
+            .iter()
+            .map(|payload| payload.to_string())
+            .collect(),
+        _ => vec![],
+    };
+    let rest = payloads.iter().filter(|payload| !tomcat.contains(payload));
+    tomcat
+        .iter()
+        .chain(rest)
+        .filter(|payload| fingerprint.allows(payload))
+        .cloned()
+        .collect()
+}
+
+// returns the methods the traversals of the url are sent with, the --raw-request
+// anchors keep the methods they were captured with and the --routes the methods
+// of their route.
+pub fn url_methods(job_settings: &JobSettings, url: &str, methods: &[Method]) -> Vec<Method> {
+    if let Some(raw_requests) = job_settings.raw_requests.get(url) {
+        return raw_requests
+            .iter()
+            .map(|raw_request| raw_request.method.clone())
+            .collect();
+    }
+    match job_settings.route_methods.get(url) {
+        Some(route_methods) if !route_methods.is_empty() => route_methods.clone(),
+        _ => methods.to_vec(),
+    }
+}
+
+// returns the injection points of the url, a --raw-request anchor with a
+// §PAYLOAD§ in its path is only injected there.
+pub fn url_injection_points(job_settings: &JobSettings, url: &str) -> Vec<Option<usize>> {
+    let anchored = job_settings
+        .raw_requests
+        .get(url)
+        .is_some_and(|raw_requests| {
+            raw_requests
+                .iter()
+                .any(|raw_request| raw_request.injection.is_some())
+        });
+    if anchored {
+        return vec![None];
+    }
+    injection_points(url, job_settings.inject_at)
+}
+
+// the jobs of a --raw-request anchor carry its headers and body, its content
+// type replaces the --content-type and its §WORD§ markers are replaced by the
+// word, which is then only sent there. returns the url around the §PAYLOAD§ of
+// its path.
+fn anchor_job(
+    job_settings: &mut JobSettings,
+    url: &str,
+    method: &Method,
+    word: &mut String,
+) -> Option<(String, String)> {
+    let raw_request = job_settings
+        .raw_requests
+        .get(url)
+        .and_then(|raw_requests| raw_requests.iter().find(|raw| raw.method == method))?
+        .clone();
+    let mut word_placed = raw_request.body.contains(WORD_MARKER);
+    job_settings.body = raw_request.body.replace(WORD_MARKER, word);
+    job_settings.content_type = String::new();
+    for (name, value) in raw_request.headers {
+        let value = String::from_utf8_lossy(value.as_bytes()).to_string();
+        word_placed |= value.contains(WORD_MARKER);
+        let value = value.replace(WORD_MARKER, word);
+        if name == CONTENT_TYPE {
+            job_settings.content_type = value;
+        } else if import::replayed(&name) {
+            job_settings.script_headers.push((name.to_string(), value));
+        }
+    }
+    let injection = raw_request.injection.map(|(base, suffix)| {
+        word_placed |= base.contains(WORD_MARKER) || suffix.contains(WORD_MARKER);
+        (
+            base.replace(WORD_MARKER, word),
+            suffix.replace(WORD_MARKER, word),
+        )
+    });
+    if word_placed {
+        word.clear();
+    }
+    injection
+}
+
+// returns the most traversal requests the jobs of the url can send: each job goes
+// back one more level per request, and the skip validation jobs also fetch the title.
+pub fn max_traversal_requests(
+    url: &str,
+    inject_at: InjectAt,
+    skip_validation: bool,
+    payloads: usize,
+    methods: usize,
+    words: usize,
+) -> usize {
+    let depth = match reqwest::Url::parse(url) {
+        Ok(parsed) => parsed.path().split('/').count() + 5,
+        Err(_) => return 0,
+    };
+    let positions = injection_points(url, inject_at).len();
+    if skip_validation {
+        positions * payloads * methods * words * depth * 2
+    } else {
+        (positions + query_params(url).len()) * payloads * methods * depth
+    }
+}
+
+// returns the amount of leading path segments to keep in front of the traversal
+// for each injection point, None injects at the end of the url.
+pub fn injection_points(url: &str, inject_at: InjectAt) -> Vec<Option<usize>> {
+    let segments = match reqwest::Url::parse(url) {
+        Ok(url) => url.path().split('/').filter(|s| !s.is_empty()).count(),
+        Err(_) => 0,
+    };
+    match inject_at {
+        InjectAt::End => vec![None],
+        InjectAt::EachSegment => {
+            let mut points: Vec<Option<usize>> = (0..segments).map(Some).collect();
+            points.push(None);
+            points
+        }
+        InjectAt::Index(index) if index < segments => vec![Some(index)],
+        InjectAt::Index(_) => vec![None],
+    }
+}
+
+// splits the url at the injection point into the part in front of the traversal
+// and the remaining path (and query) that follows it.
+fn split_at_injection_point(
+    raw_url: &str,
+    url: &reqwest::Url,
+    position: Option<usize>,
+) -> (String, String) {
+    let position = match position {
+        Some(position) => position,
+        None => return (raw_url.to_string(), "".to_string()),
+    };
+    let segments: Vec<&str> = url.path().split('/').filter(|s| !s.is_empty()).collect();
+    let mut base = url.clone();
+    base.set_path(&segments[..position].join("/"));
+    base.set_query(None);
+    let mut rest = segments[position..].join("/");
+    if url.path().ends_with('/') {
+        rest.push('/');
+    }
+    if let Some(query) = url.query() {
+        rest.push('?');
+        rest.push_str(query);
+    }
+    (base.to_string(), rest)
+}
+
+// returns the unique names of the query parameters in the url.
+pub fn query_params(url: &str) -> Vec<String> {
+    let url = match reqwest::Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return vec![],
+    };
+    let mut params = vec![];
+    for (name, _) in url.query_pairs() {
+        let name = name.into_owned();
+        if !name.is_empty() && !params.contains(&name) {
+            params.push(name);
+        }
+    }
+    params
+}
+
 // this function will test for path normalization vulnerabilities
 pub async fn run_tester(
     pb: ProgressBar,
     rx: spmc::Receiver<Job>,
     tx: mpsc::Sender<JobResult>,
-    timeout: usize,
-    http_proxy: String,
+    client_settings: ClientSettings,
 ) -> JobResult {
-    let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert(
-        reqwest::header::USER_AGENT,
-        reqwest::header::HeaderValue::from_static(
-            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:95.0) Gecko/20100101 Firefox/95.0",
-        ),
-    );
-
-    let client;
-    if http_proxy.is_empty() {
-        //no certs
-        client = reqwest::Client::builder()
-            .default_headers(headers)
-            .redirect(redirect::Policy::limited(10))
-            .timeout(Duration::from_secs(timeout.try_into().unwrap()))
-            .danger_accept_invalid_hostnames(true)
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap();
-    } else {
-        let proxy = match Proxy::all(http_proxy) {
-            Ok(proxy) => proxy,
-            Err(e) => {
-                pb.println(format!("Could not setup proxy, err: {:?}", e));
-                exit(1);
-            }
-        };
-        //no certs
-        client = reqwest::Client::builder()
-            .default_headers(headers)
-            .redirect(redirect::Policy::limited(10))
-            .timeout(Duration::from_secs(timeout.try_into().unwrap()))
-            .danger_accept_invalid_hostnames(true)
-            .danger_accept_invalid_certs(true)
-            .proxy(proxy)
-            .build()
-            .unwrap();
-    }
+    let client = client::build_client(&pb, &client_settings, 10);
+    // the --plugin instances of the worker, created with its first job.
+    let mut plugins: Option<Vec<PluginInstance>> = None;
+    // what the stages of the urls found, reported with the result of the worker.
+    let mut url_findings: Vec<Finding> = vec![];
 
     while let Ok(job) = rx.recv() {
         let job_url = job.url.unwrap();
         let job_payload = job.payload.unwrap();
-        let job_settings = job.settings.unwrap();
+        let mut job_settings = job.settings.unwrap();
+        // the other jobs of the target are redundant once its doc root is confirmed.
+        if job_settings.root_confirmed(&job_url) {
+            pb.inc(1);
+            continue;
+        }
+        let job_method = job.method.unwrap_or(Method::GET);
         let job_url_new = job_url.clone();
-        let job_payload_new = job_payload.clone();
 
-        let job_header = match job.header {
-            Some(job_header) => job_header,
-            None => "".to_owned(),
-        };
-        let job_word = match job.word {
+        let mut job_word = match job.word {
             Some(job_word) => job_word,
             None => "".to_string(),
         };
 
         let url = match reqwest::Url::parse(&job_url_new) {
             Ok(url) => url,
-            Err(_) => {
+            Err(e) => {
+                tracing::debug!(error = %e, "invalid url");
                 continue;
             }
         };
+
+        // a target that keeps blocking the payload gets it escalated, past the
+        // last level the payload is dropped for the target.
+        let waf_host = url.host_str().unwrap_or("").to_string();
+        let base_payload = job_payload.clone();
+        let level = job_settings.escalation.level(&waf_host, &base_payload);
+        let (job_payload, job_payload_new) = match waf::escalate(&base_payload, level) {
+            Some(escalated) => (escalated.clone(), escalated),
+            None => {
+                pb.inc(1);
+                continue;
+            }
+        };
+
+        let anchored_injection =
+            anchor_job(&mut job_settings, &job_url, &job_method, &mut job_word);
+
+        // the --script decides whether the job is sent and the headers it is sent with.
+        if let Some(script) = &job_settings.script {
+            match script.on_request(&job_method, &job_url, &job_payload_new) {
+                ScriptRequest::Send(headers) => job_settings.script_headers.extend(headers),
+                ScriptRequest::Skip => {
+                    pb.inc(1);
+                    continue;
+                }
+            }
+        }
+
+        let (injection_base, injection_suffix) = match anchored_injection {
+            Some(injection) => injection,
+            None => split_at_injection_point(&job_url, &url, job.position),
+        };
+
+        if let Some(param) = job.param {
+            url_findings.extend(
+                test_query_param(
+                    &pb,
+                    &client,
+                    &job_settings,
+                    &job_method,
+                    &url,
+                    &param,
+                    &job_payload,
+                )
+                .await,
+            );
+            pb.inc(1);
+            continue;
+        }
+
         let mut job_url_with_path: String = String::from("");
         let mut job_url_without_path: String = String::from("");
         let schema = url.scheme().to_string();
@@ -182,11 +781,80 @@ pub async fn run_tester(
         job_url_without_path.push_str(&host);
         job_url_without_path.push_str("/");
 
+        // the rewrite stage only depends on the url and payload, so it runs for the end position.
+        if job.position.is_none() && job_settings.rewrite_headers {
+            url_findings.extend(
+                test_rewrite_headers(&pb, &client, &job_settings, &url, &job_payload_new).await,
+            );
+        }
+
+        if job.url_stages && job_settings.nginx_alias {
+            url_findings.extend(test_nginx_alias(&pb, &client, &job_settings, &url).await);
+        }
+
+        // the response of the target decides which bypass stages apply.
+        let target_resp = match (job.url_stages, client.get(&job_url).build()) {
+            (true, Ok(req)) => client.execute(req).await.ok(),
+            _ => None,
+        };
+        if let Some(target_resp) = target_resp {
+            let target_status = target_resp.status();
+            if let Some(gateway) = Gateway::fingerprint(target_resp.headers()) {
+                url_findings.extend(test_gateway(&pb, &client, &job_settings, &url, gateway).await);
+            }
+            if is_tomcat(target_resp).await {
+                url_findings.extend(test_tomcat(&pb, &client, &job_settings, &url).await);
+            }
+            if job_settings.spoof_ip && target_status == StatusCode::FORBIDDEN {
+                url_findings.extend(
+                    test_spoof_ip(&pb, &client, &job_settings, &job_url, target_status).await,
+                );
+            }
+            if job_settings.method_override
+                && (target_status == StatusCode::FORBIDDEN
+                    || target_status == StatusCode::METHOD_NOT_ALLOWED)
+            {
+                url_findings.extend(
+                    test_method_override(&pb, &client, &job_settings, &url, target_status).await,
+                );
+            }
+            if job_settings.segment_suffixes
+                && (target_status == StatusCode::FORBIDDEN
+                    || target_status == StatusCode::UNAUTHORIZED)
+            {
+                url_findings.extend(
+                    test_segment_suffixes(&pb, &client, &job_settings, &url, target_status).await,
+                );
+            }
+        }
+
         let path_cnt = path.split("/").count() + 5;
         let mut payload = String::from(job_payload);
-        let new_url = String::from(&job_url);
+        let new_url = injection_base.clone();
         let mut track_status_codes = 0;
-        for _ in 0..path_cnt {
+        let mut depths = 0..path_cnt;
+        if job_settings.traversal_strategy == TraversalStrategy::Smart
+            && !job_settings.skip_validation
+        {
+            let flip = flip_depth(
+                &client,
+                &job_settings,
+                &job_method,
+                &injection_base,
+                &job_payload_new,
+                &injection_suffix,
+                path_cnt,
+            )
+            .await;
+            match flip {
+                Some(depth) => {
+                    payload = job_payload_new.repeat(depth);
+                    depths = depth - 1..depth;
+                }
+                None => depths = 0..0,
+            }
+        }
+        for _ in depths {
             let mut new_url = new_url.clone();
             if !new_url.as_str().ends_with("/") {
                 new_url.push_str("/");
@@ -195,6 +863,12 @@ pub async fn run_tester(
             if job_settings.skip_validation {
                 new_url.push_str(&payload);
                 new_url.push_str(&job_word);
+                new_url.push_str(&injection_suffix);
+                // the deeper traversals may still be in scope.
+                if !job_settings.scope.allows(&new_url) {
+                    payload.push_str(&job_payload_new);
+                    continue;
+                }
                 let result_url = new_url.clone();
                 let title_url = result_url.clone();
                 pb.set_message(format!(
@@ -203,37 +877,28 @@ pub async fn run_tester(
                     new_url.bold().blue(),
                 ));
 
-                let get = client.get(new_url);
-                let mut req = match get.build() {
+                let get = traversal_request(
+                    &client,
+                    &job_settings,
+                    &job_method,
+                    &new_url,
+                    &job_payload_new,
+                );
+                let req = match get.build() {
                     Ok(req) => req,
-                    Err(_) => {
+                    Err(e) => {
+                        tracing::debug!(error = %e, "failed to build the request");
                         continue;
                     }
                 };
-                if job_header != "" {
-                    let header_str = job_header.clone();
-                    let header_parts: Vec<String> =
-                        header_str.split(':').map(String::from).collect();
-                    let header_key = header_parts[0].to_string();
-                    let header_value = header_parts[1].to_string();
-
-                    let key = match reqwest::header::HeaderName::from_str(header_key.as_str()) {
-                        Ok(key) => key,
-                        Err(_) => continue,
-                    };
-                    let value = match reqwest::header::HeaderValue::from_str(header_value.as_str())
-                    {
-                        Ok(value) => value,
-                        Err(_) => continue,
-                    };
-                    req.headers_mut().append(key, value);
-                }
                 let response = match client.execute(req).await {
                     Ok(resp) => resp,
-                    Err(_) => {
+                    Err(e) => {
+                        tracing::debug!(error = %e, "request failed");
                         continue;
                     }
                 };
+                record_family(&job_settings, &waf_host, &base_payload, Outcome::Attempted);
 
                 // fetch the server from the headers
                 let server = match response.headers().get("Server") {
@@ -249,47 +914,60 @@ pub async fn run_tester(
                     None => { "" }.to_owned(),
                 };
 
-                let get = client.get(title_url);
-                let mut request = match get.build() {
+                let get = traversal_request(
+                    &client,
+                    &job_settings,
+                    &job_method,
+                    &title_url,
+                    &job_payload_new,
+                );
+                let request = match get.build() {
                     Ok(request) => request,
-                    Err(_) => {
+                    Err(e) => {
+                        tracing::debug!(error = %e, "failed to build the request");
                         continue;
                     }
                 };
-                if job_header != "" {
-                    let header_str = job_header.clone();
-                    let header_parts: Vec<String> =
-                        header_str.split(':').map(String::from).collect();
-                    let header_key = header_parts[0].to_string();
-                    let header_value = header_parts[1].to_string();
-
-                    let key = match reqwest::header::HeaderName::from_str(header_key.as_str()) {
-                        Ok(key) => key,
-                        Err(_) => continue,
-                    };
-                    let value = match reqwest::header::HeaderValue::from_str(header_value.as_str())
-                    {
-                        Ok(value) => value,
-                        Err(_) => continue,
-                    };
-                    request.headers_mut().append(key, value);
-                }
                 let response_title = match client.execute(request).await {
                     Ok(response_title) => response_title,
-                    Err(_) => {
+                    Err(e) => {
+                        tracing::debug!(error = %e, "request failed");
                         continue;
                     }
                 };
 
-                let mut title = String::from("");
-                let content = match response_title.text().await {
-                    Ok(content) => content,
-                    Err(_) => "".to_string(),
+                // the body is counted as sent, before it is decoded.
+                let content_type = response_title
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string);
+                let body = match response_title.bytes().await {
+                    Ok(body) => body.to_vec(),
+                    Err(_) => vec![],
                 };
-                let re = Regex::new(r"<title>(.*?)</title>").unwrap();
-                for cap in re.captures_iter(&content) {
-                    title.push_str(&cap[1]);
+                let size = utils::ResponseSize::of(&body);
+                let content = utils::decode_body(content_type.as_deref(), &body);
+                let meta = JobResultMeta::extract(&content);
+                let title = &meta.title;
+                if !job_settings.plugins.is_empty() {
+                    let inspection = plugin::inspection(
+                        &result_url,
+                        response.status(),
+                        response.headers(),
+                        &content,
+                    );
+                    url_findings.extend(
+                        run_plugins(&pb, &client, &job_settings, &mut plugins, &inspection).await,
+                    );
                 }
+                url_findings.extend(run_script(
+                    &pb,
+                    &job_settings,
+                    &result_url,
+                    &response,
+                    &content,
+                ));
 
                 if job_settings.int_status.contains(response.status().as_str()) {
                     if response.status().is_client_error() {
@@ -467,9 +1145,28 @@ pub async fn run_tester(
                             "]".bold().white(),
                         ));
                     }
+                    learn_words(&job_settings, &content);
+                    notify_finding(
+                        &job_settings,
+                        FindingKind::InternalRoot,
+                        &result_url,
+                        &job_payload_new,
+                        response.status(),
+                        size,
+                        &client::content_encoding(response.headers()),
+                    );
+                    record_family(&job_settings, &waf_host, &base_payload, Outcome::Bypassed);
+                    let mut follow_ups = std::mem::take(&mut url_findings);
+                    follow_ups.extend(
+                        probe_internal_root(&pb, &client, &job_settings, &result_url).await,
+                    );
                     // send the result message through the channel to the workers.
                     let result_msg = JobResult {
                         data: result_url.to_owned(),
+                        family: job_settings.family_stats.family(&base_payload),
+                        status: response.status().as_str().to_string(),
+                        meta,
+                        follow_ups,
                     };
                     let result_job = result_msg.clone();
                     if let Err(_) = tx.send(result_msg).await {
@@ -479,6 +1176,12 @@ pub async fn run_tester(
                 }
             } else {
                 new_url.push_str(&payload);
+                new_url.push_str(&injection_suffix);
+                // the deeper traversals may still be in scope.
+                if !job_settings.scope.allows(&new_url) {
+                    payload.push_str(&job_payload_new);
+                    continue;
+                }
 
                 pb.set_message(format!(
                     "{} {}",
@@ -487,112 +1190,100 @@ pub async fn run_tester(
                 ));
 
                 let new_url2 = new_url.clone();
-                let get = client.get(new_url);
-                let mut req = match get.build() {
+                let get = traversal_request(
+                    &client,
+                    &job_settings,
+                    &job_method,
+                    &new_url,
+                    &job_payload_new,
+                );
+                let req = match get.build() {
                     Ok(req) => req,
-                    Err(_) => {
+                    Err(e) => {
+                        tracing::debug!(error = %e, "failed to build the request");
                         continue;
                     }
                 };
-                if job_header != "" {
-                    let header_str = job_header.clone();
-                    let header_parts: Vec<String> =
-                        header_str.split(':').map(String::from).collect();
-                    let header_key = header_parts[0].to_string();
-                    let header_value = header_parts[1].to_string();
-
-                    let key = match reqwest::header::HeaderName::from_str(header_key.as_str()) {
-                        Ok(key) => key,
-                        Err(_) => continue,
-                    };
-                    let value = match reqwest::header::HeaderValue::from_str(header_value.as_str())
-                    {
-                        Ok(value) => value,
-                        Err(_) => continue,
-                    };
-                    req.headers_mut().append(key, value);
-                }
                 let resp = match client.execute(req).await {
                     Ok(resp) => resp,
-                    Err(_) => {
+                    Err(e) => {
+                        tracing::debug!(error = %e, "request failed");
                         continue;
                     }
                 };
+                record_family(&job_settings, &waf_host, &base_payload, Outcome::Attempted);
 
                 let content_length = match resp.content_length() {
                     Some(content_length) => content_length.to_string(),
                     None => { "" }.to_owned(),
                 };
+                let status = resp.status();
+                if fingerprint::is_blocking_status(status) {
+                    let headers = resp.headers().clone();
+                    let body = resp.text().await.unwrap_or_default();
+                    if fingerprint::classify_block(status, &headers, &body).is_some() {
+                        let waf_match = waf::identify(status, &headers, &body);
+                        job_settings
+                            .escalation
+                            .record_block(&waf_host, &base_payload, waf_match);
+                        record_family(&job_settings, &waf_host, &base_payload, Outcome::Blocked);
+                        break;
+                    }
+                }
+                job_settings.escalation.record_pass(&waf_host);
                 let backonemore_url = new_url2.clone();
 
-                if job_settings.pub_status.contains(resp.status().as_str()) {
+                if job_settings.pub_status.contains(status.as_str()) {
                     // strip the suffix hax and traverse back one more level
                     // to reach the internal doc root.
-                    let backonemore = match backonemore_url.strip_suffix(job_payload_new.as_str()) {
-                        Some(backonemore) => backonemore,
-                        None => "",
+                    let backonemore = match backonemore_url
+                        .strip_suffix(injection_suffix.as_str())
+                        .and_then(|url| url.strip_suffix(job_payload_new.as_str()))
+                    {
+                        Some(backonemore) => format!("{}{}", backonemore, injection_suffix),
+                        None => "".to_string(),
                     };
-                    let get = client.get(backonemore);
-                    let mut request = match get.build() {
+                    let get = traversal_request(
+                        &client,
+                        &job_settings,
+                        &job_method,
+                        &backonemore,
+                        &job_payload_new,
+                    );
+                    let request = match get.build() {
                         Ok(request) => request,
-                        Err(_) => {
+                        Err(e) => {
+                            tracing::debug!(error = %e, "failed to build the request");
                             continue;
                         }
                     };
-                    if job_header != "" {
-                        let header_str = job_header.clone();
-                        let header_parts: Vec<String> =
-                            header_str.split(':').map(String::from).collect();
-                        let header_key = header_parts[0].to_string();
-                        let header_value = header_parts[1].to_string();
-
-                        let key = match reqwest::header::HeaderName::from_str(header_key.as_str()) {
-                            Ok(key) => key,
-                            Err(_) => continue,
-                        };
-                        let value =
-                            match reqwest::header::HeaderValue::from_str(header_value.as_str()) {
-                                Ok(value) => value,
-                                Err(_) => continue,
-                            };
-                        request.headers_mut().append(key, value);
-                    }
                     let response_title = match client.execute(request).await {
                         Ok(response_title) => response_title,
-                        Err(_) => {
+                        Err(e) => {
+                            tracing::debug!(error = %e, "request failed");
                             continue;
                         }
                     };
 
                     let result_url = backonemore.clone();
-                    let get = client.get(backonemore);
-                    let mut request = match get.build() {
+                    let get = traversal_request(
+                        &client,
+                        &job_settings,
+                        &job_method,
+                        &backonemore,
+                        &job_payload_new,
+                    );
+                    let request = match get.build() {
                         Ok(request) => request,
-                        Err(_) => {
+                        Err(e) => {
+                            tracing::debug!(error = %e, "failed to build the request");
                             continue;
                         }
                     };
-                    if job_header != "" {
-                        let header_str = job_header.clone();
-                        let header_parts: Vec<String> =
-                            header_str.split(':').map(String::from).collect();
-                        let header_key = header_parts[0].to_string();
-                        let header_value = header_parts[1].to_string();
-
-                        let key = match reqwest::header::HeaderName::from_str(header_key.as_str()) {
-                            Ok(key) => key,
-                            Err(_) => continue,
-                        };
-                        let value =
-                            match reqwest::header::HeaderValue::from_str(header_value.as_str()) {
-                                Ok(value) => value,
-                                Err(_) => continue,
-                            };
-                        request.headers_mut().append(key, value);
-                    }
                     let response = match client.execute(request).await {
                         Ok(response) => response,
-                        Err(_) => {
+                        Err(e) => {
+                            tracing::debug!(error = %e, "request failed");
                             continue;
                         }
                     };
@@ -607,9 +1298,7 @@ pub async fn run_tester(
                         if job_settings.drop_after_fail == response.status().as_str() {
                             track_status_codes += 1;
                             if track_status_codes >= 5 {
-                                return JobResult {
-                                    data: "".to_string(),
-                                };
+                                return JobResult::default();
                             }
                         }
                         pb.println(format!(
@@ -617,15 +1306,39 @@ pub async fn run_tester(
                             "found internal doc root :: ".bold().green(),
                             result_url.bold().blue(),
                         ));
-                        let mut title = String::from("");
-                        let content = match response_title.text().await {
-                            Ok(content) => content,
-                            Err(_) => "".to_string(),
+                        // the body is counted as sent, before it is decoded.
+                        let content_type = response_title
+                            .headers()
+                            .get(CONTENT_TYPE)
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string);
+                        let body = match response_title.bytes().await {
+                            Ok(body) => body.to_vec(),
+                            Err(_) => vec![],
                         };
-                        let re = Regex::new(r"<title>(.*?)</title>").unwrap();
-                        for cap in re.captures_iter(&content) {
-                            title.push_str(&cap[1]);
+                        let size = utils::ResponseSize::of(&body);
+                        let content = utils::decode_body(content_type.as_deref(), &body);
+                        let meta = JobResultMeta::extract(&content);
+                        let title = &meta.title;
+                        if !job_settings.plugins.is_empty() {
+                            let inspection = plugin::inspection(
+                                &result_url,
+                                response.status(),
+                                response.headers(),
+                                &content,
+                            );
+                            url_findings.extend(
+                                run_plugins(&pb, &client, &job_settings, &mut plugins, &inspection)
+                                    .await,
+                            );
                         }
+                        url_findings.extend(run_script(
+                            &pb,
+                            &job_settings,
+                            &result_url,
+                            &response,
+                            &content,
+                        ));
                         // fetch the server from the headers
                         let server = match response.headers().get("Server") {
                             Some(server) => match server.to_str() {
@@ -809,9 +1522,29 @@ pub async fn run_tester(
                                 "]".bold().white(),
                             ));
                         }
+                        learn_words(&job_settings, &content);
+                        notify_finding(
+                            &job_settings,
+                            FindingKind::InternalRoot,
+                            &result_url,
+                            &job_payload_new,
+                            response.status(),
+                            size,
+                            &client::content_encoding(response.headers()),
+                        );
+                        record_family(&job_settings, &waf_host, &base_payload, Outcome::Bypassed);
+                        job_settings.confirmed_roots.confirm(&job_url);
+                        let mut follow_ups = std::mem::take(&mut url_findings);
+                        follow_ups.extend(
+                            probe_internal_root(&pb, &client, &job_settings, &result_url).await,
+                        );
                         // send the result message through the channel to the workers.
                         let result_msg = JobResult {
                             data: result_url.to_owned(),
+                            family: job_settings.family_stats.family(&base_payload),
+                            status: response.status().as_str().to_string(),
+                            meta,
+                            follow_ups,
                         };
                         let result_job = result_msg.clone();
                         if let Err(_) = tx.send(result_msg).await {
@@ -827,15 +1560,1315 @@ pub async fn run_tester(
         pb.inc(1);
     }
     return JobResult {
-        data: "".to_string(),
+        follow_ups: url_findings,
+        ..Default::default()
+    };
+}
+
+// binary searches the first depth of the payload answered with the --pub-status,
+// the traversals past the doc root of the application are expected to keep
+// getting it. None when even the deepest traversal doesn't.
+async fn flip_depth(
+    client: &Client,
+    job_settings: &JobSettings,
+    method: &Method,
+    base: &str,
+    payload: &str,
+    suffix: &str,
+    max_depth: usize,
+) -> Option<usize> {
+    let flips = |depth: usize| async move {
+        let mut url = base.to_string();
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        url.push_str(&payload.repeat(depth));
+        url.push_str(suffix);
+        if !job_settings.scope.allows(&url) {
+            return false;
+        }
+        let req = match traversal_request(client, job_settings, method, &url, payload).build() {
+            Ok(req) => req,
+            Err(_) => return false,
+        };
+        match client.execute(req).await {
+            Ok(resp) => job_settings.pub_status.contains(resp.status().as_str()),
+            Err(_) => false,
+        }
     };
+    if max_depth == 0 || !flips(max_depth).await {
+        return None;
+    }
+    let (mut low, mut high) = (1, max_depth);
+    while low < high {
+        let middle = (low + high) / 2;
+        if flips(middle).await {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    Some(low)
+}
+
+// counts the confirmed finding and queues it for the notification sinks, a
+// failing sink only warns. the finding is returned for the report.
+pub fn notify_finding(
+    job_settings: &JobSettings,
+    kind: FindingKind,
+    url: &str,
+    payload: &str,
+    status: StatusCode,
+    size: utils::ResponseSize,
+    content_encoding: &str,
+) -> Finding {
+    publish_finding(
+        job_settings,
+        Finding {
+            kind,
+            url: url.to_string(),
+            payload: payload.to_string(),
+            status: status.as_u16(),
+            size,
+            tags: job_settings.tags.tags(url),
+            evidence: String::new(),
+            family: String::new(),
+            content_encoding: content_encoding.to_string(),
+        },
+    )
+}
+
+// notify_finding for a finding that carries its evidence or payload family.
+fn publish_finding(job_settings: &JobSettings, finding: Finding) -> Finding {
+    observer::finding(finding.kind.name(), &finding.url);
+    events::publish(ScanEvent::Finding {
+        kind: finding.kind,
+        severity: finding.kind.severity(),
+        url: finding.url.clone(),
+        payload: finding.payload.clone(),
+        status: finding.status,
+        size: finding.size,
+        tags: finding.tags.clone(),
+        evidence: finding.evidence.clone(),
+        family: finding.family.clone(),
+        content_encoding: finding.content_encoding.clone(),
+    });
+    job_settings.notifier.notify(&finding);
+    finding
+}
+
+// runs the --plugin modules on the response and reports their findings, the
+// jobs they ask for are fetched and inspected once without further jobs.
+async fn run_plugins(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    plugins: &mut Option<Vec<PluginInstance>>,
+    inspection: &Inspection<'_>,
+) -> Vec<Finding> {
+    let instances = plugins.get_or_insert_with(|| {
+        job_settings
+            .plugins
+            .iter()
+            .filter_map(|plugin| match plugin.instantiate() {
+                Ok(instance) => Some(instance),
+                Err(e) => {
+                    tracing::warn!(plugin = %plugin.name, error = %e, "failed to instantiate plugin");
+                    None
+                }
+            })
+            .collect()
+    });
+    let (jobs, mut findings) = inspect(pb, job_settings, instances, inspection);
+    for job_url in jobs {
+        if !job_settings.scope.allows(&job_url) {
+            tracing::debug!(url = %job_url, "plugin job out of scope");
+            continue;
+        }
+        let request = match client.get(&job_url).build() {
+            Ok(request) => request,
+            Err(e) => {
+                tracing::debug!(error = %e, "failed to build the plugin job");
+                continue;
+            }
+        };
+        let response = match client.execute(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::debug!(error = %e, "plugin job failed");
+                continue;
+            }
+        };
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.text().await.unwrap_or_default();
+        let inspection = plugin::inspection(&job_url, status, &headers, &body);
+        findings.extend(inspect(pb, job_settings, instances, &inspection).1);
+    }
+    findings
+}
+
+// runs the plugins on the response and returns the jobs they ask for and
+// their findings.
+fn inspect(
+    pb: &ProgressBar,
+    job_settings: &JobSettings,
+    instances: &mut [PluginInstance],
+    inspection: &Inspection<'_>,
+) -> (Vec<String>, Vec<Finding>) {
+    let mut jobs = vec![];
+    let mut findings = vec![];
+    for instance in instances.iter_mut() {
+        let output = match instance.inspect(inspection) {
+            Ok(output) => output,
+            Err(e) => {
+                tracing::warn!(plugin = %instance.name, error = %e, "plugin failed");
+                continue;
+            }
+        };
+        for finding in output.findings {
+            let url = if finding.url.is_empty() {
+                inspection.url.to_string()
+            } else {
+                finding.url
+            };
+            pb.println(format!(
+                "{} {} {}{}{}",
+                format!("{} ::", instance.name).bold().green(),
+                url.bold().blue(),
+                "[".bold().white(),
+                finding.title.bold().purple(),
+                "]".bold().white(),
+            ));
+            findings.push(publish_finding(
+                job_settings,
+                Finding {
+                    kind: FindingKind::Plugin,
+                    tags: job_settings.tags.tags(&url),
+                    url,
+                    payload: String::new(),
+                    status: inspection.status,
+                    size: utils::ResponseSize::of(inspection.body.as_bytes()),
+                    evidence: format!("{}: {}", instance.name, finding.title),
+                    family: String::new(),
+                    content_encoding: inspection
+                        .headers
+                        .get("content-encoding")
+                        .map(|value| value.trim().to_lowercase())
+                        .unwrap_or_default(),
+                },
+            ));
+        }
+        jobs.extend(output.jobs);
+    }
+    (jobs, findings)
+}
+
+// runs the --script on_response hook and reports the finding it flagged.
+fn run_script(
+    pb: &ProgressBar,
+    job_settings: &JobSettings,
+    url: &str,
+    response: &reqwest::Response,
+    body: &str,
+) -> Option<Finding> {
+    let script = job_settings.script.as_ref()?;
+    let title = script.on_response(url, response.status(), response.headers(), body)?;
+    pb.println(format!(
+        "{} {} {}{}{}",
+        "script ::".bold().green(),
+        url.bold().blue(),
+        "[".bold().white(),
+        title.bold().purple(),
+        "]".bold().white(),
+    ));
+    Some(publish_finding(
+        job_settings,
+        Finding {
+            kind: FindingKind::Script,
+            url: url.to_string(),
+            payload: String::new(),
+            status: response.status().as_u16(),
+            size: utils::ResponseSize::of(body.as_bytes()),
+            tags: job_settings.tags.tags(url),
+            evidence: title,
+            family: String::new(),
+            content_encoding: client::content_encoding(response.headers()),
+        },
+    ))
+}
+
+// feeds the response of an internal doc root to the --learn-words learner.
+fn learn_words(job_settings: &JobSettings, content: &str) {
+    if let Some(word_learner) = &job_settings.word_learner {
+        word_learner.learn(content);
+    }
+}
+
+// records the outcome of the payload family against the target.
+fn record_family(job_settings: &JobSettings, host: &str, payload: &str, outcome: Outcome) {
+    job_settings.family_stats.record(host, payload, outcome);
+}
+
+// builds a traversal request with the job method, non GET/HEAD requests carry the --body
+// with the {{payload}} marker replaced by the traversal payload, as are the §PAYLOAD§
+// markers of a --raw-request body and headers.
+fn traversal_request(
+    client: &Client,
+    job_settings: &JobSettings,
+    method: &Method,
+    url: &str,
+    payload: &str,
+) -> RequestBuilder {
+    let mut req = client.request(method.clone(), url);
+    for (name, value) in &job_settings.script_headers {
+        req = req.header(name.as_str(), value.replace(PAYLOAD_MARKER, payload));
+    }
+    if job_settings.body.is_empty() || method == Method::GET || method == Method::HEAD {
+        return req;
+    }
+    let body = job_settings
+        .body
+        .replace("{{payload}}", payload)
+        .replace(PAYLOAD_MARKER, payload);
+    let mut req = req.body(body);
+    if !job_settings.content_type.is_empty() {
+        req = req.header(CONTENT_TYPE, job_settings.content_type.as_str());
+    }
+    req
+}
+
+// injects the traversal into the value of a query parameter, deepening it the same
+// way as the path traversals, and reports the first response that reaches the
+// internal status while deviating from the untouched url.
+async fn test_query_param(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    method: &Method,
+    url: &reqwest::Url,
+    param: &str,
+    payload: &str,
+) -> Option<Finding> {
+    let baseline =
+        match traversal_request(client, job_settings, method, url.as_str(), payload).build() {
+            Ok(baseline) => baseline,
+            Err(_) => return None,
+        };
+    let baseline = client.execute(baseline).await.ok()?;
+    let baseline_status = baseline.status();
+    let baseline_text = client::text(baseline).await.unwrap_or_default();
+
+    // the query is rebuilt by hand so the payload encoding is sent untouched.
+    let query = url.query().unwrap_or("");
+    let depth = url.path().split('/').count() + 5;
+    let mut traversal = payload.to_string();
+    for _ in 0..depth {
+        let injected_query = query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if name == param => format!("{}={}", name, traversal),
+                None if pair == param => format!("{}={}", pair, traversal),
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        let mut injected_url = url.clone();
+        injected_url.set_query(Some(&injected_query));
+        let injected_url = injected_url.to_string();
+        let injected_param = format!("{}={}", param, traversal);
+        traversal.push_str(payload);
+
+        pb.set_message(format!(
+            "{} {}",
+            "scanning ::".bold().white(),
+            injected_url.bold().blue(),
+        ));
+
+        let req =
+            match traversal_request(client, job_settings, method, &injected_url, payload).build() {
+                Ok(req) => req,
+                Err(_) => continue,
+            };
+        let resp = match client.execute(req).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                tracing::debug!(error = %e, "request failed");
+                continue;
+            }
+        };
+        let status = resp.status();
+        if !job_settings.int_status.contains(status.as_str()) {
+            continue;
+        }
+        let content_encoding = client::content_encoding(resp.headers());
+        let text = client::text(resp).await.unwrap_or_default();
+        let (changed, _) = utils::get_response_change(&text, &baseline_text, client.similarity());
+        if status != baseline_status || changed {
+            pb.println(format!(
+                "{} {} {}{}{} {}{}{}",
+                "found query traversal ::".bold().green(),
+                injected_url.bold().blue(),
+                "[".bold().white(),
+                param.bold().purple(),
+                "]".bold().white(),
+                "[".bold().white(),
+                status.as_str().bold().cyan(),
+                "]".bold().white(),
+            ));
+            return Some(notify_finding(
+                job_settings,
+                FindingKind::QueryTraversal,
+                &injected_url,
+                &injected_param,
+                status,
+                utils::ResponseSize::of(text.as_bytes()),
+                &content_encoding,
+            ));
+        }
+    }
+    None
+}
+
+// probes every path prefix the way an nginx `location /prefix { alias ...; }` block
+// without a trailing slash would match it, a `/prefix../` that is routed differently
+// from a prefix that can't exist means the alias lets us step out of its directory.
+async fn test_nginx_alias(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    url: &reqwest::Url,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    let segments: Vec<&str> = url.path().split('/').filter(|s| !s.is_empty()).collect();
+    let mut prefix = String::from("");
+    for segment in segments {
+        prefix.push('/');
+        prefix.push_str(segment);
+
+        let control = format!("{}pb{}", prefix, rand::thread_rng().gen::<u32>());
+        for traversal in ["../", "..%2f"] {
+            let mut control_url = url.clone();
+            control_url.set_query(None);
+            control_url.set_path(&format!("{}{}", control, traversal));
+            let mut probe_url = control_url.clone();
+            probe_url.set_path(&format!("{}{}", prefix, traversal));
+
+            let mut responses = vec![];
+            for check_url in [&probe_url, &control_url] {
+                let req = match client.get(check_url.as_str()).build() {
+                    Ok(req) => req,
+                    Err(_) => break,
+                };
+                match client.execute(req).await {
+                    Ok(resp) => responses.push(resp),
+                    Err(_) => break,
+                }
+            }
+            let (probe, control_status) = match (responses.pop(), responses.pop()) {
+                (Some(control), Some(probe)) => (probe, control.status()),
+                _ => continue,
+            };
+            let probe_status = probe.status();
+            if probe_status != control_status && probe_status != StatusCode::NOT_FOUND {
+                let evidence = format!(
+                    "GET {} -> {}, GET {} -> {}",
+                    probe_url.path(),
+                    probe_status.as_str(),
+                    control_url.path(),
+                    control_status.as_str()
+                );
+                pb.println(format!(
+                    "{} {} {}{}{} {}{}{}",
+                    "found nginx alias traversal ::".bold().green(),
+                    probe_url.as_str().bold().blue(),
+                    "[".bold().white(),
+                    evidence.bold().purple(),
+                    "]".bold().white(),
+                    "[".bold().white(),
+                    format!("location {}", prefix).bold().cyan(),
+                    "]".bold().white(),
+                ));
+                let content_encoding = client::content_encoding(probe.headers());
+                let size = match probe.bytes().await {
+                    Ok(body) => utils::ResponseSize::of(&body),
+                    Err(_) => utils::ResponseSize::default(),
+                };
+                findings.push(publish_finding(
+                    job_settings,
+                    Finding {
+                        kind: FindingKind::NginxAlias,
+                        url: probe_url.to_string(),
+                        payload: format!("location {}", prefix),
+                        status: probe_status.as_u16(),
+                        size,
+                        tags: job_settings.tags.tags(probe_url.as_str()),
+                        evidence,
+                        family: String::new(),
+                        content_encoding,
+                    },
+                ));
+            }
+        }
+    }
+    findings
+}
+
+// the Gateway enum holds the managed api gateways with known path confusion quirks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Gateway {
+    AwsApiGw,
+    AwsAlb,
+    AzureApim,
+}
+
+impl Gateway {
+    // fingerprints the gateway from the headers it adds to every response.
+    fn fingerprint(headers: &HeaderMap) -> Option<Gateway> {
+        let server = match headers.get("Server") {
+            Some(server) => server.to_str().unwrap_or("").to_lowercase(),
+            None => "".to_string(),
+        };
+        if headers.contains_key("x-amz-apigw-id") || headers.contains_key("x-amzn-requestid") {
+            Some(Gateway::AwsApiGw)
+        } else if server.starts_with("awselb") {
+            Some(Gateway::AwsAlb)
+        } else if headers.contains_key("apim-request-id")
+            || headers.contains_key("ocp-apim-trace-location")
+        {
+            Some(Gateway::AzureApim)
+        } else {
+            None
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Gateway::AwsApiGw => "aws-api-gateway",
+            Gateway::AwsAlb => "aws-alb",
+            Gateway::AzureApim => "azure-apim",
+        }
+    }
+
+    // the payloads appended to the stage (first path segment): api gateway strips the
+    // stage before routing, the alb routes double slashes as is and apim only decodes
+    // the traversal once it picked the backend api.
+    fn payloads(&self) -> &'static [&'static str] {
+        match self {
+            Gateway::AwsApiGw => &["/..%2f", "/..%252f", "//"],
+            Gateway::AwsAlb => &["//", "/%2e%2e%2f"],
+            Gateway::AzureApim => &["/..%2f", "/%2e%2e/", "/..%5c"],
+        }
+    }
+}
+
+// probes the stage prefix of the url with the payloads of the gateway, a response
+// reaching the internal status that differs from a missing path below the stage
+// means the gateway and the backend disagree on the route.
+async fn test_gateway(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    url: &reqwest::Url,
+    gateway: Gateway,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    let stage = match url.path().split('/').find(|s| !s.is_empty()) {
+        Some(stage) => stage,
+        None => return findings,
+    };
+    let origin = url.origin().ascii_serialization();
+
+    let control_path = format!("/{}/pb{}", stage, rand::thread_rng().gen::<u32>());
+    let control_status = match client.get(format!("{}{}", origin, control_path)).build() {
+        Ok(req) => match client.execute(req).await {
+            Ok(resp) => resp.status(),
+            Err(_) => return findings,
+        },
+        Err(_) => return findings,
+    };
+
+    for payload in gateway.payloads() {
+        let probe_url = format!("{}/{}{}", origin, stage, payload);
+        let req = match client.get(&probe_url).build() {
+            Ok(req) => req,
+            Err(e) => {
+                tracing::debug!(error = %e, "failed to build the request");
+                continue;
+            }
+        };
+        let resp = match client.execute(req).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                tracing::debug!(error = %e, "request failed");
+                continue;
+            }
+        };
+        let status = resp.status();
+        if status != control_status && job_settings.int_status.contains(status.as_str()) {
+            pb.println(format!(
+                "{} {} {}{}{} {}{}{}",
+                "found gateway path confusion ::".bold().green(),
+                probe_url.bold().blue(),
+                "[".bold().white(),
+                gateway.name().bold().purple(),
+                "]".bold().white(),
+                "[".bold().white(),
+                status.as_str().bold().cyan(),
+                "]".bold().white(),
+            ));
+            let content_encoding = client::content_encoding(resp.headers());
+            let size = match resp.bytes().await {
+                Ok(body) => utils::ResponseSize::of(&body),
+                Err(_) => utils::ResponseSize::default(),
+            };
+            findings.push(publish_finding(
+                job_settings,
+                Finding {
+                    kind: FindingKind::GatewayConfusion,
+                    url: probe_url.clone(),
+                    payload: payload.to_string(),
+                    status: status.as_u16(),
+                    size,
+                    tags: job_settings.tags.tags(&probe_url),
+                    evidence: format!(
+                        "{} GET {} -> {}",
+                        gateway.name(),
+                        control_path,
+                        control_status.as_str()
+                    ),
+                    family: String::new(),
+                    content_encoding,
+                },
+            ));
+        }
+    }
+    findings
+}
+
+// fingerprints tomcat from the server header, the JSESSIONID cookie or its error pages.
+async fn is_tomcat(resp: reqwest::Response) -> bool {
+    let server = match resp.headers().get("Server") {
+        Some(server) => server.to_str().unwrap_or("").to_lowercase(),
+        None => "".to_string(),
+    };
+    if server.contains("tomcat") || server.contains("coyote") {
+        return true;
+    }
+    let jsessionid = resp
+        .headers()
+        .get_all(reqwest::header::SET_COOKIE)
+        .iter()
+        .any(|cookie| cookie.to_str().unwrap_or("").starts_with("JSESSIONID="));
+    if jsessionid {
+        return true;
+    }
+    match resp.text().await {
+        Ok(text) => text.contains("Apache Tomcat"),
+        Err(_) => false,
+    }
+}
+
+// tomcat treats `..;/` as a path segment parameter while the proxies in front of it
+// see a plain directory, so the manager apps are probed through it at every depth.
+async fn test_tomcat(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    url: &reqwest::Url,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut base_url = url.clone();
+    base_url.set_query(None);
+    let mut base_url = base_url.to_string();
+    if !base_url.ends_with('/') {
+        base_url.push('/');
+    }
+
+    let depth = url.path().split('/').filter(|s| !s.is_empty()).count() + 1;
+    for app in TOMCAT_MANAGER_PATHS {
+        // the manager reachable without a traversal is not a bypass.
+        let mut direct_url = url.clone();
+        direct_url.set_query(None);
+        direct_url.set_path(app);
+        if tomcat_manager_status(client, direct_url.as_str())
+            .await
+            .is_some()
+        {
+            continue;
+        }
+
+        let mut traversal = String::from("");
+        for _ in 0..depth {
+            traversal.push_str("..;/");
+            let probe_url = format!("{}{}{}", base_url, traversal, app.trim_start_matches('/'));
+            pb.set_message(format!(
+                "{} {}",
+                "scanning ::".bold().white(),
+                probe_url.bold().blue(),
+            ));
+            if let Some((status, size, content_encoding)) =
+                tomcat_manager_status(client, &probe_url).await
+            {
+                pb.println(format!(
+                    "{} {} {}{}{} {}{}{}",
+                    "found tomcat bypass ::".bold().green(),
+                    probe_url.bold().blue(),
+                    "[".bold().white(),
+                    TOMCAT_FAMILY.bold().purple(),
+                    "]".bold().white(),
+                    "[".bold().white(),
+                    status.as_str().bold().cyan(),
+                    "]".bold().white(),
+                ));
+                findings.push(publish_finding(
+                    job_settings,
+                    Finding {
+                        kind: FindingKind::TomcatManager,
+                        url: probe_url.clone(),
+                        payload: format!("{}{}", traversal, app.trim_start_matches('/')),
+                        status: status.as_u16(),
+                        size,
+                        tags: job_settings.tags.tags(&probe_url),
+                        evidence: String::new(),
+                        family: TOMCAT_FAMILY.to_string(),
+                        content_encoding,
+                    },
+                ));
+                break;
+            }
+        }
+    }
+    findings
+}
+
+// returns the status, size and Content-Encoding when the response comes from a
+// tomcat manager app.
+async fn tomcat_manager_status(
+    client: &Client,
+    url: &str,
+) -> Option<(StatusCode, utils::ResponseSize, String)> {
+    let req = client.get(url).build().ok()?;
+    let resp = client.execute(req).await.ok()?;
+    let status = resp.status();
+    let realm = match resp.headers().get(reqwest::header::WWW_AUTHENTICATE) {
+        Some(realm) => realm.to_str().unwrap_or("").to_string(),
+        None => "".to_string(),
+    };
+    let content_encoding = client::content_encoding(resp.headers());
+    let text = resp.text().await.unwrap_or_default();
+    let size = utils::ResponseSize::of(text.as_bytes());
+    if status == StatusCode::UNAUTHORIZED && realm.contains("Tomcat") {
+        return Some((status, size, content_encoding));
+    }
+    if status.is_success()
+        && (text.contains("Tomcat Web Application Manager")
+            || text.contains("Tomcat Virtual Host Manager"))
+    {
+        return Some((status, size, content_encoding));
+    }
+    None
+}
+
+// runs the follow up stages against a confirmed internal doc root and returns
+// what they found.
+async fn probe_internal_root(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    root_url: &str,
+) -> Vec<Finding> {
+    let mut follow_ups = vec![];
+    if job_settings.cache_deception {
+        follow_ups.extend(test_cache_deception(pb, client, job_settings, root_url).await);
+    }
+    if is_spring(client, root_url).await {
+        follow_ups.extend(test_spring_actuators(pb, client, job_settings, root_url).await);
+    }
+    if job_settings.iis_shortname {
+        follow_ups.extend(test_iis_shortnames(pb, client, job_settings, root_url).await);
+    }
+    follow_ups
+}
+
+// returns the status of a short name pattern below the root.
+async fn shortname_status(client: &Client, root_url: &str, pattern: &str) -> Option<StatusCode> {
+    let url = format!("{}/{}*~1*/a.aspx", root_url.trim_end_matches('/'), pattern);
+    let req = client.get(&url).build().ok()?;
+    let resp = client.execute(req).await.ok()?;
+    Some(resp.status())
+}
+
+// enumerates the 8.3 short names of the directories below the internal doc root,
+// iis answers a wildcard matching an existing short name differently from one that
+// doesn't, which lets the names be guessed one character at a time. iis serves the
+// directories under their short names too, so the names are bruteforced as words.
+async fn test_iis_shortnames(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    root_url: &str,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    let req = match client.get(root_url).build() {
+        Ok(req) => req,
+        Err(_) => return findings,
+    };
+    let server = match client.execute(req).await {
+        Ok(resp) => match resp.headers().get("Server") {
+            Some(server) => server.to_str().unwrap_or("").to_string(),
+            None => "".to_string(),
+        },
+        Err(_) => return findings,
+    };
+    if !server.contains("IIS") {
+        return findings;
+    }
+
+    // the wildcard matching everything tells us how a hit looks,
+    // it must differ from a prefix that can't exist.
+    let hit_status = match shortname_status(client, root_url, "").await {
+        Some(hit_status) => hit_status,
+        None => return findings,
+    };
+    let miss_pattern = format!("pb{}", rand::thread_rng().gen::<u16>());
+    match shortname_status(client, root_url, &miss_pattern).await {
+        Some(miss_status) if miss_status != hit_status => {}
+        _ => return findings,
+    }
+
+    let mut prefixes = vec![String::from("")];
+    while let Some(prefix) = prefixes.pop() {
+        let mut extended = false;
+        if prefix.len() < 6 {
+            for c in IIS_SHORTNAME_CHARS.chars() {
+                let candidate = format!("{}{}", prefix, c);
+                pb.set_message(format!(
+                    "{} {}",
+                    "iis short name scanning ::".bold().white(),
+                    candidate.bold().blue(),
+                ));
+                if shortname_status(client, root_url, &candidate).await == Some(hit_status) {
+                    prefixes.push(candidate);
+                    extended = true;
+                }
+            }
+        }
+        if !extended && !prefix.is_empty() {
+            let shortname = format!("{}~1", prefix.to_uppercase());
+            let shortname_url = format!("{}/{}", root_url.trim_end_matches('/'), shortname);
+            pb.println(format!(
+                "{} {}",
+                "found iis short name ::".bold().green(),
+                shortname_url.bold().blue(),
+            ));
+            if let Some(word_learner) = &job_settings.word_learner {
+                word_learner.add(&shortname);
+            }
+            findings.push(notify_finding(
+                job_settings,
+                FindingKind::IisShortname,
+                &shortname_url,
+                &format!("{}*~1*", prefix),
+                hit_status,
+                utils::ResponseSize::default(),
+                "",
+            ));
+        }
+    }
+    findings
+}
+
+// fingerprints spring boot from the error page it serves for a path that can't exist.
+async fn is_spring(client: &Client, root_url: &str) -> bool {
+    let missing_url = format!(
+        "{}/pb{}",
+        root_url.trim_end_matches('/'),
+        rand::thread_rng().gen::<u32>()
+    );
+    let req = match client.get(&missing_url).build() {
+        Ok(req) => req,
+        Err(_) => return false,
+    };
+    let resp = match client.execute(req).await {
+        Ok(resp) => resp,
+        Err(_) => return false,
+    };
+    if resp.headers().contains_key("X-Application-Context") {
+        return true;
+    }
+    let text = resp.text().await.unwrap_or_default();
+    text.contains("Whitelabel Error Page")
+        || (text.contains("\"timestamp\"")
+            && text.contains("\"error\"")
+            && text.contains("\"path\""))
+}
+
+// probes the spring boot actuators behind the internal doc root, an exposed
+// actuator leaks the environment or the heap so it's reported as high severity.
+async fn test_spring_actuators(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    root_url: &str,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    for actuator in SPRING_ACTUATOR_PATHS {
+        let actuator_url = format!("{}{}", root_url.trim_end_matches('/'), actuator);
+        let req = match client.get(&actuator_url).build() {
+            Ok(req) => req,
+            Err(e) => {
+                tracing::debug!(error = %e, "failed to build the request");
+                continue;
+            }
+        };
+        let resp = match client.execute(req).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                tracing::debug!(error = %e, "request failed");
+                continue;
+            }
+        };
+        if !resp.status().is_success() {
+            continue;
+        }
+        let content_type = match resp.headers().get(CONTENT_TYPE) {
+            Some(content_type) => content_type.to_str().unwrap_or("").to_string(),
+            None => "".to_string(),
+        };
+        // a soft 404 html page is not an actuator.
+        if content_type.contains("text/html") {
+            continue;
+        }
+        let status = resp.status();
+        pb.println(format!(
+            "{} {} {}{}{} {}{}{}",
+            "found spring actuator ::".bold().green(),
+            actuator_url.bold().blue(),
+            "[".bold().white(),
+            FindingKind::SpringActuator
+                .severity()
+                .to_string()
+                .bold()
+                .red(),
+            "]".bold().white(),
+            "[".bold().white(),
+            status.as_str().bold().cyan(),
+            "]".bold().white(),
+        ));
+        let content_encoding = client::content_encoding(resp.headers());
+        // the heapdump can be hundreds of megabytes, its body is never read.
+        let size = if actuator.ends_with("heapdump") {
+            utils::ResponseSize {
+                size: resp.content_length().unwrap_or(0) as usize,
+                ..Default::default()
+            }
+        } else {
+            match resp.bytes().await {
+                Ok(body) => utils::ResponseSize::of(&body),
+                Err(_) => utils::ResponseSize::default(),
+            }
+        };
+        findings.push(notify_finding(
+            job_settings,
+            FindingKind::SpringActuator,
+            &actuator_url,
+            actuator,
+            status,
+            size,
+            &content_encoding,
+        ));
+    }
+    findings
+}
+
+// returns true when the response headers show it was served from a cache.
+fn is_cache_hit(headers: &HeaderMap) -> bool {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("")
+            .to_lowercase()
+    };
+    header("x-cache").contains("hit")
+        || header("cf-cache-status") == "hit"
+        || header("age").parse::<u64>().is_ok_and(|age| age > 0)
+}
+
+// requests the internal route with static looking suffixes twice, a cached second
+// response serving the route content means the cache and the backend disagree on
+// the normalization, which makes the route vulnerable to web cache deception.
+async fn test_cache_deception(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    route_url: &str,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    let route_text = match client.get(route_url).build() {
+        Ok(req) => match client.execute(req).await {
+            Ok(resp) => client::text(resp).await.unwrap_or_default(),
+            Err(_) => return findings,
+        },
+        Err(_) => return findings,
+    };
+
+    let route_url = route_url.trim_end_matches('/');
+    for suffix in CACHE_DECEPTION_SUFFIXES {
+        let name = format!("pb{}", rand::thread_rng().gen::<u32>());
+        let probe_url = format!("{}{}", route_url, suffix.replace("{}", &name));
+
+        let mut responses = vec![];
+        for _ in 0..2 {
+            let req = match client.get(&probe_url).build() {
+                Ok(req) => req,
+                Err(_) => break,
+            };
+            match client.execute(req).await {
+                Ok(resp) => responses.push(resp),
+                Err(_) => break,
+            }
+        }
+        let second = match (responses.len(), responses.pop()) {
+            (2, Some(second)) => second,
+            _ => continue,
+        };
+        if !second.status().is_success() || !is_cache_hit(second.headers()) {
+            continue;
+        }
+        let cache_status = ["x-cache", "cf-cache-status", "age"]
+            .iter()
+            .filter_map(|name| {
+                let value = second.headers().get(*name)?.to_str().ok()?;
+                Some(format!("{}: {}", name, value))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let status = second.status();
+        let content_encoding = client::content_encoding(second.headers());
+        let text = client::text(second).await.unwrap_or_default();
+        let (changed, _) = utils::get_response_change(&text, &route_text, client.similarity());
+        if !changed {
+            pb.println(format!(
+                "{} {} {}{}{}",
+                "found cache deception ::".bold().green(),
+                probe_url.bold().blue(),
+                "[".bold().white(),
+                cache_status.bold().purple(),
+                "]".bold().white(),
+            ));
+            let suffix = probe_url.trim_start_matches(route_url);
+            findings.push(notify_finding(
+                job_settings,
+                FindingKind::CacheDeception,
+                &probe_url,
+                suffix,
+                status,
+                utils::ResponseSize::of(text.as_bytes()),
+                &content_encoding,
+            ));
+        }
+    }
+    findings
+}
+
+// sends the traversal in the rewrite headers while requesting the benign web root,
+// a flip to an internal status means the backend routed on the header instead of the path.
+async fn test_rewrite_headers(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    url: &reqwest::Url,
+    payload: &str,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut web_root_url = url.clone();
+    web_root_url.set_path("/");
+    web_root_url.set_query(None);
+    let web_root_url = web_root_url.to_string();
+
+    let mut rewrite_path = url.path().to_string();
+    if !rewrite_path.ends_with('/') {
+        rewrite_path.push('/');
+    }
+    rewrite_path.push_str(payload);
+
+    let baseline = match client.get(&web_root_url).build() {
+        Ok(baseline) => baseline,
+        Err(_) => return findings,
+    };
+    let baseline_status = match client.execute(baseline).await {
+        Ok(baseline) => baseline.status(),
+        Err(_) => return findings,
+    };
+
+    for header in REWRITE_HEADERS {
+        let req = match client
+            .get(&web_root_url)
+            .header(header, rewrite_path.as_str())
+            .build()
+        {
+            Ok(req) => req,
+            Err(_) => continue,
+        };
+        let resp = match client.execute(req).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                tracing::debug!(error = %e, "request failed");
+                continue;
+            }
+        };
+        let status = resp.status();
+        if status != baseline_status && job_settings.int_status.contains(status.as_str()) {
+            let rewrite = format!("{}: {}", header, rewrite_path);
+            pb.println(format!(
+                "{} {} {}{}{} {}{}{}",
+                "found header rewrite ::".bold().green(),
+                web_root_url.bold().blue(),
+                "[".bold().white(),
+                rewrite.bold().purple(),
+                "]".bold().white(),
+                "[".bold().white(),
+                status.as_str().bold().cyan(),
+                "]".bold().white(),
+            ));
+            let content_encoding = client::content_encoding(resp.headers());
+            let size = match resp.bytes().await {
+                Ok(body) => utils::ResponseSize::of(&body),
+                Err(_) => utils::ResponseSize::default(),
+            };
+            findings.push(notify_finding(
+                job_settings,
+                FindingKind::HeaderRewrite,
+                &web_root_url,
+                &rewrite,
+                status,
+                size,
+                &content_encoding,
+            ));
+        }
+    }
+    findings
+}
+
+// replays a public root that returns 403 with the ip spoofing headers, a
+// header that gets a 2xx/3xx instead of the status of the plain request is
+// reported. the other statuses are rate limits or the server refusing the value.
+async fn test_spoof_ip(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    url: &str,
+    target_status: StatusCode,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    for (header, value) in iproduct!(SPOOF_IP_HEADERS, SPOOF_IP_VALUES) {
+        let req = match client.get(url).header(header, value).build() {
+            Ok(req) => req,
+            Err(e) => {
+                tracing::debug!(error = %e, "failed to build the request");
+                continue;
+            }
+        };
+        let resp = match client.execute(req).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                tracing::debug!(error = %e, "request failed");
+                continue;
+            }
+        };
+        let status = resp.status();
+        if status != target_status && (status.is_success() || status.is_redirection()) {
+            let spoof = format!("{}: {}", header, value);
+            pb.println(format!(
+                "{} {} {}{}{} {}{}{}",
+                "found ip spoof bypass ::".bold().green(),
+                url.bold().blue(),
+                "[".bold().white(),
+                spoof.bold().purple(),
+                "]".bold().white(),
+                "[".bold().white(),
+                status.as_str().bold().cyan(),
+                "]".bold().white(),
+            ));
+            let content_encoding = client::content_encoding(resp.headers());
+            let size = match resp.bytes().await {
+                Ok(body) => utils::ResponseSize::of(&body),
+                Err(_) => utils::ResponseSize::default(),
+            };
+            findings.push(notify_finding(
+                job_settings,
+                FindingKind::IpSpoof,
+                url,
+                &spoof,
+                status,
+                size,
+                &content_encoding,
+            ));
+        }
+    }
+    findings
+}
+
+// retries a path returning 403/405 through the X-HTTP-Method-Override header and the
+// _method parameter, a successful response means the backend honored the override.
+// rails, laravel and express only read _method from a post, in the query or the form.
+async fn test_method_override(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    url: &reqwest::Url,
+    target_status: StatusCode,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    for method in OVERRIDE_METHODS {
+        let header_req = client
+            .request(Method::POST, url.as_str())
+            .header("X-HTTP-Method-Override", method)
+            .build();
+
+        let mut query_url = url.clone();
+        query_url.query_pairs_mut().append_pair("_method", method);
+        let query_req = client.request(Method::POST, query_url.as_str()).build();
+
+        let form_req = client
+            .request(Method::POST, url.as_str())
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body(format!("_method={}", method))
+            .build();
+
+        let tricks = [
+            (format!("X-HTTP-Method-Override: {}", method), header_req),
+            (format!("?_method={}", method), query_req),
+            (format!("_method={} (form)", method), form_req),
+        ];
+        for (trick, req) in tricks {
+            let req = match req {
+                Ok(req) => req,
+                Err(e) => {
+                    tracing::debug!(error = %e, "failed to build the request");
+                    continue;
+                }
+            };
+            let resp = match client.execute(req).await {
+                Ok(resp) => resp,
+                Err(e) => {
+                    tracing::debug!(error = %e, "request failed");
+                    continue;
+                }
+            };
+            let status = resp.status();
+            if status != target_status && !status.is_client_error() && !status.is_server_error() {
+                pb.println(format!(
+                    "{} {} {}{}{} {}{}{}",
+                    "found method override ::".bold().green(),
+                    url.as_str().bold().blue(),
+                    "[".bold().white(),
+                    trick.bold().purple(),
+                    "]".bold().white(),
+                    "[".bold().white(),
+                    status.as_str().bold().cyan(),
+                    "]".bold().white(),
+                ));
+                let content_encoding = client::content_encoding(resp.headers());
+                let size = match resp.bytes().await {
+                    Ok(body) => utils::ResponseSize::of(&body),
+                    Err(_) => utils::ResponseSize::default(),
+                };
+                findings.push(notify_finding(
+                    job_settings,
+                    FindingKind::MethodOverride,
+                    url.as_str(),
+                    &trick,
+                    status,
+                    size,
+                    &content_encoding,
+                ));
+            }
+        }
+    }
+    findings
+}
+
+// retries a path returning 401/403 with a suffix appended to each of its segments,
+// a successful response means the acl and the backend disagree on the segment.
+async fn test_segment_suffixes(
+    pb: &ProgressBar,
+    client: &Client,
+    job_settings: &JobSettings,
+    url: &reqwest::Url,
+    target_status: StatusCode,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    let segments: Vec<&str> = url.path().split('/').filter(|s| !s.is_empty()).collect();
+    let origin = url.origin().ascii_serialization();
+    for (index, suffix) in iproduct!(0..segments.len(), SEGMENT_SUFFIXES) {
+        let mut path = segments.clone();
+        let segment = format!("{}{}", segments[index], suffix);
+        path[index] = &segment;
+        let mut suffixed_url = format!("{}/{}", origin, path.join("/"));
+        if url.path().ends_with('/') {
+            suffixed_url.push('/');
+        }
+        if let Some(query) = url.query() {
+            suffixed_url.push('?');
+            suffixed_url.push_str(query);
+        }
+
+        let req = match client.get(&suffixed_url).build() {
+            Ok(req) => req,
+            Err(e) => {
+                tracing::debug!(error = %e, "failed to build the request");
+                continue;
+            }
+        };
+        let resp = match client.execute(req).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                tracing::debug!(error = %e, "request failed");
+                continue;
+            }
+        };
+        let status = resp.status();
+        if status != target_status && !status.is_client_error() && !status.is_server_error() {
+            pb.println(format!(
+                "{} {} {}{}{} {}{}{}",
+                "found segment suffix bypass ::".bold().green(),
+                suffixed_url.bold().blue(),
+                "[".bold().white(),
+                suffix.bold().purple(),
+                "]".bold().white(),
+                "[".bold().white(),
+                status.as_str().bold().cyan(),
+                "]".bold().white(),
+            ));
+            let content_encoding = client::content_encoding(resp.headers());
+            let size = match resp.bytes().await {
+                Ok(body) => utils::ResponseSize::of(&body),
+                Err(_) => utils::ResponseSize::default(),
+            };
+            findings.push(notify_finding(
+                job_settings,
+                FindingKind::SegmentSuffix,
+                &suffixed_url,
+                suffix,
+                status,
+                size,
+                &content_encoding,
+            ));
+        }
+    }
+    findings
 }
 
 pub async fn save_traversals(_: ProgressBar, mut outfile: File, traversal: String) {
     let mut outbuf = traversal.as_bytes().to_owned();
     outbuf.extend_from_slice(b"\n");
-    if let Err(_) = outfile.write(&outbuf).await {
-        // pb.println(format!("failed to write output '{:?}': {:?}", outbuf, e));
-        return;
+    if let Err(e) = outfile.write(&outbuf).await {
+        tracing::warn!(error = %e, traversal = %traversal, "failed to write the output file");
     }
 }
@@ -0,0 +1,255 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+
+use futures::StreamExt;
+use hickory_resolver::{
+    config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+    TokioAsyncResolver,
+};
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use reqwest::Url;
+
+// the AddressFamily enum constrains which addresses we connect to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressFamily {
+    Any,
+    Ipv4,
+    Ipv6,
+}
+
+impl AddressFamily {
+    fn allows(&self, ip: &IpAddr) -> bool {
+        match self {
+            AddressFamily::Any => true,
+            AddressFamily::Ipv4 => ip.is_ipv4(),
+            AddressFamily::Ipv6 => ip.is_ipv6(),
+        }
+    }
+}
+
+// the Resolver struct resolves hostnames once and caches the addresses
+// for the duration of the scan, optionally through a user supplied dns server
+pub struct Resolver {
+    nameserver: Option<SocketAddr>,
+    family: AddressFamily,
+    inner: Option<Arc<TokioAsyncResolver>>,
+    cache: Arc<Mutex<HashMap<String, Vec<IpAddr>>>>,
+}
+
+impl Resolver {
+    pub fn new(nameserver: Option<SocketAddr>, family: AddressFamily) -> Resolver {
+        let inner = nameserver.map(|nameserver| {
+            let group =
+                NameServerConfigGroup::from_ips_clear(&[nameserver.ip()], nameserver.port(), true);
+            let config = ResolverConfig::from_parts(None, vec![], group);
+            Arc::new(TokioAsyncResolver::tokio(config, ResolverOpts::default()))
+        });
+        Resolver {
+            nameserver,
+            family,
+            inner,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    // resolves the hostnames of all the urls up front so the workers
+    // only ever hit the cache, returns the amount of resolved hosts.
+    pub async fn preresolve(&self, urls: &[String]) -> usize {
+        let mut hosts = vec![];
+        for url in urls {
+            let url = match Url::parse(url) {
+                Ok(url) => url,
+                Err(_) => continue,
+            };
+            if let Some(host) = url.domain() {
+                hosts.push(host.to_string());
+            }
+        }
+        hosts.sort();
+        hosts.dedup();
+
+        let lookups = futures::stream::iter(hosts)
+            .map(|host| {
+                let inner = self.inner.clone();
+                let cache = self.cache.clone();
+                let family = self.family;
+                async move { cached_lookup(inner, cache, family, &host).await.is_ok() }
+            })
+            .buffer_unordered(50);
+        lookups
+            .filter(|ok| futures::future::ready(*ok))
+            .count()
+            .await
+    }
+}
+
+impl Resolver {
+    // returns the cached addresses of a host without doing a lookup.
+    pub fn cached(&self, host: &str) -> Option<Vec<IpAddr>> {
+        self.cache.lock().unwrap().get(host).cloned()
+    }
+}
+
+impl fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Resolver")
+            .field("nameserver", &self.nameserver)
+            .field("family", &self.family)
+            .finish()
+    }
+}
+
+impl Resolve for Resolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let inner = self.inner.clone();
+        let cache = self.cache.clone();
+        let family = self.family;
+        Box::pin(async move {
+            let ips = cached_lookup(inner, cache, family, name.as_str()).await?;
+            let addrs: Vec<SocketAddr> = ips.into_iter().map(|ip| SocketAddr::new(ip, 0)).collect();
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+// looks up the host in the cache and falls back to a real lookup on a miss,
+// only the addresses of the requested family are returned.
+async fn cached_lookup(
+    inner: Option<Arc<TokioAsyncResolver>>,
+    cache: Arc<Mutex<HashMap<String, Vec<IpAddr>>>>,
+    family: AddressFamily,
+    host: &str,
+) -> Result<Vec<IpAddr>, Box<dyn Error + Send + Sync + 'static>> {
+    let cached = cache.lock().unwrap().get(host).cloned();
+    let ips = match cached {
+        Some(ips) => ips,
+        None => {
+            let ips: Vec<IpAddr> = match inner {
+                Some(inner) => inner.lookup_ip(host).await?.iter().collect(),
+                None => tokio::net::lookup_host((host, 0))
+                    .await?
+                    .map(|addr| addr.ip())
+                    .collect(),
+            };
+            cache.lock().unwrap().insert(host.to_string(), ips.clone());
+            ips
+        }
+    };
+
+    let ips: Vec<IpAddr> = ips.into_iter().filter(|ip| family.allows(ip)).collect();
+    if ips.is_empty() {
+        return Err(format!("no {:?} address found for {}", family, host).into());
+    }
+    Ok(ips)
+}
+
+// rewrites the targets so the tls sni and the host header carry the --sni name while
+// the connection goes to --connect-to, or to the original address of the target.
+// the routing is pinned through resolve overrides since reqwest derives the sni from the url.
+pub fn route_targets(
+    urls: Vec<String>,
+    sni: &str,
+    connect_to: Option<SocketAddr>,
+    resolver: &Resolver,
+    overrides: &mut Vec<(String, SocketAddr)>,
+) -> Vec<String> {
+    let mut routed = vec![];
+    for url in urls {
+        let mut parsed = match Url::parse(&url) {
+            Ok(parsed) => parsed,
+            Err(_) => {
+                routed.push(url);
+                continue;
+            }
+        };
+        let host = match parsed.host_str() {
+            Some(host) => host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string(),
+            None => {
+                routed.push(url);
+                continue;
+            }
+        };
+
+        let addr = match connect_to {
+            Some(connect_to) => Some(connect_to.ip()),
+            None => match host.parse::<IpAddr>() {
+                Ok(ip) => Some(ip),
+                Err(_) => resolver.cached(&host).and_then(|ips| ips.first().copied()),
+            },
+        };
+        let addr = match addr {
+            Some(addr) => addr,
+            None => {
+                routed.push(url);
+                continue;
+            }
+        };
+
+        if let Some(connect_to) = connect_to {
+            if parsed.set_port(Some(connect_to.port())).is_err() {
+                routed.push(url);
+                continue;
+            }
+        }
+
+        let name = if sni.is_empty() {
+            host
+        } else {
+            sni.to_string()
+        };
+        if name.parse::<IpAddr>().is_ok() {
+            // ip literals never hit the resolver so we connect to the address directly.
+            if parsed.set_ip_host(addr).is_err() {
+                routed.push(url);
+                continue;
+            }
+        } else {
+            if parsed.set_host(Some(&name)).is_err() {
+                routed.push(url);
+                continue;
+            }
+            overrides.push((name, SocketAddr::new(addr, 0)));
+        }
+        routed.push(parsed.to_string());
+    }
+    routed
+}
+
+// parses a --resolve override in the curl style host:port:ip format.
+pub fn parse_resolve_override(value: &str) -> Option<(String, SocketAddr)> {
+    let mut parts = value.splitn(3, ':');
+    let host = parts.next()?.trim();
+    let port = parts.next()?.trim().parse::<u16>().ok()?;
+    let ip = parts
+        .next()?
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .ok()?;
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), SocketAddr::new(ip, port)))
+}
+
+// parses the --dns-server value, the port defaults to 53 when omitted.
+pub fn parse_dns_server(value: &str) -> Option<SocketAddr> {
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Some(addr);
+    }
+    match value.parse::<IpAddr>() {
+        Ok(ip) => Some(SocketAddr::new(ip, 53)),
+        Err(_) => None,
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Mutex;
+
+use futures::Stream;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::fingerprint::TargetFingerprint;
+use crate::notify::FindingKind;
+use crate::output::ci::Severity;
+use crate::transform::FamilyOutcome;
+use crate::utils::ResponseSize;
+
+// the ScanEvent enum holds what happened during the scan, streamed to the
+// subscribers as it happens instead of after the report
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ScanEvent {
+    // the profile of a target is ready
+    FingerprintCompleted {
+        url: String,
+        fingerprint: TargetFingerprint,
+    },
+    // an internal doc root was confirmed, or a follow up stage found something behind it
+    Finding {
+        kind: FindingKind,
+        severity: Severity,
+        url: String,
+        payload: String,
+        status: u16,
+        #[serde(flatten)]
+        size: ResponseSize,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        #[serde(skip_serializing_if = "String::is_empty")]
+        evidence: String,
+        #[serde(skip_serializing_if = "String::is_empty")]
+        family: String,
+        #[serde(skip_serializing_if = "String::is_empty")]
+        content_encoding: String,
+    },
+    // the bruteforcer found a route behind an internal doc root
+    RouteDiscovered {
+        url: String,
+        content_length: String,
+        #[serde(flatten)]
+        size: ResponseSize,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<String>,
+        #[serde(skip_serializing_if = "String::is_empty")]
+        content_encoding: String,
+    },
+    // the state of the progress bar, every second
+    Progress {
+        stage: String,
+        done: u64,
+        total: u64,
+        rps: u64,
+        eta: u64,
+        errors: u64,
+    },
+    // the outcomes of the payload families per waf, once every stage is over
+    FamilySummary {
+        families: Vec<FamilyOutcome>,
+    },
+    // every stage of the runner is over, the last event of a scan
+    ScanCompleted {
+        cancelled: bool,
+    },
+}
+
+// the events are only kept once something subscribed
+static EVENTS: Mutex<Option<broadcast::Sender<ScanEvent>>> = Mutex::new(None);
+
+// sends the event to the subscribers, if any.
+pub fn publish(event: ScanEvent) {
+    if let Some(tx) = EVENTS.lock().unwrap().as_ref() {
+        let _ = tx.send(event);
+    }
+}
+
+// returns the stream of the events published from now on, it ends once the
+// scan is over and the events are closed. a subscriber that falls too far
+// behind skips the events it missed.
+pub fn subscribe() -> impl Stream<Item = ScanEvent> {
+    let rx = EVENTS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| broadcast::channel(1024).0)
+        .subscribe();
+    futures::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some((event, rx)),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+// ends the streams of the subscribers once they received the pending events.
+pub fn close() {
+    EVENTS.lock().unwrap().take();
+}
@@ -0,0 +1,796 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::{OnceLock, RwLock};
+
+use base64::Engine;
+use colored::Colorize;
+use futures::StreamExt;
+use rand::Rng;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_ENCODING, SERVER, SET_COOKIE};
+use reqwest::tls::TlsInfo;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+use crate::client::Client;
+use crate::events::{self, ScanEvent};
+use crate::waf;
+
+// the Probe enum holds the canonical probes sent to profile the normalization,
+// each probe stands for the payload family that relies on the same quirk
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum Probe {
+    DotDot,
+    EncodedDot,
+    DoubleSlash,
+    Semicolon,
+}
+
+impl Probe {
+    const ALL: [Probe; 4] = [
+        Probe::DotDot,
+        Probe::EncodedDot,
+        Probe::DoubleSlash,
+        Probe::Semicolon,
+    ];
+
+    // builds the probe path from a segment that can't exist and the target path.
+    fn path(&self, missing: &str, target: &str) -> String {
+        match self {
+            Probe::DotDot => format!("/{}/../{}", missing, target),
+            Probe::EncodedDot => format!("/{}/%2e%2e/{}", missing, target),
+            Probe::DoubleSlash => format!("//{}", target),
+            Probe::Semicolon => format!("/{}/..;/{}", missing, target),
+        }
+    }
+
+    // returns true when the payload is built on the construct of the probe, a
+    // rejected probe only rules out the payloads that send it as is.
+    fn matches(&self, payload: &str) -> bool {
+        let payload = payload.to_lowercase();
+        match self {
+            Probe::DotDot => !payload.is_empty() && payload.replace("../", "").is_empty(),
+            Probe::EncodedDot => payload.contains("%2e%2e/"),
+            Probe::DoubleSlash => payload.contains("//"),
+            Probe::Semicolon => payload.contains("..;/"),
+        }
+    }
+}
+
+// the Normalization enum classifies how the front-end handled a probe
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Normalization {
+    // the probe was resolved and served the target
+    Normalized,
+    // the probe was forwarded as is
+    Passed,
+    // the probe was refused before reaching a backend
+    Rejected,
+}
+
+// the Tech enum holds the backends with their own traversal quirks
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all(deserialize = "lowercase"))]
+pub enum Tech {
+    Nginx,
+    Iis,
+    Tomcat,
+    Express,
+}
+
+impl Tech {
+    // detects the backend from the response headers, the hints of an application
+    // server win over the server header of a proxy in front of it.
+    pub fn detect(headers: &HeaderMap) -> Option<Tech> {
+        let header = |name: &str| match headers.get(name) {
+            Some(value) => value.to_str().unwrap_or("").to_lowercase(),
+            None => "".to_string(),
+        };
+        let server = header("server");
+        let powered_by = header("x-powered-by");
+        let jsessionid = headers
+            .get_all(SET_COOKIE)
+            .iter()
+            .any(|cookie| cookie.to_str().unwrap_or("").starts_with("JSESSIONID="));
+        if jsessionid || server.contains("tomcat") || server.contains("coyote") {
+            Some(Tech::Tomcat)
+        } else if powered_by.contains("express") {
+            Some(Tech::Express)
+        } else if server.contains("microsoft-iis") || powered_by.contains("asp.net") {
+            Some(Tech::Iis)
+        } else if server.starts_with("nginx") {
+            Some(Tech::Nginx)
+        } else {
+            None
+        }
+    }
+
+    // returns true when the backend interprets the quirk of the payload family,
+    // backslashes are only separators on iis and express never strips `;` parameters.
+    fn interprets(&self, payload: &str) -> bool {
+        let payload = payload.to_lowercase();
+        let backslash =
+            payload.contains('\\') || payload.contains("%5c") || payload.contains("%ef%bc%bc");
+        let semicolon = payload.contains(';');
+        match self {
+            Tech::Iis => true,
+            Tech::Tomcat | Tech::Nginx => !backslash,
+            Tech::Express => !backslash && !semicolon,
+        }
+    }
+}
+
+// the TechRule struct describes a product loaded with --signatures, the header
+// checks match a substring of the value and the cookie checks the start of the name
+#[derive(Clone, Debug)]
+pub struct TechRule {
+    pub product: &'static str,
+    pub tech: Option<Tech>,
+    pub headers: &'static [(&'static str, &'static str)],
+    pub cookies: &'static [&'static str],
+}
+
+static TECH_RULES: OnceLock<Vec<TechRule>> = OnceLock::new();
+
+// merges the rules with the built-in detection, only the first call takes effect.
+pub fn add_tech_rules(rules: Vec<TechRule>) {
+    let _ = TECH_RULES.set(rules);
+}
+
+impl TechRule {
+    fn matches(&self, headers: &HeaderMap) -> bool {
+        let header_match = self.headers.iter().any(|(name, value)| {
+            headers.get_all(*name).iter().any(|header| {
+                value.is_empty() || header.to_str().unwrap_or("").to_lowercase().contains(value)
+            })
+        });
+        let cookie_match = headers.get_all(SET_COOKIE).iter().any(|cookie| {
+            let cookie = cookie.to_str().unwrap_or("");
+            self.cookies.iter().any(|name| cookie.starts_with(name))
+        });
+        header_match || cookie_match
+    }
+}
+
+// the custom rules win over the built-in detection, they describe the
+// products a team knows sit in front of its targets.
+fn detect_tech_from_rules(headers: &HeaderMap) -> Option<&'static TechRule> {
+    TECH_RULES.get()?.iter().find(|rule| rule.matches(headers))
+}
+
+// the TargetFingerprint struct holds what we learned about a target before the scan
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TargetFingerprint {
+    pub normalization: HashMap<Probe, Normalization>,
+    pub tech: Option<Tech>,
+    pub product: Option<String>,
+    pub certificate: Option<CertificateInfo>,
+    pub cdn: Option<Cdn>,
+    // the title httpx saw, the profile itself doesn't read the bodies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    // the Content-Encoding the target served its response with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<String>,
+}
+
+// the HttpxTarget struct holds what httpx already learned about a target, its
+// -json lines can be given as the urls and spare the profile the requests it
+// would send to learn the same
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct HttpxTarget {
+    pub status_code: u16,
+    pub title: String,
+    pub tech: Vec<String>,
+    pub webserver: String,
+}
+
+// parses an httpx json line, the other json targets have no status_code.
+pub fn parse_httpx(line: &str) -> Option<HttpxTarget> {
+    if !line.trim_start().starts_with('{') {
+        return None;
+    }
+    let target: HttpxTarget = serde_json::from_str(line).ok()?;
+    if target.status_code == 0 {
+        return None;
+    }
+    Some(target)
+}
+
+impl HttpxTarget {
+    // the backend among the technologies httpx detected, eg `Nginx:1.25.3` or
+    // `Apache Tomcat`, with the same precedence as the header detection.
+    fn detect_tech(&self) -> Option<Tech> {
+        let names: Vec<String> = self.tech.iter().map(|name| name.to_lowercase()).collect();
+        let any = |matches: &dyn Fn(&str) -> bool| names.iter().any(|name| matches(name));
+        if any(&|name| name.contains("tomcat")) {
+            Some(Tech::Tomcat)
+        } else if any(&|name| name.starts_with("express")) {
+            Some(Tech::Express)
+        } else if any(&|name| name.starts_with("iis") || name.contains("asp.net")) {
+            Some(Tech::Iis)
+        } else if any(&|name| name.starts_with("nginx")) {
+            Some(Tech::Nginx)
+        } else {
+            None
+        }
+    }
+}
+
+// the Cdn enum holds the cdns a target can be served from, a traversal the edge
+// refuses can still work against the origin behind it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Cdn {
+    Cloudflare,
+    Akamai,
+    Fastly,
+    CloudFront,
+    AzureFrontDoor,
+}
+
+impl fmt::Display for Cdn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Cdn::Cloudflare => "Cloudflare",
+            Cdn::Akamai => "Akamai",
+            Cdn::Fastly => "Fastly",
+            Cdn::CloudFront => "CloudFront",
+            Cdn::AzureFrontDoor => "Azure Front Door",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Cdn {
+    // detects the cdn from the response headers, then from the issuer and the
+    // names of the certificate the edge served.
+    pub fn detect(headers: &HeaderMap, certificate: Option<&CertificateInfo>) -> Option<Cdn> {
+        let header = |name: &str| match headers.get(name) {
+            Some(value) => value.to_str().unwrap_or("").to_lowercase(),
+            None => "".to_string(),
+        };
+        let server = header("server");
+        let via = header("via");
+        if headers.contains_key("cf-ray") || server == "cloudflare" {
+            return Some(Cdn::Cloudflare);
+        }
+        if headers.contains_key("x-amz-cf-id") || via.contains("cloudfront") {
+            return Some(Cdn::CloudFront);
+        }
+        if headers.contains_key("x-fastly-request-id")
+            || header("x-served-by").starts_with("cache-")
+        {
+            return Some(Cdn::Fastly);
+        }
+        if headers.contains_key("akamai-grn") || server.starts_with("akamaighost") {
+            return Some(Cdn::Akamai);
+        }
+        if headers.contains_key("x-azure-ref") {
+            return Some(Cdn::AzureFrontDoor);
+        }
+
+        let certificate = certificate?;
+        let issuer = certificate.issuer.to_lowercase();
+        let names = |suffix: &str| certificate.sans.iter().any(|san| san.ends_with(suffix));
+        if issuer.contains("cloudflare") {
+            Some(Cdn::Cloudflare)
+        } else if names(".cloudfront.net") {
+            Some(Cdn::CloudFront)
+        } else if names(".akamaized.net") || names(".edgekey.net") {
+            Some(Cdn::Akamai)
+        } else if names(".fastly.net") || names(".fastlylb.net") {
+            Some(Cdn::Fastly)
+        } else if names(".azurefd.net") {
+            Some(Cdn::AzureFrontDoor)
+        } else {
+            None
+        }
+    }
+}
+
+// the OriginHint struct holds a target served from a cdn and an input url that
+// looks like the origin behind it
+#[derive(Clone, Debug)]
+pub struct OriginHint {
+    pub url: String,
+    pub cdn: Cdn,
+    pub origin: String,
+}
+
+// the CertificateInfo struct holds the metadata of the certificate the target served,
+// its names often reveal the real backend host behind a cdn
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub sans: Vec<String>,
+}
+
+impl CertificateInfo {
+    // parses the der encoded peer certificate.
+    pub fn parse(der: &[u8]) -> Option<CertificateInfo> {
+        let (_, cert) = X509Certificate::from_der(der).ok()?;
+        let mut sans = vec![];
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::DNSName(dns) => sans.push(dns.to_string()),
+                    GeneralName::IPAddress(ip) => match ip.len() {
+                        4 => sans.push(IpAddr::from(<[u8; 4]>::try_from(*ip).ok()?).to_string()),
+                        16 => sans.push(IpAddr::from(<[u8; 16]>::try_from(*ip).ok()?).to_string()),
+                        _ => continue,
+                    },
+                    _ => continue,
+                }
+            }
+        }
+        Some(CertificateInfo {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            sans,
+        })
+    }
+
+    // returns true when one of the names of the certificate covers the host,
+    // a wildcard only covers a single label.
+    pub fn covers(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.sans.iter().any(|san| {
+            let san = san.to_lowercase();
+            match san.strip_prefix("*.") {
+                Some(parent) => host
+                    .split_once('.')
+                    .is_some_and(|(_, host_parent)| host_parent == parent),
+                None => san == host,
+            }
+        })
+    }
+}
+
+// returns true for the %u encoded payloads, only iis decodes them. the overlong
+// utf-8 ones are left to every backend since tomcat and old apache decode them too.
+fn is_iis_only(payload: &str) -> bool {
+    payload.to_lowercase().contains("%u")
+}
+
+impl TargetFingerprint {
+    // a payload can't work when the front-end refuses the quirk it relies on or the
+    // backend doesn't interpret it, targets that couldn't be profiled allow every payload.
+    pub fn allows(&self, payload: &str) -> bool {
+        if let Some(tech) = self.tech {
+            if (is_iis_only(payload) && tech != Tech::Iis) || !tech.interprets(payload) {
+                return false;
+            }
+        }
+        Probe::ALL.iter().all(|probe| {
+            !probe.matches(payload)
+                || self.normalization.get(probe) != Some(&Normalization::Rejected)
+        })
+    }
+}
+
+// the BlockPage enum classifies a response served by a waf instead of the target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockPage {
+    // the request was refused
+    Blocked,
+    // the waf asks for a captcha or a javascript challenge
+    Challenge,
+}
+
+// the markers of challenge pages and generic block pages in response bodies
+const CHALLENGE_MARKERS: [&str; 6] = [
+    "challenge-platform",
+    "cf-chl",
+    "g-recaptcha",
+    "h-captcha",
+    "captcha",
+    "just a moment...",
+];
+const BLOCK_MARKERS: [&str; 5] = [
+    "request blocked",
+    "request rejected",
+    "your support id is",
+    "web application firewall",
+    "has been blocked",
+];
+
+// returns true for the statuses block pages are served with.
+pub fn is_blocking_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 403 | 406 | 429 | 503)
+}
+
+// classifies a single response as a waf block page, a 403 without the evidence
+// of a block is the target's own and returns None, even behind a cdn.
+pub fn classify_block(status: StatusCode, headers: &HeaderMap, body: &str) -> Option<BlockPage> {
+    if !is_blocking_status(status) {
+        return None;
+    }
+    let lowercase_body = body.to_lowercase();
+    if CHALLENGE_MARKERS
+        .iter()
+        .any(|marker| lowercase_body.contains(marker))
+    {
+        return Some(BlockPage::Challenge);
+    }
+    if waf::detect(status, headers, body).is_some()
+        || BLOCK_MARKERS
+            .iter()
+            .any(|marker| lowercase_body.contains(marker))
+    {
+        return Some(BlockPage::Blocked);
+    }
+    None
+}
+
+// the bundled favicon hash database in the shodan http.favicon.hash format
+const FAVICON_HASHES: [(i32, &str, Option<Tech>); 5] = [
+    (116323821, "Spring Boot", Some(Tech::Tomcat)),
+    (-297069493, "Apache Tomcat", Some(Tech::Tomcat)),
+    (81586312, "Jenkins", None),
+    (-335242539, "F5 BIG-IP", None),
+    (1485257654, "SonarQube", None),
+];
+
+// the 32 bit murmur3 hash with a zero seed, as returned by mmh3.hash.
+fn murmur3_32(data: &[u8]) -> i32 {
+    const C1: u32 = 0xcc9e2d51;
+    const C2: u32 = 0x1b873593;
+    let mut hash: u32 = 0;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash ^= k;
+        hash = hash
+            .rotate_left(13)
+            .wrapping_mul(5)
+            .wrapping_add(0xe6546b64);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        let mut k: u32 = 0;
+        for (i, byte) in tail.iter().enumerate() {
+            k |= (*byte as u32) << (8 * i);
+        }
+        k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        hash ^= k;
+    }
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85ebca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2ae35);
+    hash ^= hash >> 16;
+    hash as i32
+}
+
+// hashes the favicon the way shodan does, murmur3 over the base64 encoding
+// wrapped at 76 characters with a trailing newline.
+pub fn favicon_hash(favicon: &[u8]) -> i32 {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(favicon);
+    let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / 76 + 1);
+    for (i, c) in encoded.chars().enumerate() {
+        if i > 0 && i % 76 == 0 {
+            wrapped.push('\n');
+        }
+        wrapped.push(c);
+    }
+    wrapped.push('\n');
+    murmur3_32(wrapped.as_bytes())
+}
+
+// falls back to the favicon of the target when the headers gave nothing away.
+async fn detect_tech_from_favicon(
+    client: &Client,
+    origin: &str,
+) -> Option<(&'static str, Option<Tech>)> {
+    let req = client.get(format!("{}/favicon.ico", origin)).build().ok()?;
+    let resp = client.execute(req).await.ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    let favicon = resp.bytes().await.ok()?;
+    let hash = favicon_hash(&favicon);
+    FAVICON_HASHES
+        .iter()
+        .find(|(known, _, _)| *known == hash)
+        .map(|(_, product, tech)| (*product, *tech))
+}
+
+async fn fetch(
+    client: &Client,
+    url: &str,
+) -> Option<(StatusCode, HeaderMap, Option<CertificateInfo>)> {
+    let req = client.get(url).build().ok()?;
+    let resp = client.execute(req).await.ok()?;
+    let certificate = resp
+        .extensions()
+        .get::<TlsInfo>()
+        .and_then(|tls_info| tls_info.peer_certificate())
+        .and_then(CertificateInfo::parse);
+    Some((resp.status(), resp.headers().clone(), certificate))
+}
+
+// detects the backend of the target, then sends the canonical probes and
+// classifies the answers against the status of the target itself. what httpx
+// already knew about the target replaces the request to the target and the
+// favicon.
+pub async fn profile(client: &Client, url: &str, known: Option<&HttpxTarget>) -> TargetFingerprint {
+    let mut fingerprint = TargetFingerprint::default();
+    let parsed = match reqwest::Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(_) => return fingerprint,
+    };
+    let known =
+        known.and_then(|known| Some((StatusCode::from_u16(known.status_code).ok()?, known)));
+    let target_status = match known {
+        Some((target_status, known)) => {
+            let mut headers = HeaderMap::new();
+            if let Ok(server) = HeaderValue::from_str(&known.webserver) {
+                headers.insert(SERVER, server);
+            }
+            fingerprint.tech = known.detect_tech().or(Tech::detect(&headers));
+            if let Some(rule) = detect_tech_from_rules(&headers) {
+                fingerprint.product = Some(rule.product.to_string());
+                fingerprint.tech = rule.tech.or(fingerprint.tech);
+            }
+            fingerprint.cdn = Cdn::detect(&headers, None);
+            if !known.title.is_empty() {
+                fingerprint.title = Some(known.title.clone());
+            }
+            target_status
+        }
+        None => match fetch(client, url).await {
+            Some((target_status, headers, certificate)) => {
+                fingerprint.tech = Tech::detect(&headers);
+                if let Some(rule) = detect_tech_from_rules(&headers) {
+                    fingerprint.product = Some(rule.product.to_string());
+                    fingerprint.tech = rule.tech.or(fingerprint.tech);
+                }
+                fingerprint.cdn = Cdn::detect(&headers, certificate.as_ref());
+                fingerprint.certificate = certificate;
+                fingerprint.content_encoding = headers
+                    .get(CONTENT_ENCODING)
+                    .and_then(|encoding| encoding.to_str().ok())
+                    .map(str::to_string);
+                target_status
+            }
+            None => return fingerprint,
+        },
+    };
+
+    let origin = parsed.origin().ascii_serialization();
+    if known.is_none() && fingerprint.tech.is_none() && fingerprint.product.is_none() {
+        if let Some((product, tech)) = detect_tech_from_favicon(client, &origin).await {
+            fingerprint.product = Some(product.to_string());
+            fingerprint.tech = tech;
+        }
+    }
+    let target = parsed.path().trim_start_matches('/').to_string();
+    let missing = format!("pb{}", rand::thread_rng().gen::<u32>());
+    for probe in Probe::ALL {
+        let probe_url = format!("{}{}", origin, probe.path(&missing, &target));
+        let status = match fetch(client, &probe_url).await {
+            Some((status, _, _)) => status,
+            None => continue,
+        };
+        let normalization = if status == target_status {
+            Normalization::Normalized
+        } else if status == StatusCode::BAD_REQUEST || status == StatusCode::FORBIDDEN {
+            Normalization::Rejected
+        } else {
+            Normalization::Passed
+        };
+        fingerprint.normalization.insert(probe, normalization);
+    }
+    fingerprint
+}
+
+// prints what was learned about the target, one line per finding.
+pub fn print_fingerprint(url: &str, fingerprint: &TargetFingerprint) {
+    let or_unknown = |value: Option<String>| value.unwrap_or_else(|| "unknown".to_string());
+    println!(
+        "{} {} {}{}{} {}{}{} {}{}{}",
+        "fingerprint ::".bold().green(),
+        url.bold().blue(),
+        "[".bold().white(),
+        or_unknown(fingerprint.tech.map(|tech| format!("{:?}", tech)))
+            .bold()
+            .purple(),
+        "]".bold().white(),
+        "[".bold().white(),
+        or_unknown(fingerprint.product.clone()).bold().purple(),
+        "]".bold().white(),
+        "[".bold().white(),
+        or_unknown(fingerprint.cdn.map(|cdn| cdn.to_string()))
+            .bold()
+            .cyan(),
+        "]".bold().white(),
+    );
+    for probe in Probe::ALL {
+        if let Some(normalization) = fingerprint.normalization.get(&probe) {
+            println!(
+                "{} {:?} {}{}{}",
+                "normalization ::".bold().green(),
+                probe,
+                "[".bold().white(),
+                format!("{:?}", normalization).bold().cyan(),
+                "]".bold().white(),
+            );
+        }
+    }
+    if let Some(content_encoding) = &fingerprint.content_encoding {
+        println!(
+            "{} {}{}{}",
+            "content encoding ::".bold().green(),
+            "[".bold().white(),
+            content_encoding.bold().cyan(),
+            "]".bold().white(),
+        );
+    }
+    if let Some(certificate) = &fingerprint.certificate {
+        println!(
+            "{} {}{}{} {}{}{} {}{}{}",
+            "certificate ::".bold().green(),
+            "[".bold().white(),
+            certificate.subject.bold().purple(),
+            "]".bold().white(),
+            "[".bold().white(),
+            certificate.issuer.bold().cyan(),
+            "]".bold().white(),
+            "[".bold().white(),
+            certificate.sans.join(", ").bold().purple(),
+            "]".bold().white(),
+        );
+    }
+}
+
+// profiles the target and publishes its fingerprint.
+pub async fn profile_target(
+    client: &Client,
+    url: &str,
+    known: Option<&HttpxTarget>,
+) -> TargetFingerprint {
+    let fingerprint = profile(client, url, known).await;
+    events::publish(ScanEvent::FingerprintCompleted {
+        url: url.to_string(),
+        fingerprint: fingerprint.clone(),
+    });
+    fingerprint
+}
+
+// profiles all the targets concurrently.
+pub async fn profile_targets(
+    client: &Client,
+    urls: &[String],
+    known: &HashMap<String, HttpxTarget>,
+    concurrency: usize,
+) -> HashMap<String, TargetFingerprint> {
+    futures::stream::iter(urls.iter().cloned())
+        .map(|url| async move {
+            let fingerprint = profile_target(client, &url, known.get(&url)).await;
+            (url, fingerprint)
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+// the Fingerprints struct holds the profiles of the targets, shared by the
+// senders and the report so the targets streamed in are profiled on arrival
+#[derive(Debug, Default)]
+pub struct Fingerprints {
+    targets: RwLock<HashMap<String, TargetFingerprint>>,
+}
+
+impl Fingerprints {
+    pub fn new(targets: HashMap<String, TargetFingerprint>) -> Fingerprints {
+        Fingerprints {
+            targets: RwLock::new(targets),
+        }
+    }
+
+    pub fn insert(&self, url: &str, fingerprint: TargetFingerprint) {
+        self.targets
+            .write()
+            .unwrap()
+            .insert(url.to_string(), fingerprint);
+    }
+
+    pub fn get(&self, url: &str) -> Option<TargetFingerprint> {
+        self.targets.read().unwrap().get(url).cloned()
+    }
+
+    // the profiles of every target so far.
+    pub fn all(&self) -> HashMap<String, TargetFingerprint> {
+        self.targets.read().unwrap().clone()
+    }
+}
+
+// the registrable part of the host, naively its last two labels.
+fn base_domain(host: &str) -> String {
+    let labels: Vec<&str> = host.rsplitn(3, '.').collect();
+    match labels.as_slice() {
+        [tld, domain, ..] => format!("{}.{}", domain, tld),
+        _ => host.to_string(),
+    }
+}
+
+// correlates the targets served from a cdn with the input urls that look like their
+// origin: urls that aren't behind a cdn themselves and either serve a certificate
+// naming the cdn host, or are an ip or a host on the same domain.
+pub fn origin_hints(fingerprints: &HashMap<String, TargetFingerprint>) -> Vec<OriginHint> {
+    let host_of = |url: &str| {
+        reqwest::Url::parse(url).ok().and_then(|url| {
+            url.host_str()
+                .map(|host| host.trim_matches(['[', ']']).to_lowercase())
+        })
+    };
+    let mut hints = vec![];
+    for (url, fingerprint) in fingerprints {
+        let (cdn, host) = match (fingerprint.cdn, host_of(url)) {
+            (Some(cdn), Some(host)) => (cdn, host),
+            _ => continue,
+        };
+        for (origin, origin_fingerprint) in fingerprints {
+            let origin_host = match host_of(origin) {
+                Some(origin_host) => origin_host,
+                None => continue,
+            };
+            if origin_fingerprint.cdn.is_some() || origin_host == host {
+                continue;
+            }
+            let names_host = origin_fingerprint
+                .certificate
+                .as_ref()
+                .is_some_and(|certificate| certificate.covers(&host));
+            let is_ip = origin_host.parse::<IpAddr>().is_ok();
+            let same_domain = !is_ip && base_domain(&origin_host) == base_domain(&host);
+            if names_host || (is_ip && origin_fingerprint.certificate.is_none()) || same_domain {
+                hints.push(OriginHint {
+                    url: url.clone(),
+                    cdn,
+                    origin: origin.clone(),
+                });
+            }
+        }
+    }
+    hints.sort_by(|a, b| (&a.url, &a.origin).cmp(&(&b.url, &b.origin)));
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(tech: Option<Tech>) -> TargetFingerprint {
+        TargetFingerprint {
+            tech,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overlong_utf8_is_sent_to_every_backend() {
+        for tech in [Some(Tech::Iis), Some(Tech::Tomcat), Some(Tech::Nginx), None] {
+            assert!(fingerprint(tech).allows("..%c0%af"));
+            assert!(fingerprint(tech).allows("..%c1%9c"));
+        }
+    }
+
+    #[test]
+    fn percent_u_is_only_sent_to_iis() {
+        assert!(fingerprint(Some(Tech::Iis)).allows("%uff0e%uff0e%uff0f"));
+        assert!(!fingerprint(Some(Tech::Tomcat)).allows("%uff0e%uff0e%uff0f"));
+        assert!(!fingerprint(Some(Tech::Express)).allows("..%uff0f"));
+    }
+
+    #[test]
+    fn unknown_backend_allows_every_payload() {
+        assert!(fingerprint(None).allows("%uff0e%uff0e%u2215"));
+        assert!(fingerprint(None).allows("..\\"));
+    }
+}
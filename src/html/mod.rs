@@ -0,0 +1,223 @@
+// the elements whose content is text up to their end tag, the tags inside
+// them aren't tags.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "title", "textarea"];
+
+// the Token enum is a piece of the html, the names are lowercase
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token<'a> {
+    Start {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    End(String),
+    Text(&'a str),
+}
+
+// the Tokenizer struct reads the html a token at a time, the comments, the
+// doctype and the processing instructions are skipped. it doesn't build a
+// tree, so broken markup only costs the tokens it breaks.
+pub struct Tokenizer<'a> {
+    html: &'a str,
+    position: usize,
+    raw_text: Option<String>,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(html: &'a str) -> Tokenizer<'a> {
+        Tokenizer {
+            html,
+            position: 0,
+            raw_text: None,
+        }
+    }
+
+    // the content of a raw text element, up to its end tag or the end of the html.
+    fn raw_text(&mut self, name: &str) -> &'a str {
+        let rest = &self.html[self.position..];
+        let end = rest
+            .to_ascii_lowercase()
+            .find(&format!("</{}", name))
+            .unwrap_or(rest.len());
+        self.position += end;
+        &rest[..end]
+    }
+
+    // skips past the end of the markup, the whole html when it is never closed.
+    fn skip_past(&mut self, end: &str) {
+        match self.html[self.position..].find(end) {
+            Some(at) => self.position += at + end.len(),
+            None => self.position = self.html.len(),
+        }
+    }
+
+    fn end_tag(&mut self) -> Token<'a> {
+        let rest = &self.html[self.position + 2..];
+        let name = rest
+            .split(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        self.skip_past(">");
+        Token::End(name)
+    }
+
+    fn start_tag(&mut self) -> Token<'a> {
+        let rest = &self.html[self.position + 1..];
+        let name_len = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_len].to_ascii_lowercase();
+        let (attributes, tag_len) = attributes(&rest[name_len..]);
+        self.position += 1 + name_len + tag_len;
+        if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+            self.raw_text = Some(name.clone());
+        }
+        Token::Start { name, attributes }
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        loop {
+            if self.position >= self.html.len() {
+                return None;
+            }
+            if let Some(name) = self.raw_text.take() {
+                let text = self.raw_text(&name);
+                if !text.is_empty() {
+                    return Some(Token::Text(text));
+                }
+                continue;
+            }
+            let rest = &self.html[self.position..];
+            if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.position += end;
+                return Some(Token::Text(&rest[..end]));
+            }
+            let next = rest[1..].chars().next();
+            if rest.starts_with("<!--") {
+                self.skip_past("-->");
+            } else if rest.starts_with("<!") || rest.starts_with("<?") {
+                self.skip_past(">");
+            } else if rest.starts_with("</") {
+                return Some(self.end_tag());
+            } else if next.is_some_and(|c| c.is_ascii_alphabetic()) {
+                return Some(self.start_tag());
+            } else {
+                // a < that doesn't open a tag is text.
+                let end = rest[1..].find('<').map_or(rest.len(), |at| at + 1);
+                self.position += end;
+                return Some(Token::Text(&rest[..end]));
+            }
+        }
+    }
+}
+
+// parses the attributes of a start tag up to its >, the names are lowercase
+// and the values are decoded. gives the length of the tag read.
+fn attributes(tag: &str) -> (Vec<(String, String)>, usize) {
+    let mut attributes = vec![];
+    let mut position = 0;
+    loop {
+        let rest = &tag[position..];
+        let skipped = rest
+            .find(|c: char| !c.is_whitespace() && c != '/')
+            .unwrap_or(rest.len());
+        position += skipped;
+        let rest = &tag[position..];
+        if rest.is_empty() {
+            return (attributes, position);
+        }
+        if rest.starts_with('>') {
+            return (attributes, position + 1);
+        }
+        let name_len = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(rest.len())
+            .max(1);
+        let name = rest[..name_len].to_ascii_lowercase();
+        position += name_len;
+        let rest = &tag[position..];
+        let before_value = rest.len() - rest.trim_start().len();
+        if !rest.trim_start().starts_with('=') {
+            attributes.push((name, String::new()));
+            continue;
+        }
+        position += before_value + 1;
+        let rest = &tag[position..];
+        let value_start = rest.len() - rest.trim_start().len();
+        position += value_start;
+        let rest = &tag[position..];
+        let value = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let end = rest[1..].find(quote).map_or(rest.len(), |at| at + 1);
+                position += (end + 1).min(rest.len());
+                &rest[1..end]
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == '>')
+                    .unwrap_or(rest.len());
+                position += end;
+                &rest[..end]
+            }
+        };
+        attributes.push((name, decode(value)));
+    }
+}
+
+// decodes the character references of the text, the unknown ones are kept.
+pub fn decode(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        decoded.push_str(&rest[..at]);
+        rest = &rest[at..];
+        let reference = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| Some((character(&rest[1..end + 1])?, end + 2)));
+        match reference {
+            Some((character, len)) => {
+                decoded.push(character);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn character(reference: &str) -> Option<char> {
+    if let Some(number) = reference.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse::<u32>().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    match reference {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => None,
+    }
+}
+
+// the text with its runs of whitespace collapsed into single spaces.
+pub fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use base64::Engine;
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::Deserialize;
+
+// the request headers that aren't replayed, they describe the connection or
+// the browser cache, or are set by the client itself. the cookies go to the
+// cookie jar.
+const SKIPPED_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "content-type",
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "te",
+    "upgrade",
+    "accept-encoding",
+    "user-agent",
+    "range",
+    "if-range",
+    "if-match",
+    "if-none-match",
+    "if-modified-since",
+    "if-unmodified-since",
+];
+
+// the TargetHeaders struct holds the request headers the targets were browsed
+// with, by origin, so the traversals of a target carry them too
+#[derive(Clone, Debug, Default)]
+pub struct TargetHeaders {
+    headers: HashMap<String, Vec<(HeaderName, String)>>,
+}
+
+// the Import struct holds the targets imported from the burp proxy history
+// and the har files, the cookies are given by origin in the order they were sent
+#[derive(Debug, Default)]
+pub struct Import {
+    pub urls: Vec<String>,
+    pub headers: TargetHeaders,
+    pub cookies: Vec<(String, String)>,
+}
+
+// the HarFile struct is the part of a har file the requests are read from
+#[derive(Debug, Deserialize)]
+struct HarFile {
+    log: HarLog,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarLog {
+    #[serde(default)]
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarEntry {
+    request: HarRequest,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarRequest {
+    url: String,
+    #[serde(default)]
+    headers: Vec<HarPair>,
+    #[serde(default)]
+    cookies: Vec<HarPair>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HarPair {
+    name: String,
+    value: String,
+}
+
+impl TargetHeaders {
+    // the headers to send to the url, none when its origin wasn't imported.
+    pub fn headers(&self, url: &str) -> &[(HeaderName, String)] {
+        if self.headers.is_empty() {
+            return &[];
+        }
+        match origin(url).and_then(|origin| self.headers.get(&origin)) {
+            Some(headers) => headers,
+            None => &[],
+        }
+    }
+
+    // a header sent again for the origin keeps its latest value.
+    fn insert(&mut self, origin: &str, name: HeaderName, value: String) {
+        let headers = self.headers.entry(origin.to_string()).or_default();
+        match headers.iter_mut().find(|(seen, _)| *seen == name) {
+            Some(header) => header.1 = value,
+            None => headers.push((name, value)),
+        }
+    }
+}
+
+// whether the header of a captured request is sent again.
+pub fn replayed(name: &HeaderName) -> bool {
+    !SKIPPED_HEADERS.contains(&name.as_str())
+}
+
+fn origin(url: &str) -> Option<String> {
+    let origin = reqwest::Url::parse(url).ok()?.origin();
+    if origin.is_tuple() {
+        Some(origin.ascii_serialization())
+    } else {
+        None
+    }
+}
+
+impl Import {
+    // reads the items saved from the burp proxy history, `Save items` with the
+    // requests base64 encoded or not.
+    pub fn burp(&mut self, path: &str) -> Result<(), String> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => return Err(format!("failed to open burp items: {:?}", e)),
+        };
+        if !contents.contains("<items") {
+            return Err(format!("{} isn't a burp items export", path));
+        }
+        let targets = self.urls.len();
+        for item in contents.split("<item>").skip(1) {
+            let item = match item.split_once("</item>") {
+                Some((item, _)) => item,
+                None => continue,
+            };
+            let origin = match element(item, "url").and_then(|(_, url)| self.add_url(&url)) {
+                Some(origin) => origin,
+                None => continue,
+            };
+            let request = match element(item, "request") {
+                Some((attributes, request)) if attributes.contains("base64=\"true\"") => {
+                    match base64::engine::general_purpose::STANDARD.decode(request.trim()) {
+                        Ok(request) => String::from_utf8_lossy(&request).to_string(),
+                        Err(_) => continue,
+                    }
+                }
+                Some((_, request)) => request,
+                None => continue,
+            };
+            // the headers of the raw request, from the request line to the blank line.
+            for line in request
+                .lines()
+                .skip(1)
+                .take_while(|line| !line.trim().is_empty())
+            {
+                if let Some((name, value)) = line.split_once(':') {
+                    self.add_header(&origin, name, value);
+                }
+            }
+        }
+        if self.urls.len() == targets {
+            return Err(format!("no urls found in the burp items {}", path));
+        }
+        Ok(())
+    }
+
+    // reads the requests of a har file saved from the network tab of the
+    // browser, the http/2 pseudo-headers are left out.
+    pub fn har(&mut self, path: &str) -> Result<(), String> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => return Err(format!("failed to open har file: {:?}", e)),
+        };
+        let har: HarFile = match serde_json::from_str(&contents) {
+            Ok(har) => har,
+            Err(e) => return Err(format!("invalid har file {}: {}", path, e)),
+        };
+        let targets = self.urls.len();
+        for entry in har.log.entries {
+            let origin = match self.add_url(&entry.request.url) {
+                Some(origin) => origin,
+                None => continue,
+            };
+            for header in &entry.request.headers {
+                self.add_header(&origin, &header.name, &header.value);
+            }
+            // the cookies are listed on their own as well, they are only taken
+            // from the list when the cookie header was left out.
+            let cookie_header = entry
+                .request
+                .headers
+                .iter()
+                .any(|header| header.name.eq_ignore_ascii_case("cookie"));
+            if !cookie_header {
+                for cookie in &entry.request.cookies {
+                    self.cookies
+                        .push((origin.clone(), format!("{}={}", cookie.name, cookie.value)));
+                }
+            }
+        }
+        if self.urls.len() == targets {
+            return Err(format!("no urls found in the har file {}", path));
+        }
+        Ok(())
+    }
+
+    // adds the url to the targets once and gives its origin, the urls that
+    // aren't http or https are skipped.
+    fn add_url(&mut self, url: &str) -> Option<String> {
+        let mut url = reqwest::Url::parse(url.trim()).ok()?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return None;
+        }
+        url.set_fragment(None);
+        let url = url.to_string();
+        let origin = origin(&url)?;
+        if !self.urls.contains(&url) {
+            self.urls.push(url);
+        }
+        Some(origin)
+    }
+
+    fn add_header(&mut self, origin: &str, name: &str, value: &str) {
+        let name = match HeaderName::from_bytes(name.trim().as_bytes()) {
+            Ok(name) => name,
+            Err(_) => return,
+        };
+        let value = value.trim();
+        if HeaderValue::from_str(value).is_err() {
+            return;
+        }
+        if name == reqwest::header::COOKIE {
+            self.cookies.push((origin.to_string(), value.to_string()));
+        } else if replayed(&name) {
+            self.headers.insert(origin, name, value.to_string());
+        }
+    }
+}
+
+// the attributes and the text of the first <tag> of the item, the text of a
+// CDATA section is taken as is.
+fn element(item: &str, tag: &str) -> Option<(String, String)> {
+    let open = format!("<{}", tag);
+    let mut rest = item;
+    let start = loop {
+        let at = rest.find(&open)?;
+        rest = &rest[at + open.len()..];
+        if rest.starts_with('>') || rest.starts_with(char::is_whitespace) {
+            break rest;
+        }
+    };
+    let (attributes, rest) = start.split_once('>')?;
+    if attributes.ends_with('/') {
+        return Some((attributes.to_string(), String::new()));
+    }
+    let (text, _) = rest.split_once(&format!("</{}>", tag))?;
+    let text = match text
+        .trim()
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+    {
+        Some(cdata) => cdata.to_string(),
+        None => unescape(text),
+    };
+    Some((attributes.to_string(), text))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,32 @@
+// the scanner behind the pathbuster binary, embed it with runner::Runner
+
+pub mod agent;
+pub mod auth;
+pub mod bruteforcer;
+pub mod cancel;
+pub mod client;
+pub mod detector;
+pub mod dns;
+pub mod events;
+pub mod fingerprint;
+pub mod html;
+pub mod import;
+pub mod logging;
+pub mod metrics;
+pub mod notify;
+pub mod observer;
+pub mod output;
+pub mod plugin;
+pub mod progress;
+pub mod routes;
+pub mod runner;
+pub mod scope;
+pub mod script;
+pub mod signatures;
+pub mod tags;
+pub mod transform;
+pub mod utils;
+pub mod vhost;
+pub mod waf;
+pub mod watch;
+pub mod wordlist;
@@ -0,0 +1,37 @@
+use std::fs::OpenOptions;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use tracing_subscriber::filter::LevelFilter;
+
+// installs the subscriber of the tracing events: json lines appended to the
+// log file, or the compact format on stderr.
+pub fn init(level: &str, log_file: Option<&str>) -> Result<(), String> {
+    let level = match LevelFilter::from_str(level) {
+        Ok(level) => level,
+        Err(_) => {
+            return Err(format!(
+                "invalid --log-level {}, use off, error, warn, info, debug or trace",
+                level
+            ))
+        }
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_target(false);
+    let result = match log_file {
+        Some(log_file) => {
+            let file = match OpenOptions::new().create(true).append(true).open(log_file) {
+                Ok(file) => file,
+                Err(e) => return Err(format!("failed to open log file: {:?}", e)),
+            };
+            builder
+                .json()
+                .with_current_span(true)
+                .with_writer(Mutex::new(file))
+                .try_init()
+        }
+        None => builder.compact().with_writer(std::io::stderr).try_init(),
+    };
+    result.map_err(|e| format!("failed to set up logging: {}", e))
+}
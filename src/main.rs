@@ -1,33 +1,36 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::io::Write;
+use std::future::ready;
+use std::io::{IsTerminal, Write};
 use std::process::exit;
+use std::sync::Arc;
 use std::time::Duration;
 
 use clap::App;
 use clap::Arg;
+use clap::ValueSource;
 
-use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use tokio::fs::OpenOptions;
-use tokio::sync::mpsc;
 
+use tokio::fs::File;
 use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::runtime::Builder;
+use tokio::sync::mpsc;
 use tokio::time::Instant;
-use tokio::{fs::File, task};
+use tokio_util::sync::CancellationToken;
 
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 
-use crate::bruteforcer::BruteJob;
-use crate::bruteforcer::BruteResult;
-use crate::detector::Job;
-use crate::detector::JobResult;
-
-mod bruteforcer;
-mod detector;
-mod utils;
+use pathbuster::client::ClientSettings;
+use pathbuster::detector::JobSettings;
+use pathbuster::runner::{Options, Runner};
+use pathbuster::waf::Escalation;
+use pathbuster::{
+    agent, auth, bruteforcer, cancel, client, detector, dns, events, fingerprint, import, logging,
+    metrics, notify, observer, output, plugin, routes, scope, script, signatures, tags, transform,
+    utils, waf, watch, wordlist,
+};
 
 // our fancy ascii banner to make it look hackery :D
 fn print_banner() {
@@ -72,25 +75,172 @@ fn print_banner() {
     );
 }
 
+// every flag of the scan can also be set with a PATHBUSTER_<FLAG> environment
+// variable, eg PATHBUSTER_RATE=50 or PATHBUSTER_INSECURE=true, the flags given
+// on the command line take precedence over them.
+fn with_env_overrides(app: App<'static>) -> App<'static> {
+    let ids: Vec<&'static str> = app
+        .get_arguments()
+        .map(|arg| arg.get_id())
+        .filter(|id| !matches!(*id, "help" | "version"))
+        .collect();
+    ids.into_iter().fold(app, |app, id| {
+        // clap keeps the names for the lifetime of the app, which is the whole run.
+        let name = format!("PATHBUSTER_{}", id.to_uppercase().replace('-', "_"));
+        let name: &'static str = Box::leak(name.into_boxed_str());
+        app.mut_arg(id, |arg| arg.env(name))
+    })
+}
+
 // asynchronous entry point main where the magic happens.
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
-    // print the banner
-    print_banner();
-
     // parse the cli arguments
-    let matches = App::new("pathbuster")
+    let app = App::new("pathbuster")
         .version("0.5.5")
         .author("Blake Jacobs <krypt0mux@gmail.com>")
         .about("path-normalization pentesting tool")
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            App::new("estimate")
+                .about("estimate the requests and duration of the scan without sending any traffic to the targets"),
+        )
+        .subcommand(
+            App::new("payloads")
+                .about("preview the variants the payload families generate from a payload")
+                .arg(
+                    Arg::with_name("payload")
+                        .short('p')
+                        .long("payload")
+                        .takes_value(true)
+                        .required(true)
+                        .help("the traversal payload to mutate"),
+                )
+                .arg(
+                    Arg::with_name("family")
+                        .long("family")
+                        .takes_value(true)
+                        .required(false)
+                        .help("the families to apply, same syntax as --bypass-transform (default: all)"),
+                )
+                .arg(
+                    Arg::with_name("level")
+                        .long("level")
+                        .takes_value(true)
+                        .required(false)
+                        .default_value("1")
+                        .help("the waf escalation level the variants are sent at"),
+                ),
+        )
+        .subcommand(
+            App::new("fingerprint")
+                .about("only fingerprint the target, the client options still apply")
+                .arg(
+                    Arg::with_name("url")
+                        .short('u')
+                        .long("url")
+                        .takes_value(true)
+                        .required(true)
+                        .help("the url you would like to fingerprint"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .takes_value(false)
+                        .required(false)
+                        .help("print the fingerprint as json"),
+                ),
+        )
+        .subcommand(
+            App::new("history")
+                .about("print when the findings of a host were seen across the scans of a --db database")
+                .arg(
+                    Arg::with_name("host")
+                        .short('u')
+                        .long("host")
+                        .takes_value(true)
+                        .required(true)
+                        .help("the host whose findings are printed"),
+                )
+                .arg(
+                    Arg::with_name("db")
+                        .long("db")
+                        .takes_value(true)
+                        .required(false)
+                        .default_value("results.db")
+                        .help("the sqlite database the scans were written to"),
+                ),
+        )
+        .subcommand(
+            App::new("merge")
+                .about("merge the json outputs of the --shard scans into one without the duplicates")
+                .arg(
+                    Arg::with_name("files")
+                        .takes_value(true)
+                        .multiple_values(true)
+                        .required(true)
+                        .help("the outputs of the shards, json lines, json arrays or one url per line"),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .short('o')
+                        .long("out")
+                        .takes_value(true)
+                        .required(false)
+                        .help("the file the merged findings are written to as json lines (default: stdout)"),
+                ),
+        )
+        .subcommand(
+            App::new("controller")
+                .about("push a shard of the urls to each --agent and aggregate the findings they report")
+                .arg(
+                    Arg::with_name("listen")
+                        .long("listen")
+                        .takes_value(true)
+                        .required(false)
+                        .default_value(agent::DEFAULT_LISTEN)
+                        .help("the address the agents connect to, another one than localhost (eg 0.0.0.0:7700) needs --token"),
+                )
+                .arg(
+                    Arg::with_name("token")
+                        .long("token")
+                        .takes_value(true)
+                        .required(false)
+                        .env("PATHBUSTER_CONTROLLER_TOKEN")
+                        .help("the shared secret the agents must send with --agent-token"),
+                )
+                .arg(
+                    Arg::with_name("agents")
+                        .long("agents")
+                        .takes_value(true)
+                        .required(false)
+                        .default_value("1")
+                        .help("the agents to wait for before the scan starts"),
+                )
+                .arg(
+                    Arg::with_name("urls")
+                        .short('u')
+                        .long("urls")
+                        .takes_value(true)
+                        .required(true)
+                        .help("the urls the agents split between them"),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .short('o')
+                        .long("out")
+                        .takes_value(true)
+                        .required(false)
+                        .help("the file the findings of the agents are appended to as json lines"),
+                ),
+        )
         .arg(
             Arg::with_name("urls")
                 .short('u')
                 .long("urls")
                 .takes_value(true)
-                .required(true)
                 .display_order(1)
-                .help("the url you would like to test"),
+                .help("the file with the urls you would like to test, - or a pipe reads them from stdin"),
         )
         .arg(
             Arg::with_name("rate")
@@ -174,11 +324,12 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
         )
         .arg(
             Arg::with_name("header")
+                .short('H')
                 .long("header")
-                .default_value("")
                 .takes_value(true)
+                .multiple_occurrences(true)
                 .display_order(11)
-                .help("The header to insert into each request"),
+                .help("The header to insert into each request, can be repeated"),
         )
         .arg(
             Arg::with_name("workers")
@@ -196,16 +347,25 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
                 .takes_value(true)
                 .display_order(13)
                 .default_value("./payloads/traversals.txt")
-                .help("the file containing the traversal payloads"),
+                .help("the file or http(s) url containing the traversal payloads"),
         )
         .arg(
             Arg::with_name("wordlist")
                 .long("wordlist")
+                .multiple_occurrences(true)
                 .required(true)
                 .takes_value(true)
                 .display_order(14)
                 .default_value("./wordlists/wordlist.txt")
-                .help("the file containing the wordlist used for directory bruteforcing"),
+                .help("the file, http(s) url or seclists:<path> list containing the wordlist used for directory bruteforcing, can be repeated"),
+        )
+        .arg(
+            Arg::with_name("wordlist-dir")
+                .long("wordlist-dir")
+                .required(false)
+                .takes_value(true)
+                .display_order(57)
+                .help("a directory of wordlists merged with the --wordlist ones"),
         )
         .arg(
             Arg::with_name("out")
@@ -215,390 +375,2651 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
                 .takes_value(true)
                 .help("The output file"),
         )
-        .get_matches();
-
-    let rate = match matches.value_of("rate").unwrap().parse::<u32>() {
-        Ok(n) => n,
-        Err(_) => {
-            println!("{}", "could not parse rate, using default of 1000");
-            1000
-        }
-    };
-
-    let concurrency = match matches.value_of("concurrency").unwrap().parse::<u32>() {
-        Ok(n) => n,
-        Err(_) => {
-            println!("{}", "could not parse concurrency, using default of 1000");
-            1000
-        }
-    };
-
-    let drop_after_fail = match matches
-        .get_one::<String>("drop-after-fail")
-        .map(|s| s.to_string())
-    {
-        Some(drop_after_fail) => drop_after_fail,
-        None => {
-            println!(
-                "{}",
-                "could not parse drop-after-fail, using default of 302,301"
-            );
-            "".to_string()
-        }
-    };
-
-    let http_proxy = match matches.get_one::<String>("proxy").map(|p| p.to_string()) {
-        Some(http_proxy) => http_proxy,
-        None => "".to_string(),
-    };
-
-    let payloads_path = match matches.value_of("payloads") {
-        Some(payloads_path) => payloads_path,
-        None => {
-            println!("{}", "invalid payloads file");
-            exit(1);
-        }
-    };
-
-    let header = match matches.value_of("header").unwrap().parse::<String>() {
-        Ok(header) => header,
-        Err(_) => "".to_string(),
-    };
-
-    let mut skip_dir = matches.is_present("skip-brute");
-    let skip_validation = matches.is_present("skip-validation");
-    if skip_validation {
-        skip_dir = true;
-    }
-
-    let wordlist_path = match matches.value_of("wordlist") {
-        Some(wordlist_path) => wordlist_path,
-        None => {
-            println!("{}", "invalid wordlist file");
-            exit(1);
-        }
-    };
-    let urls_path = match matches.get_one::<String>("urls").map(|s| s.to_string()) {
-        Some(urls_path) => urls_path,
-        None => "".to_string(),
-    };
-    // copy some variables
-    let _urls_path = urls_path.clone();
-
-    let int_status = match matches
-        .get_one::<String>("int-status")
-        .map(|s| s.to_string())
-    {
-        Some(int_status) => int_status,
-        None => "".to_string(),
-    };
-
-    let pub_status = match matches
-        .get_one::<String>("pub-status")
-        .map(|s| s.to_string())
-    {
-        Some(pub_status) => pub_status,
-        None => "".to_string(),
-    };
-
-    let timeout = match matches.get_one::<String>("timeout").map(|s| s.to_string()) {
-        Some(timeout) => timeout.parse::<usize>().unwrap(),
-        None => 10,
-    };
-
-    let w: usize = match matches.value_of("workers").unwrap().parse::<usize>() {
-        Ok(w) => w,
-        Err(_) => {
-            println!("{}", "could not parse workers, using default of 10");
-            10
-        }
-    };
-
-    // Set up a worker pool with 4 threads
-    let rt = Builder::new_multi_thread()
-        .enable_all()
-        .worker_threads(w)
-        .build()
-        .unwrap();
-
-    let now = Instant::now();
-
-    // define the file handle for the wordlists.
-    let payloads_handle = match File::open(payloads_path).await {
-        Ok(payloads_handle) => payloads_handle,
-        Err(e) => {
-            println!("failed to open input file: {:?}", e);
-            exit(1);
-        }
-    };
-
-    // define the file handle for the wordlists.
-    let wordlist_handle = match File::open(wordlist_path).await {
-        Ok(wordlist_handle) => wordlist_handle,
-        Err(e) => {
-            println!("failed to open input file: {:?}", e);
-            exit(1);
-        }
-    };
-
-    // build our wordlists by constructing the arrays and storing
-    // the words in the array.
-    let (job_tx, job_rx) = spmc::channel::<Job>();
-    let (result_tx, _result_rx) = mpsc::channel::<JobResult>(w);
-
-    let mut urls = vec![];
-    let mut payloads = vec![];
-    let mut wordlist = vec![];
-
-    let payload_buf = BufReader::new(payloads_handle);
-    let mut payload_lines = payload_buf.lines();
-
-    // read the payloads file and append each line to an array.
-    while let Ok(Some(payload)) = payload_lines.next_line().await {
-        payloads.push(payload);
-    }
-
-    let wordlist_buf = BufReader::new(wordlist_handle);
-    let mut wordlist_lines = wordlist_buf.lines();
-
-    // read the payloads file and append each line to an array.
-    while let Ok(Some(word)) = wordlist_lines.next_line().await {
-        wordlist.push(word);
-    }
-
-    // read the hosts file if specified and append each line to an array.
-    let urls_handle = match File::open(urls_path).await {
-        Ok(urls_handle) => urls_handle,
-        Err(e) => {
-            println!("failed to open input file: {:?}", e);
-            exit(1);
-        }
-    };
-    let urls_buf = BufReader::new(urls_handle);
-    let mut urls_lines = urls_buf.lines();
-    while let Ok(Some(url)) = urls_lines.next_line().await {
-        urls.push(url);
-    }
-
-    // set the message
-    println!(
-        "{}",
-        "----------------------------------------------------------"
-            .bold()
-            .white()
-    );
-    println!(
-        "{}  {}      {} {}\n{}  {}          {} {}\n{}  {}  {} {}\n{}  {}  {} {}\n{}  {}   {} {}\n{}  {}       {} {}",
-        ">".bold().green(),
-        "Payloads".bold().white(),
-        ":".bold().white(),
-        payloads.len().to_string().bold().cyan(),
-        ">".bold().green(),
-        "Urls".bold().white(),
-        ":".bold().white(),
-        urls.len().to_string().bold().cyan(),
-        ">".bold().green(),
-        "Int Matchers".bold().white(),
-        ":".bold().white(),
-        int_status.to_string().bold().cyan(),
-        ">".bold().green(),
-        "Pub Matchers".bold().white(),
-        ":".bold().white(),
-        pub_status.to_string().bold().cyan(),
-        ">".bold().green(),
-        "Concurrency".bold().white(),
-        ":".bold().white(),
-        concurrency.to_string().bold().cyan(),
-        ">".bold().green(),
-        "Workers".bold().white(),
-        ":".bold().white(),
-        w.to_string().bold().cyan(),
-    );
-    println!(
-        "{}",
-        "----------------------------------------------------------"
-            .bold()
-            .white()
-    );
-    println!("");
-
-    let bar_length = (urls.len() * payloads.len()) as u64;
-
-    let pb = ProgressBar::new(bar_length);
-    pb.set_draw_target(ProgressDrawTarget::stderr());
-    pb.enable_steady_tick(Duration::from_millis(200));
-    pb.set_style(
-        ProgressStyle::with_template("{spinner:.blue} ({eta}) {elapsed} ({len}) {pos} {msg}")
-            .unwrap()
-            .progress_chars(r#"#>-"#),
-    );
-
-    // spawn our workers
-    let out_pb = pb.clone();
-    let job_pb: ProgressBar = pb.clone();
-    let job_wordlist = wordlist.clone();
-    rt.spawn(async move {
-        detector::send_url(
-            job_tx,
-            urls,
-            payloads,
-            job_wordlist,
-            rate,
-            int_status,
-            pub_status,
-            drop_after_fail,
-            skip_validation,
-            header,
-        )
-        .await
-    });
-
-    // process the jobs
-    let workers = FuturesUnordered::new();
-
-    // process the jobs for scanning.
-    for _ in 0..concurrency {
-        let http_proxy = http_proxy.clone();
-        let jrx = job_rx.clone();
-        let jtx: mpsc::Sender<JobResult> = result_tx.clone();
-        let jpb = job_pb.clone();
-        workers.push(task::spawn(async move {
-            //  run the detector
-            detector::run_tester(jpb, jrx, jtx, timeout, http_proxy).await
-        }));
-    }
-
-    let outfile_path = match matches.value_of("out") {
-        Some(outfile_path) => outfile_path,
-        None => {
-            println!("{}", "invalid output file path");
-            exit(1);
-        }
-    };
-
-    let mut outfile_path_brute = String::from("discovered-routes");
-    outfile_path_brute.push_str(".txt");
-
-    // print the results
-    let out_pb = out_pb.clone();
-    let brute_wordlist = wordlist.clone();
-    let worker_results: Vec<_> = workers.collect().await;
-    let mut results: Vec<String> = vec![];
-    let mut brute_results: HashMap<String, String> = HashMap::new();
-    for result in worker_results {
-        let result = match result {
-            Ok(result) => result,
-            Err(_) => continue,
-        };
-        let result_data = result.data.clone();
-        let out_data = result.data.clone();
-        if result.data.is_empty() == false {
-            let out_pb = out_pb.clone();
-            results.push(result_data);
-            let outfile_handle_traversal = match OpenOptions::new()
-                .create(true)
-                .write(true)
-                .append(true)
-                .open(outfile_path)
-                .await
-            {
-                Ok(outfile_handle_traversal) => outfile_handle_traversal,
-                Err(e) => {
-                    println!("failed to open output file: {:?}", e);
-                    exit(1);
-                }
-            };
-            detector::save_traversals(out_pb, outfile_handle_traversal, out_data).await;
-        }
-    }
-
-    if !skip_dir {
-        let pb_results = results.clone();
-        let outfile_path_brute = outfile_path_brute.clone();
-        let outfile_handle_brute = match OpenOptions::new()
-            .create(true)
-            .write(true)
-            .append(true)
-            .open(outfile_path_brute)
-            .await
-        {
-            Ok(outfile_handle_brute) => outfile_handle_brute,
-            Err(e) => {
-                println!("failed to open output file: {:?}", e);
-                exit(1);
-            }
-        };
-        let out_pb = out_pb.clone();
-        let bar_length = (pb_results.len() * wordlist.len()) as u64;
-        out_pb.set_length(bar_length);
-        out_pb.set_position(0);
-        let brute_pb = out_pb.clone();
-        let brute_wordlist = brute_wordlist.clone();
-        let (brute_job_tx, brute_job_rx) = spmc::channel::<BruteJob>();
-        let (brute_result_tx, brute_result_rx) = mpsc::channel::<BruteResult>(w);
-        // start orchestrator tasks
-        rt.spawn(async move {
-            bruteforcer::send_word_to_url(brute_job_tx, results, brute_wordlist, rate).await
-        });
-        rt.spawn(async move {
-            bruteforcer::save_discoveries(out_pb, outfile_handle_brute, brute_result_rx).await
-        });
-
-        // process the jobs for directory bruteforcing.
-        let workers = FuturesUnordered::new();
-        for _ in 0..concurrency {
-            let http_proxy = http_proxy.clone();
-            let brx = brute_job_rx.clone();
-            let btx: mpsc::Sender<BruteResult> = brute_result_tx.clone();
-            let bpb = brute_pb.clone();
-            workers.push(task::spawn(async move {
-                bruteforcer::run_bruteforcer(bpb, brx, btx, timeout, http_proxy).await
-            }));
-        }
-        let worker_results: Vec<_> = workers.collect().await;
-        for result in worker_results {
-            let result = match result {
-                Ok(result) => result,
-                Err(_) => continue,
-            };
-            let content_length = result.rs.clone();
-            let result_data = result.data.clone();
-            if result.data.is_empty() == false {
-                brute_results.insert(result_data, content_length);
-            }
-        }
-    }
-    rt.shutdown_background();
-
-    // print out the discoveries.
-    println!("\n\n");
-    println!("{}", "Discovered:".bold().green());
-    println!("{}", "===========".bold().green());
-    for result in brute_results {
-        println!(
-            "{} {} {} {}",
-            "::".bold().green(),
-            result.0.bold().white(),
-            "::".bold().green(),
-            result.1.bold().white()
-        );
-    }
-
-    let elapsed_time = now.elapsed();
-
-    println!("\n\n");
-    println!(
-        "{}, {} {}{}",
-        "Completed!".bold().green(),
-        "scan took".bold().white(),
-        elapsed_time.as_secs().to_string().bold().white(),
-        "s".bold().white()
-    );
-    println!(
-        "{} {}",
-        "results are saved in".bold().white(),
-        outfile_path.bold().cyan(),
-    );
+        .arg(
+            Arg::with_name("http3")
+                .long("http3")
+                .takes_value(false)
+                .required(false)
+                .display_order(16)
+                .help("experimental: send requests over http3 (QUIC), falling back to h2/h1"),
+        )
+        .arg(
+            Arg::with_name("proxy-auth")
+                .long("proxy-auth")
+                .required(false)
+                .takes_value(true)
+                .display_order(17)
+                .help("proxy credentials to use (eg user:pass)"),
+        )
+        .arg(
+            Arg::with_name("resolve")
+                .long("resolve")
+                .required(false)
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .display_order(18)
+                .help("pin a hostname to an ip, can be repeated (eg example.com:443:10.0.0.1)"),
+        )
+        .arg(
+            Arg::with_name("dns-server")
+                .long("dns-server")
+                .required(false)
+                .takes_value(true)
+                .display_order(19)
+                .help("the dns server used to resolve hostnames (eg 1.1.1.1:53)"),
+        )
+        .arg(
+            Arg::with_name("ipv4-only")
+                .long("ipv4-only")
+                .takes_value(false)
+                .required(false)
+                .conflicts_with("ipv6-only")
+                .display_order(20)
+                .help("only connect to targets over ipv4"),
+        )
+        .arg(
+            Arg::with_name("ipv6-only")
+                .long("ipv6-only")
+                .takes_value(false)
+                .required(false)
+                .display_order(21)
+                .help("only connect to targets over ipv6"),
+        )
+        .arg(
+            Arg::with_name("client-cert")
+                .long("client-cert")
+                .required(false)
+                .takes_value(true)
+                .display_order(22)
+                .help("client certificate for mutual tls (pem or .p12/.pfx bundle)"),
+        )
+        .arg(
+            Arg::with_name("client-key")
+                .long("client-key")
+                .required(false)
+                .takes_value(true)
+                .display_order(23)
+                .help("pkcs8 pem private key belonging to --client-cert"),
+        )
+        .arg(
+            Arg::with_name("client-cert-password")
+                .long("client-cert-password")
+                .required(false)
+                .takes_value(true)
+                .default_value("")
+                .display_order(24)
+                .help("password of the .p12/.pfx client certificate bundle"),
+        )
+        .arg(
+            Arg::with_name("insecure")
+                .short('k')
+                .long("insecure")
+                .takes_value(false)
+                .required(false)
+                .display_order(25)
+                .help("skip tls certificate and hostname verification"),
+        )
+        .arg(
+            Arg::with_name("ca-cert")
+                .long("ca-cert")
+                .required(false)
+                .takes_value(true)
+                .display_order(26)
+                .help("pem bundle of extra root certificates to trust"),
+        )
+        .arg(
+            Arg::with_name("tls-min")
+                .long("tls-min")
+                .required(false)
+                .takes_value(true)
+                .possible_values(["1.0", "1.1", "1.2"])
+                .display_order(27)
+                .help("the minimum tls version to negotiate"),
+        )
+        .arg(
+            Arg::with_name("tls-max")
+                .long("tls-max")
+                .required(false)
+                .takes_value(true)
+                .possible_values(["1.0", "1.1", "1.2"])
+                .display_order(28)
+                .help("the maximum tls version to negotiate, leave it out to allow 1.3"),
+        )
+        .arg(
+            Arg::with_name("sni")
+                .long("sni")
+                .required(false)
+                .takes_value(true)
+                .display_order(29)
+                .help("the server name (sni and host header) to send instead of the url host"),
+        )
+        .arg(
+            Arg::with_name("connect-to")
+                .long("connect-to")
+                .required(false)
+                .takes_value(true)
+                .display_order(30)
+                .help("connect to this host:port instead of the url host"),
+        )
+        .arg(
+            Arg::with_name("user-agent")
+                .long("user-agent")
+                .required(false)
+                .takes_value(true)
+                .default_value(client::DEFAULT_USER_AGENT)
+                .display_order(31)
+                .help("the user agent to send with each request"),
+        )
+        .arg(
+            Arg::with_name("random-agent")
+                .long("random-agent")
+                .required(false)
+                .takes_value(false)
+                .display_order(32)
+                .help("rotate through a built-in list of user agents per request"),
+        )
+        .arg(
+            Arg::with_name("headers-file")
+                .long("headers-file")
+                .required(false)
+                .takes_value(true)
+                .display_order(33)
+                .help("file containing headers to insert into each request, one per line"),
+        )
+        .arg(
+            Arg::with_name("cookie")
+                .long("cookie")
+                .required(false)
+                .takes_value(true)
+                .display_order(34)
+                .help("cookies to send to each target (eg \"a=b; c=d\")"),
+        )
+        .arg(
+            Arg::with_name("login-request")
+                .long("login-request")
+                .required(false)
+                .takes_value(true)
+                .display_order(35)
+                .help("raw http login request sent before the scan to capture the session"),
+        )
+        .arg(
+            Arg::with_name("session-expired-status")
+                .long("session-expired-status")
+                .required(false)
+                .takes_value(true)
+                .default_value("401")
+                .display_order(38)
+                .help("statuses that mean the login session expired and must be refreshed"),
+        )
+        .arg(
+            Arg::with_name("session-expired-regex")
+                .long("session-expired-regex")
+                .required(false)
+                .takes_value(true)
+                .display_order(39)
+                .help("regex matched against response bodies to detect an expired session"),
+        )
+        .arg(
+            Arg::with_name("rewrite-headers")
+                .long("rewrite-headers")
+                .required(false)
+                .takes_value(false)
+                .display_order(40)
+                .help("also send the traversals in X-Original-URL, X-Rewrite-URL and X-Forwarded-Path"),
+        )
+        .arg(
+            Arg::with_name("spoof-ip")
+                .long("spoof-ip")
+                .required(false)
+                .takes_value(false)
+                .display_order(41)
+                .help("replay targets returning 403 with X-Forwarded-For style ip spoofing headers"),
+        )
+        .arg(
+            Arg::with_name("method-override")
+                .long("method-override")
+                .required(false)
+                .takes_value(false)
+                .display_order(108)
+                .help("retry targets returning 403/405 with X-HTTP-Method-Override and _method tricks"),
+        )
+        .arg(
+            Arg::with_name("inject-at")
+                .long("inject-at")
+                .required(false)
+                .takes_value(true)
+                .default_value("end")
+                .display_order(47)
+                .help("where to insert the traversals in the path: end, each-segment or index:N"),
+        )
+        .arg(
+            Arg::with_name("cache-deception")
+                .long("cache-deception")
+                .required(false)
+                .takes_value(false)
+                .display_order(49)
+                .help("probe the internal routes found for web cache deception"),
+        )
+        .arg(
+            Arg::with_name("nginx-alias")
+                .long("nginx-alias")
+                .required(false)
+                .takes_value(false)
+                .display_order(50)
+                .help("probe the path prefixes for the nginx alias off-by-slash traversal"),
+        )
+        .arg(
+            Arg::with_name("iis-shortname")
+                .long("iis-shortname")
+                .required(false)
+                .takes_value(false)
+                .display_order(51)
+                .help("enumerate iis 8.3 short names of the directories behind the internal doc roots"),
+        )
+        .arg(
+            Arg::with_name("skip-profiling")
+                .long("skip-profiling")
+                .required(false)
+                .takes_value(false)
+                .display_order(52)
+                .help("skip profiling the path normalization of the targets before the scan"),
+        )
+        .arg(
+            Arg::with_name("signatures")
+                .long("signatures")
+                .required(false)
+                .takes_value(true)
+                .display_order(53)
+                .help("a yaml file of waf signatures and tech rules merged with the built-in ones"),
+        )
+        .arg(
+            Arg::with_name("bypass-transform")
+                .long("bypass-transform")
+                .required(false)
+                .takes_value(true)
+                .display_order(54)
+                .help("comma separated payload families generated from the payloads (eg query_confusion)"),
+        )
+        .arg(
+            Arg::with_name("transform-file")
+                .long("transform-file")
+                .required(false)
+                .takes_value(true)
+                .display_order(55)
+                .help("a yaml file of named substitution rules generated as additional payload families"),
+        )
+        .arg(
+            Arg::with_name("max-per-family")
+                .long("max-per-family")
+                .required(false)
+                .takes_value(true)
+                .display_order(56)
+                .help("the maximum amount of variants each payload family generates from a payload"),
+        )
+        .arg(
+            Arg::with_name("wordlist-lowercase")
+                .long("wordlist-lowercase")
+                .required(false)
+                .takes_value(false)
+                .display_order(58)
+                .help("lowercase the words of the wordlist"),
+        )
+        .arg(
+            Arg::with_name("wordlist-strip-slashes")
+                .long("wordlist-strip-slashes")
+                .required(false)
+                .takes_value(false)
+                .display_order(59)
+                .help("strip the leading slashes of the words of the wordlist"),
+        )
+        .arg(
+            Arg::with_name("wordlist-drop-invalid")
+                .long("wordlist-drop-invalid")
+                .required(false)
+                .takes_value(false)
+                .display_order(60)
+                .help("drop the words holding characters that aren't valid in a path"),
+        )
+        .arg(
+            Arg::with_name("wordlist-min-length")
+                .long("wordlist-min-length")
+                .required(false)
+                .takes_value(true)
+                .display_order(61)
+                .help("drop the words shorter than the length"),
+        )
+        .arg(
+            Arg::with_name("wordlist-max-length")
+                .long("wordlist-max-length")
+                .required(false)
+                .takes_value(true)
+                .display_order(62)
+                .help("drop the words longer than the length"),
+        )
+        .arg(
+            Arg::with_name("learn-words")
+                .long("learn-words")
+                .required(false)
+                .takes_value(false)
+                .display_order(63)
+                .help("bruteforce the path segments of the links and json of the internal doc roots as well"),
+        )
+        .arg(
+            Arg::with_name("extensions")
+                .long("extensions")
+                .required(false)
+                .takes_value(true)
+                .display_order(64)
+                .help("the comma separated extensions appended to the words, detected from the links of the targets when not given"),
+        )
+        .arg(
+            Arg::with_name("dirsearch")
+                .long("dirsearch")
+                .required(false)
+                .takes_value(false)
+                .display_order(65)
+                .help("apply the extensions like dirsearch, only to the %EXT% placeholders of the words"),
+        )
+        .arg(
+            Arg::with_name("force-extensions")
+                .long("force-extensions")
+                .required(false)
+                .takes_value(false)
+                .display_order(66)
+                .help("like dirsearch -f, append the extensions and a slash to every word without an extension"),
+        )
+        .arg(
+            Arg::with_name("db")
+                .long("db")
+                .required(false)
+                .takes_value(true)
+                .display_order(67)
+                .help("the sqlite database the targets, fingerprints, findings and discoveries of the scan are added to"),
+        )
+        .arg(
+            Arg::with_name("diff")
+                .long("diff")
+                .required(false)
+                .takes_value(true)
+                .display_order(68)
+                .help("the findings of a previous scan, only the new findings are reported"),
+        )
+        .arg(
+            Arg::with_name("diff-all")
+                .long("diff-all")
+                .required(false)
+                .takes_value(false)
+                .display_order(69)
+                .help("with --diff, report the known and resolved findings as well"),
+        )
+        .arg(
+            Arg::with_name("notify-webhook")
+                .long("notify-webhook")
+                .required(false)
+                .takes_value(true)
+                .display_order(70)
+                .help("the webhook url each confirmed finding is posted to as soon as it is found"),
+        )
+        .arg(
+            Arg::with_name("notify-format")
+                .long("notify-format")
+                .required(false)
+                .takes_value(true)
+                .default_value("auto")
+                .display_order(71)
+                .help("the body posted to the webhook: generic, slack, discord or auto to pick it from the url"),
+        )
+        .arg(
+            Arg::with_name("syslog")
+                .long("syslog")
+                .required(false)
+                .takes_value(true)
+                .display_order(72)
+                .help("the syslog server each confirmed finding is sent to as a rfc 5424 message, udp://host:port or tcp://host:port"),
+        )
+        .arg(
+            Arg::with_name("ci")
+                .long("ci")
+                .required(false)
+                .takes_value(false)
+                .display_order(73)
+                .help("hide the banner, colors and progress, print the findings as annotations and exit with 2 on the findings at or above --fail-on"),
+        )
+        .arg(
+            Arg::with_name("fail-on")
+                .long("fail-on")
+                .required(false)
+                .takes_value(true)
+                .default_value("high")
+                .display_order(74)
+                .help("the lowest severity failing a --ci scan: low (discoveries), medium (virtual hosts) or high (internal doc roots)"),
+        )
+        .arg(
+            Arg::with_name("metrics-listen")
+                .long("metrics-listen")
+                .required(false)
+                .takes_value(true)
+                .display_order(75)
+                .help("the address the prometheus metrics of the scan are served on, eg 127.0.0.1:9900"),
+        )
+        .arg(
+            Arg::with_name("log-level")
+                .long("log-level")
+                .required(false)
+                .takes_value(true)
+                .default_value("warn")
+                .display_order(76)
+                .help("the level of the logged events: off, error, warn, info, debug or trace"),
+        )
+        .arg(
+            Arg::with_name("log-file")
+                .long("log-file")
+                .required(false)
+                .takes_value(true)
+                .display_order(77)
+                .help("the file the events are appended to as json lines instead of stderr"),
+        )
+        .arg(
+            Arg::with_name("silent")
+                .long("silent")
+                .required(false)
+                .takes_value(false)
+                .display_order(78)
+                .help("only print the findings on stdout, without the banner, configuration, progress and report"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .required(false)
+                .takes_value(true)
+                .default_value("url")
+                .display_order(79)
+                .help("the format of the --silent findings: url or jsonl"),
+        )
+        .arg(
+            Arg::with_name("json-progress")
+                .long("json-progress")
+                .required(false)
+                .takes_value(false)
+                .display_order(80)
+                .help("emit the stage, done, total, rps, eta and errors as a json line on stderr every second instead of the progress bar"),
+        )
+        .arg(
+            Arg::with_name("triage")
+                .long("triage")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with_all(&["ci", "silent"])
+                .display_order(81)
+                .help("open a prompt after the scan to mark the findings as interesting or false positives and export the kept ones"),
+        )
+        .arg(
+            Arg::with_name("shard")
+                .long("shard")
+                .required(false)
+                .takes_value(true)
+                .display_order(82)
+                .help("only send the part of the jobs of the shard, eg 2/5, so several machines split one scan"),
+        )
+        .arg(
+            Arg::with_name("agent")
+                .long("agent")
+                .required(false)
+                .takes_value(true)
+                .conflicts_with("shard")
+                .display_order(83)
+                .help("run as an agent of the controller at the address, it pushes the urls and the shard and collects the findings"),
+        )
+        .arg(
+            Arg::with_name("agent-token")
+                .long("agent-token")
+                .required(false)
+                .takes_value(true)
+                .requires("agent")
+                .display_order(107)
+                .help("the --token of the controller"),
+        )
+        .arg(
+            Arg::with_name("events")
+                .long("events")
+                .required(false)
+                .takes_value(true)
+                .display_order(84)
+                .help("append the fingerprints, findings, discovered routes and progress to the file as json lines while the scan runs"),
+        )
+        .arg(
+            Arg::with_name("plugin")
+                .long("plugin")
+                .required(false)
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .display_order(85)
+                .help("a wasm module inspecting the responses of the detector, can be given more than once"),
+        )
+        .arg(
+            Arg::with_name("script")
+                .long("script")
+                .required(false)
+                .takes_value(true)
+                .display_order(86)
+                .help("a rhai script whose on_request(req) and on_response(resp) hooks change the headers, skip jobs or flag findings"),
+        )
+        .arg(
+            Arg::with_name("shared-pool")
+                .long("shared-pool")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with("http3")
+                .display_order(87)
+                .help("share one connection pool between the workers instead of one per worker"),
+        )
+        .arg(
+            Arg::with_name("scope")
+                .long("scope")
+                .required(false)
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .display_order(88)
+                .help("only request the domain and its subdomains, can be given more than once"),
+        )
+        .arg(
+            Arg::with_name("exclude-path")
+                .long("exclude-path")
+                .required(false)
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .display_order(89)
+                .help("never request the urls with the path in them, eg /logout, can be given more than once"),
+        )
+        .arg(
+            Arg::with_name("exclude-regex")
+                .long("exclude-regex")
+                .required(false)
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .display_order(90)
+                .help("never request the urls matching the regex, can be given more than once"),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .long("watch")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with_all(&["agent", "triage"])
+                .display_order(91)
+                .help("keep running and scan the urls appended to the --urls file as they come"),
+        )
+        .arg(
+            Arg::with_name("import-burp")
+                .long("import-burp")
+                .required(false)
+                .takes_value(true)
+                .conflicts_with_all(&["agent", "watch"])
+                .display_order(92)
+                .help("scan the urls of the items saved from the burp proxy history, with the headers and cookies they were browsed with"),
+        )
+        .arg(
+            Arg::with_name("import-har")
+                .long("import-har")
+                .required(false)
+                .takes_value(true)
+                .conflicts_with_all(&["agent", "watch"])
+                .display_order(93)
+                .help("scan the urls of the requests of a har file, with the headers and cookies they were sent with"),
+        )
+        .arg(
+            Arg::with_name("raw-request")
+                .long("raw-request")
+                .required(false)
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .conflicts_with_all(&["agent", "watch"])
+                .display_order(94)
+                .help("raw http request file, or directory of them, whose url is scanned with its method, headers and body, can be given more than once"),
+        )
+        .arg(
+            Arg::with_name("routes")
+                .long("routes")
+                .required(false)
+                .takes_value(true)
+                .display_order(95)
+                .help("file of `METHOD /path` routes, eg a framework route dump, scanned under each target with the methods of the route"),
+        )
+        .arg(
+            Arg::with_name("traversal-strategy")
+                .long("traversal-strategy")
+                .required(false)
+                .takes_value(true)
+                .default_value("linear")
+                .display_order(96)
+                .help("how the depth of the traversals is found: linear tries every depth, smart binary searches the first depth answered with --pub-status"),
+        )
+        .arg(
+            Arg::with_name("stop-on-root")
+                .long("stop-on-root")
+                .required(false)
+                .takes_value(false)
+                .display_order(97)
+                .help("drop the remaining jobs of a target once its internal doc root is confirmed"),
+        )
+        .arg(
+            Arg::with_name("delay")
+                .long("delay")
+                .required(false)
+                .takes_value(true)
+                .display_order(98)
+                .help("sleep a random duration within the range before each request, eg 100-400ms or 1-2s"),
+        )
+        .arg(
+            Arg::with_name("no-compression")
+                .long("no-compression")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with("accept-encoding")
+                .display_order(99)
+                .help("ask for uncompressed responses with Accept-Encoding: identity"),
+        )
+        .arg(
+            Arg::with_name("accept-encoding")
+                .long("accept-encoding")
+                .required(false)
+                .takes_value(true)
+                .display_order(100)
+                .help("the Accept-Encoding sent with every request, eg gzip, br, the bodies are compared as served"),
+        )
+        .arg(
+            Arg::with_name("similarity")
+                .long("similarity")
+                .required(false)
+                .takes_value(true)
+                .display_order(101)
+                .help("how the responses are compared: sift3, simhash, levenshtein-lines or jaccard-shingles, by default simhash for the bodies over 32KB and sift3 for the others"),
+        )
+        .arg(
+            Arg::with_name("similarity-threshold")
+                .long("similarity-threshold")
+                .required(false)
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .display_order(102)
+                .help("the score above which an algorithm counts the responses as changed, and optionally the one below, eg simhash=5 or sift3=300-100000"),
+        )
+        .arg(
+            Arg::with_name("calibrate")
+                .long("calibrate")
+                .required(false)
+                .takes_value(false)
+                .display_order(103)
+                .help("sample the web root of each host while bruteforcing and raise its similarity threshold above the natural variance of the responses"),
+        )
+        .arg(
+            Arg::with_name("vhost-wordlist")
+                .long("vhost-wordlist")
+                .required(false)
+                .takes_value(true)
+                .display_order(48)
+                .help("wordlist of hostnames to fuzz the Host header of the internal doc roots with"),
+        )
+        .arg(
+            Arg::with_name("head-first")
+                .long("head-first")
+                .required(false)
+                .takes_value(false)
+                .display_order(46)
+                .help("send a HEAD request first while bruteforcing and only fetch the bodies of 200 responses that differ in length from the word under the web root"),
+        )
+        .arg(
+            Arg::with_name("methods")
+                .long("methods")
+                .required(false)
+                .takes_value(true)
+                .default_value("GET")
+                .display_order(42)
+                .help("comma separated http methods to send the traversals with (eg GET,POST)"),
+        )
+        .arg(
+            Arg::with_name("body")
+                .long("body")
+                .required(false)
+                .takes_value(true)
+                .conflicts_with("body-file")
+                .display_order(43)
+                .help("request body for non GET methods, {{payload}} is replaced by the traversal"),
+        )
+        .arg(
+            Arg::with_name("body-file")
+                .long("body-file")
+                .required(false)
+                .takes_value(true)
+                .display_order(44)
+                .help("file containing the request body for non GET methods"),
+        )
+        .arg(
+            Arg::with_name("content-type")
+                .long("content-type")
+                .required(false)
+                .takes_value(true)
+                .default_value("application/x-www-form-urlencoded")
+                .display_order(45)
+                .help("the content type of the request body"),
+        )
+        .arg(
+            Arg::with_name("auth-basic")
+                .long("auth-basic")
+                .required(false)
+                .takes_value(true)
+                .conflicts_with_all(&["auth-bearer", "auth-ntlm"])
+                .display_order(36)
+                .help("basic authentication credentials (eg user:pass)"),
+        )
+        .arg(
+            Arg::with_name("auth-bearer")
+                .long("auth-bearer")
+                .required(false)
+                .takes_value(true)
+                .conflicts_with("auth-ntlm")
+                .display_order(37)
+                .help("bearer token to authenticate with"),
+        )
+        .arg(
+            Arg::with_name("auth-ntlm")
+                .long("auth-ntlm")
+                .required(false)
+                .takes_value(true)
+                .display_order(104)
+                .help("ntlm credentials the requests are authenticated with (eg DOMAIN\\user:pass)"),
+        )
+        .arg(
+            Arg::with_name("refresh-lists")
+                .long("refresh-lists")
+                .required(false)
+                .takes_value(false)
+                .display_order(105)
+                .help("download the http(s) payloads and wordlists again instead of reading their cached copy"),
+        )
+        .arg(
+            Arg::with_name("syslog-sd-id")
+                .long("syslog-sd-id")
+                .required(false)
+                .takes_value(true)
+                .display_order(106)
+                .help("the SD-ID of the structured data of the --syslog messages, a name and your private enterprise number (default finding@32473, the documentation number)"),
+        );
+    let matches = with_env_overrides(app).get_matches();
+
+    if let Err(e) = logging::init(
+        matches.value_of("log-level").unwrap(),
+        matches.value_of("log-file"),
+    ) {
+        println!("{}", e);
+        exit(1);
+    }
+
+    // print the banner, unless the output is meant for another tool
+    let ci = matches.is_present("ci");
+    let silent = matches.is_present("silent");
+    let json_progress = matches.is_present("json-progress");
+    if ci || silent {
+        colored::control::set_override(false);
+    }
+    let fingerprint_matches = matches.subcommand_matches("fingerprint");
+    if !ci
+        && !silent
+        && !fingerprint_matches
+            .is_some_and(|fingerprint_matches| fingerprint_matches.is_present("json"))
+    {
+        print_banner();
+    }
+
+    if let Some(signatures_path) = matches.value_of("signatures") {
+        if let Err(e) = signatures::load(signatures_path) {
+            println!("{}", e);
+            exit(1);
+        }
+    }
+
+    let rate = match matches.value_of("rate").unwrap().parse::<u32>() {
+        Ok(n) => n,
+        Err(_) => {
+            tracing::warn!("could not parse rate, using default of 1000");
+            1000
+        }
+    };
+
+    let concurrency = match matches.value_of("concurrency").unwrap().parse::<u32>() {
+        Ok(n) => n,
+        Err(_) => {
+            tracing::warn!("could not parse concurrency, using default of 1000");
+            1000
+        }
+    };
+
+    let drop_after_fail = match matches
+        .get_one::<String>("drop-after-fail")
+        .map(|s| s.to_string())
+    {
+        Some(drop_after_fail) => drop_after_fail,
+        None => {
+            tracing::warn!("could not parse drop-after-fail, using default of 302,301");
+            "".to_string()
+        }
+    };
+
+    let http_proxy = match matches.get_one::<String>("proxy").map(|p| p.to_string()) {
+        Some(http_proxy) => http_proxy,
+        None => "".to_string(),
+    };
+
+    let proxy_auth = match matches
+        .get_one::<String>("proxy-auth")
+        .map(|p| p.to_string())
+    {
+        Some(proxy_auth) => proxy_auth,
+        None => "".to_string(),
+    };
+    if !proxy_auth.is_empty() && http_proxy.is_empty() {
+        println!("--proxy-auth has no effect without --proxy");
+    }
+
+    let mut resolve_overrides = vec![];
+    if let Some(values) = matches.values_of("resolve") {
+        for value in values {
+            match dns::parse_resolve_override(value) {
+                Some(resolve_override) => resolve_overrides.push(resolve_override),
+                None => {
+                    println!("invalid resolve override: {}", value);
+                    exit(1);
+                }
+            }
+        }
+    }
+
+    let nameserver = match matches.value_of("dns-server") {
+        Some(dns_server) => match dns::parse_dns_server(dns_server) {
+            Some(nameserver) => Some(nameserver),
+            None => {
+                println!("invalid dns server: {}", dns_server);
+                exit(1);
+            }
+        },
+        None => None,
+    };
+    let address_family = if matches.is_present("ipv4-only") {
+        dns::AddressFamily::Ipv4
+    } else if matches.is_present("ipv6-only") {
+        dns::AddressFamily::Ipv6
+    } else {
+        dns::AddressFamily::Any
+    };
+    let dns_resolver = Arc::new(dns::Resolver::new(nameserver, address_family));
+
+    let payloads_path = match matches.value_of("payloads") {
+        Some(payloads_path) => payloads_path,
+        None => {
+            println!("{}", "invalid payloads file");
+            exit(1);
+        }
+    };
+
+    let mut header_lines: Vec<String> = vec![];
+    if let Some(headers_path) = matches.value_of("headers-file") {
+        match std::fs::read_to_string(headers_path) {
+            Ok(contents) => {
+                for line in contents.lines() {
+                    let line = line.trim();
+                    if !line.is_empty() && !line.starts_with('#') {
+                        header_lines.push(line.to_string());
+                    }
+                }
+            }
+            Err(e) => {
+                println!("failed to open headers file: {:?}", e);
+                exit(1);
+            }
+        }
+    }
+    if let Some(values) = matches.values_of("header") {
+        header_lines.extend(values.map(|value| value.to_string()));
+    }
+    let mut headers = vec![];
+    for line in header_lines {
+        match client::parse_header(&line) {
+            Ok(header) => headers.push(header),
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        }
+    }
+
+    let custom_transforms = match matches.value_of("transform-file") {
+        Some(transform_path) => match transform::load_transform_file(transform_path) {
+            Ok(custom_transforms) => custom_transforms,
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        },
+        None => vec![],
+    };
+    let bypass_transform = matches.value_of("bypass-transform").unwrap_or("");
+    let mut transforms = match transform::parse_transforms(bypass_transform, &custom_transforms) {
+        Ok(transforms) => transforms,
+        Err(e) => {
+            println!("{}", e);
+            exit(1);
+        }
+    };
+    // the families of the transform file are always generated.
+    for custom_transform in &custom_transforms {
+        if !transforms.contains(custom_transform) {
+            transforms.push(custom_transform.clone());
+        }
+    }
+
+    let max_per_family = match matches.value_of("max-per-family") {
+        Some(max_per_family) => match max_per_family.parse::<usize>() {
+            Ok(max_per_family) => Some(max_per_family),
+            Err(_) => {
+                println!("invalid --max-per-family: {}", max_per_family);
+                exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // the payloads subcommand prints the variants that would be sent and exits.
+    if let Some(payloads_matches) = matches.subcommand_matches("payloads") {
+        let payload = payloads_matches.value_of("payload").unwrap().to_string();
+        let preview_transforms = match payloads_matches.value_of("family") {
+            Some(family) => match transform::parse_transforms(family, &custom_transforms) {
+                Ok(preview_transforms) => preview_transforms,
+                Err(e) => {
+                    println!("{}", e);
+                    exit(1);
+                }
+            },
+            None => transform::builtin_transforms()
+                .into_iter()
+                .chain(custom_transforms)
+                .collect(),
+        };
+        let level = match payloads_matches.value_of("level").unwrap().parse::<u32>() {
+            Ok(level) if level > 0 => level,
+            _ => {
+                println!("invalid --level, use 1 or higher");
+                exit(1);
+            }
+        };
+        for (variant, family) in
+            transform::generate_payloads(&[payload], &preview_transforms, max_per_family)
+        {
+            let variant = match waf::escalate(&variant, level) {
+                Some(variant) => variant,
+                None => format!("{} (dropped at level {})", variant, level),
+            };
+            println!(
+                "{} {}{}{} {}",
+                "payload ::".bold().green(),
+                "[".bold().white(),
+                family.bold().purple(),
+                "]".bold().white(),
+                variant.bold().blue(),
+            );
+        }
+        return Ok(());
+    }
+
+    // the history subcommand prints the findings of a host over the stored scans and exits.
+    if let Some(history_matches) = matches.subcommand_matches("history") {
+        let host = history_matches.value_of("host").unwrap();
+        let db_path = history_matches.value_of("db").unwrap();
+        let histories = match output::db::history(db_path, host) {
+            Ok(histories) => histories,
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        };
+        if histories.is_empty() {
+            println!("no findings for {} in {}", host, db_path);
+        }
+        for history in histories {
+            println!(
+                "{} {} {}{}{}",
+                "history ::".bold().green(),
+                history.url.bold().blue(),
+                "[".bold().white(),
+                history.kind.bold().purple(),
+                "]".bold().white(),
+            );
+            println!(
+                "\t {} {}  {} {}",
+                "first seen:".bold().white(),
+                utils::format_time(history.first_seen).cyan(),
+                "last seen:".bold().white(),
+                utils::format_time(history.last_seen).cyan(),
+            );
+            for (started_at, status) in history.changes {
+                let status = match status {
+                    output::db::Status::Found => status.to_string().bold().green(),
+                    output::db::Status::Resolved => status.to_string().bold().yellow(),
+                    output::db::Status::Regressed => status.to_string().bold().red(),
+                };
+                println!("\t {} {}", utils::format_time(started_at).white(), status,);
+            }
+        }
+        return Ok(());
+    }
+
+    // the merge subcommand joins the outputs of the shards of a scan and exits.
+    if let Some(merge_matches) = matches.subcommand_matches("merge") {
+        let files: Vec<&str> = merge_matches.values_of("files").unwrap().collect();
+        let merged = match output::merge::merge(&files) {
+            Ok(merged) => merged,
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        };
+        let mut lines = merged
+            .iter()
+            .map(|finding| finding.to_string())
+            .collect::<Vec<String>>()
+            .join("\n");
+        match merge_matches.value_of("out") {
+            Some(out) => {
+                lines.push('\n');
+                if let Err(e) = std::fs::write(out, lines) {
+                    println!("failed to write merged findings: {:?}", e);
+                    exit(1);
+                }
+                println!(
+                    "{} {} {} {}",
+                    "merged ::".bold().green(),
+                    merged.len().to_string().bold().cyan(),
+                    "findings into".bold().white(),
+                    out.bold().cyan(),
+                );
+            }
+            None => println!("{}", lines),
+        }
+        return Ok(());
+    }
+
+    // the controller subcommand hands the scan out to the agents and exits once they are done.
+    if let Some(controller_matches) = matches.subcommand_matches("controller") {
+        let agents = match controller_matches
+            .value_of("agents")
+            .unwrap()
+            .parse::<usize>()
+        {
+            Ok(agents) if agents > 0 => agents,
+            _ => {
+                println!("invalid --agents, use a number above 0");
+                exit(1);
+            }
+        };
+        let urls = match std::fs::read_to_string(controller_matches.value_of("urls").unwrap()) {
+            Ok(contents) => contents
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect(),
+            Err(e) => {
+                println!("failed to open input file: {:?}", e);
+                exit(1);
+            }
+        };
+        let listen = controller_matches.value_of("listen").unwrap();
+        let token = controller_matches.value_of("token").unwrap_or("");
+        let out = controller_matches.value_of("out");
+        match agent::run_controller(listen, token, agents, urls, out).await {
+            Ok(count) => println!(
+                "{} {} {}",
+                "Completed!".bold().green(),
+                count.to_string().bold().cyan(),
+                "findings reported by the agents".bold().white(),
+            ),
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    let mut skip_dir = matches.is_present("skip-brute");
+    let head_first = matches.is_present("head-first");
+    let calibration = matches
+        .is_present("calibrate")
+        .then(|| Arc::new(bruteforcer::Calibration::default()));
+    let skip_validation = matches.is_present("skip-validation");
+    if skip_validation {
+        skip_dir = true;
+    }
+
+    let inject_at = match detector::InjectAt::parse(matches.value_of("inject-at").unwrap()) {
+        Some(inject_at) => inject_at,
+        None => {
+            println!("invalid --inject-at, use end, each-segment or index:N");
+            exit(1);
+        }
+    };
+    let traversal_strategy =
+        match detector::TraversalStrategy::parse(matches.value_of("traversal-strategy").unwrap()) {
+            Some(traversal_strategy) => traversal_strategy,
+            None => {
+                println!("invalid --traversal-strategy, use linear or smart");
+                exit(1);
+            }
+        };
+    let mut shard = match matches.value_of("shard") {
+        Some(value) => match detector::Shard::parse(value) {
+            Some(shard) => Some(shard),
+            None => {
+                println!("invalid --shard, use <index>/<total> such as 2/5");
+                exit(1);
+            }
+        },
+        None => None,
+    };
+    let scope_domains: Vec<&str> = matches.values_of("scope").unwrap_or_default().collect();
+    let exclude_paths: Vec<&str> = matches
+        .values_of("exclude-path")
+        .unwrap_or_default()
+        .collect();
+    let exclude_regexes: Vec<&str> = matches
+        .values_of("exclude-regex")
+        .unwrap_or_default()
+        .collect();
+    let scope = match scope::Scope::new(&scope_domains, &exclude_paths, &exclude_regexes) {
+        Ok(scope) => Arc::new(scope),
+        Err(e) => {
+            println!("{}", e);
+            exit(1);
+        }
+    };
+
+    // an agent scans the shard and the urls the controller pushes to it, and
+    // streams the findings back as they are found.
+    let mut agent_urls = None;
+    let agent_token = matches.value_of("agent-token").unwrap_or("");
+    let agent = match matches.value_of("agent") {
+        Some(controller) => match agent::connect(controller, agent_token).await {
+            Ok((connection, assignment)) => {
+                shard = detector::Shard::parse(&assignment.shard);
+                agent_urls = Some(assignment.urls);
+                Some(connection.stream_findings())
+            }
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let mut methods = vec![];
+    for method in matches.value_of("methods").unwrap().split(',') {
+        let method = method.trim().to_uppercase();
+        if method.is_empty() {
+            continue;
+        }
+        match reqwest::Method::from_bytes(method.as_bytes()) {
+            Ok(method) => methods.push(method),
+            Err(_) => {
+                println!("invalid http method: {}", method);
+                exit(1);
+            }
+        }
+    }
+    if methods.is_empty() {
+        methods.push(reqwest::Method::GET);
+    }
+
+    let body = match matches.value_of("body-file") {
+        Some(body_path) => match std::fs::read_to_string(body_path) {
+            Ok(body) => body.trim_end_matches('\n').to_string(),
+            Err(e) => {
+                println!("failed to open body file: {:?}", e);
+                exit(1);
+            }
+        },
+        None => matches.value_of("body").unwrap_or("").to_string(),
+    };
+    if reqwest::header::HeaderValue::from_str(matches.value_of("content-type").unwrap()).is_err() {
+        println!(
+            "invalid content type: {}",
+            matches.value_of("content-type").unwrap()
+        );
+        exit(1);
+    }
+
+    // the default wordlist only applies when no wordlist directory was given either.
+    let mut wordlist_sources: Vec<String> = vec![];
+    if matches.value_source("wordlist") != Some(ValueSource::DefaultValue)
+        || !matches.is_present("wordlist-dir")
+    {
+        if let Some(values) = matches.values_of("wordlist") {
+            wordlist_sources.extend(values.map(|value| value.to_string()));
+        }
+    }
+    if let Some(wordlist_dir) = matches.value_of("wordlist-dir") {
+        match wordlist::list_dir(wordlist_dir) {
+            Ok(paths) => wordlist_sources.extend(paths),
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        }
+    }
+    if wordlist_sources.is_empty() {
+        println!("{}", "invalid wordlist file");
+        exit(1);
+    }
+    let mut wordlist_options = wordlist::WordlistOptions {
+        lowercase: matches.is_present("wordlist-lowercase"),
+        strip_slashes: matches.is_present("wordlist-strip-slashes"),
+        drop_invalid: matches.is_present("wordlist-drop-invalid"),
+        ..Default::default()
+    };
+    for (name, length) in [
+        ("wordlist-min-length", &mut wordlist_options.min_length),
+        ("wordlist-max-length", &mut wordlist_options.max_length),
+    ] {
+        if let Some(value) = matches.value_of(name) {
+            match value.parse::<usize>() {
+                Ok(value) => *length = Some(value),
+                Err(_) => {
+                    println!("invalid --{}: {}", name, value);
+                    exit(1);
+                }
+            }
+        }
+    }
+    // without --urls the urls are read from stdin when it is piped, eg `httpx | pathbuster`.
+    let urls_path = match matches.get_one::<String>("urls").map(|s| s.to_string()) {
+        Some(urls_path) => urls_path,
+        None if matches.is_present("agent")
+            || matches.is_present("import-burp")
+            || matches.is_present("import-har")
+            || matches.is_present("raw-request")
+            || fingerprint_matches.is_some() =>
+        {
+            "".to_string()
+        }
+        None if !std::io::stdin().is_terminal() => "-".to_string(),
+        None => {
+            println!("--urls is required, give the file with the urls, - or pipe them in");
+            exit(1);
+        }
+    };
+    if urls_path == "-" && matches.is_present("triage") {
+        println!("--triage reads its commands from stdin, give the urls with --urls <file>");
+        exit(1);
+    }
+    // each batch of new urls is scanned by a pathbuster of its own with the same flags.
+    if matches.is_present("watch") {
+        if urls_path.is_empty() || urls_path == "-" {
+            println!("--watch follows a file, give the urls with --urls <file>");
+            exit(1);
+        }
+        if let Err(e) = watch::run(&urls_path, silent).await {
+            println!("{}", e);
+            exit(1);
+        }
+        return Ok(());
+    }
+    // copy some variables
+    let _urls_path = urls_path.clone();
+
+    let int_status = match matches
+        .get_one::<String>("int-status")
+        .map(|s| s.to_string())
+    {
+        Some(int_status) => int_status,
+        None => "".to_string(),
+    };
+
+    let pub_status = match matches
+        .get_one::<String>("pub-status")
+        .map(|s| s.to_string())
+    {
+        Some(pub_status) => pub_status,
+        None => "".to_string(),
+    };
+
+    let timeout = match matches.get_one::<String>("timeout").map(|s| s.to_string()) {
+        Some(timeout) => timeout.parse::<usize>().unwrap(),
+        None => 10,
+    };
+
+    let http3 = matches.is_present("http3");
+    if http3 && !cfg!(feature = "http3") {
+        println!("http3 support is not compiled in, rebuild pathbuster with --features http3");
+        exit(1);
+    }
+
+    let identity = match matches.value_of("client-cert") {
+        Some(cert_path) => {
+            let key_path = matches.value_of("client-key").unwrap_or("");
+            let password = matches.value_of("client-cert-password").unwrap_or("");
+            match client::load_identity(cert_path, key_path, password) {
+                Ok(identity) => Some(identity),
+                Err(e) => {
+                    println!("{}", e);
+                    exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let insecure = matches.is_present("insecure");
+    let ca_certs = match matches.value_of("ca-cert") {
+        Some(ca_path) => match client::load_ca_certs(ca_path) {
+            Ok(ca_certs) => ca_certs,
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        },
+        None => vec![],
+    };
+
+    let tls_min = matches
+        .value_of("tls-min")
+        .and_then(client::parse_tls_version);
+    let tls_max = matches
+        .value_of("tls-max")
+        .and_then(client::parse_tls_version);
+    if let (Some(tls_min), Some(tls_max)) = (tls_min, tls_max) {
+        if tls_min > tls_max {
+            println!("--tls-min can not be greater than --tls-max");
+            exit(1);
+        }
+    }
+
+    let sni = matches.value_of("sni").unwrap_or("").to_string();
+    let connect_to = match matches.value_of("connect-to") {
+        Some(connect_to) => match tokio::net::lookup_host(connect_to).await {
+            Ok(mut addrs) => match addrs.next() {
+                Some(addr) => Some(addr),
+                None => {
+                    println!("could not resolve --connect-to: {}", connect_to);
+                    exit(1);
+                }
+            },
+            Err(e) => {
+                println!("invalid --connect-to {}: {:?}", connect_to, e);
+                exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // the captured requests are traversal anchors, the traversals of their url
+    // are sent with their method, headers and body.
+    let mut raw_requests = vec![];
+    for raw_path in matches.values_of("raw-request").unwrap_or_default() {
+        match auth::load_raw_requests(raw_path) {
+            Ok(loaded) => raw_requests.extend(loaded),
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        }
+    }
+
+    let routes = match matches.value_of("routes") {
+        Some(routes_path) => match routes::load(routes_path) {
+            Ok(routes) => routes,
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        },
+        None => vec![],
+    };
+
+    let login_request = match matches.value_of("login-request") {
+        Some(login_path) => match auth::load_raw_request(login_path) {
+            Ok(login_request) => Some(login_request),
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let expired_statuses: Vec<u16> = matches
+        .value_of("session-expired-status")
+        .unwrap()
+        .split(',')
+        .filter_map(|status| status.trim().parse::<u16>().ok())
+        .collect();
+    let expired_regex = match matches.value_of("session-expired-regex") {
+        Some(expired_regex) => match regex::Regex::new(expired_regex) {
+            Ok(expired_regex) => Some(expired_regex),
+            Err(e) => {
+                println!("invalid --session-expired-regex: {:?}", e);
+                exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let authorization = if let Some(credentials) = matches.value_of("auth-basic") {
+        Some(auth::basic_authorization(credentials))
+    } else {
+        matches
+            .value_of("auth-bearer")
+            .map(auth::bearer_authorization)
+    };
+    let authorization = match authorization {
+        Some(Ok(authorization)) => Some(authorization),
+        Some(Err(e)) => {
+            println!("{}", e);
+            exit(1);
+        }
+        None => None,
+    };
+
+    let ntlm = match matches
+        .value_of("auth-ntlm")
+        .map(auth::ntlm::parse_credentials)
+    {
+        Some(Ok(ntlm)) => Some(ntlm),
+        Some(Err(e)) => {
+            println!("{}", e);
+            exit(1);
+        }
+        None => None,
+    };
+
+    let delay = match matches.value_of("delay") {
+        Some(value) => match client::parse_delay(value) {
+            Some(delay) => Some(delay),
+            None => {
+                println!("invalid --delay {}, use eg 250ms, 1s or 100-400ms", value);
+                exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let accept_encoding = if matches.is_present("no-compression") {
+        Some(reqwest::header::HeaderValue::from_static("identity"))
+    } else {
+        match matches.value_of("accept-encoding") {
+            Some(value) => match reqwest::header::HeaderValue::from_str(value) {
+                Ok(accept_encoding) => Some(accept_encoding),
+                Err(_) => {
+                    println!("invalid --accept-encoding {}", value);
+                    exit(1);
+                }
+            },
+            None => None,
+        }
+    };
+    let similarity_algorithm = match matches.value_of("similarity") {
+        Some(value) => match utils::SimilarityAlgorithm::parse(value) {
+            Some(algorithm) => Some(algorithm),
+            None => {
+                println!("invalid --similarity, use sift3, simhash, levenshtein-lines or jaccard-shingles");
+                exit(1);
+            }
+        },
+        None => None,
+    };
+    let mut similarity = utils::Similarity::new(similarity_algorithm);
+    for value in matches
+        .values_of("similarity-threshold")
+        .unwrap_or_default()
+    {
+        similarity = match utils::parse_threshold(value) {
+            Some((algorithm, threshold)) => similarity.with_threshold(algorithm, threshold),
+            None => {
+                println!(
+                    "invalid --similarity-threshold {}, use eg simhash=5 or sift3=300-100000",
+                    value
+                );
+                exit(1);
+            }
+        };
+    }
+
+    let mut client_settings = ClientSettings {
+        timeout,
+        http_proxy,
+        proxy_auth,
+        http3,
+        resolve_overrides,
+        dns_resolver: dns_resolver.clone(),
+        address_family,
+        identity,
+        insecure,
+        ca_certs,
+        tls_min,
+        tls_max,
+        user_agent: matches.value_of("user-agent").unwrap().to_string(),
+        random_agent: matches.is_present("random-agent"),
+        headers,
+        cookie_jar: Arc::new(reqwest::cookie::Jar::default()),
+        session: Arc::new(
+            auth::Session::new(
+                login_request,
+                authorization,
+                expired_statuses,
+                expired_regex,
+            )
+            .with_ntlm(ntlm),
+        ),
+        client_factory: None,
+        scope: scope.clone(),
+        target_headers: Arc::new(import::TargetHeaders::default()),
+        delay,
+        accept_encoding,
+        similarity: Arc::new(similarity),
+    };
+
+    // the fingerprint subcommand only profiles the target and prints what was learned.
+    if let Some(fingerprint_matches) = fingerprint_matches {
+        let url = fingerprint_matches.value_of("url").unwrap();
+        if let Some(cookies) = matches.value_of("cookie") {
+            client::add_cookies(&client_settings.cookie_jar, cookies, &[url.to_string()]);
+        }
+        let profile_client = client::build_client(&ProgressBar::hidden(), &client_settings, 10);
+        let fingerprint = fingerprint::profile(&profile_client, url, None).await;
+        if fingerprint_matches.is_present("json") {
+            let mut json = serde_json::to_value(&fingerprint)?;
+            json["url"] = serde_json::Value::from(url);
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        } else {
+            fingerprint::print_fingerprint(url, &fingerprint);
+        }
+        return Ok(());
+    }
+
+    let w: usize = match matches.value_of("workers").unwrap().parse::<usize>() {
+        Ok(w) => w,
+        Err(_) => {
+            tracing::warn!("could not parse workers, using default of 10");
+            10
+        }
+    };
+
+    let now = Instant::now();
+    let started_at = utils::unix_time();
+
+    // the findings of the previous scan the findings of this one are compared with.
+    let baseline = match matches.value_of("diff") {
+        Some(diff_path) => match output::diff::load_baseline(diff_path) {
+            Ok(baseline) => Some(baseline),
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        },
+        None => None,
+    };
+    let diff_all = matches.is_present("diff-all");
+    let fail_on = match output::ci::Severity::parse(matches.value_of("fail-on").unwrap()) {
+        Some(fail_on) => fail_on,
+        None => {
+            println!("invalid --fail-on, use low, medium or high");
+            exit(1);
+        }
+    };
+
+    // the sinks the confirmed findings are sent to as soon as they are found.
+    let mut notifier = notify::Notifier::new();
+    if let Some(webhook_url) = matches.value_of("notify-webhook") {
+        let format = matches.value_of("notify-format").unwrap();
+        match notify::WebhookFormat::parse(format, webhook_url) {
+            Some(format) => notifier = notifier.with_webhook(webhook_url, format),
+            None => {
+                println!("invalid --notify-format, use generic, slack, discord or auto");
+                exit(1);
+            }
+        }
+    }
+    if let Some(syslog) = matches.value_of("syslog") {
+        match notify::parse_syslog(syslog) {
+            Some((transport, address)) => notifier = notifier.with_syslog(transport, &address),
+            None => {
+                println!("invalid --syslog, use udp://host:port or tcp://host:port");
+                exit(1);
+            }
+        }
+    }
+    if let Some(sd_id) = matches.value_of("syslog-sd-id") {
+        if !notify::valid_sd_id(sd_id) {
+            println!("invalid --syslog-sd-id, use a name and a private enterprise number (eg finding@12345)");
+            exit(1);
+        }
+        notifier = notifier.with_syslog_sd_id(sd_id);
+    }
+    let silent_format = match output::silent::LineFormat::parse(matches.value_of("format").unwrap())
+    {
+        Some(silent_format) => silent_format,
+        None => {
+            println!("invalid --format, use url or jsonl");
+            exit(1);
+        }
+    };
+    if silent {
+        notifier = notifier.with_stdout(silent_format);
+    }
+    let notifier = Arc::new(notifier);
+    let notifier_task = notifier.start();
+
+    // the counters are kept for --metrics-listen and --json-progress.
+    observer::register(Arc::new(metrics::MetricsObserver));
+
+    // stream the events of the scan to the --events file.
+    let events_writer = match matches.value_of("events") {
+        Some(events_path) => {
+            let mut events_file = match std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(events_path)
+            {
+                Ok(events_file) => events_file,
+                Err(e) => {
+                    println!("failed to open events file: {:?}", e);
+                    exit(1);
+                }
+            };
+            let mut events = Box::pin(events::subscribe());
+            Some(tokio::spawn(async move {
+                while let Some(event) = events.next().await {
+                    if let Ok(line) = serde_json::to_string(&event) {
+                        if let Err(e) = writeln!(events_file, "{}", line) {
+                            tracing::warn!(error = %e, "failed to write the events file");
+                        }
+                    }
+                }
+            }))
+        }
+        None => None,
+    };
+
+    // serve the counters of the scan to prometheus.
+    if let Some(metrics_listen) = matches.value_of("metrics-listen") {
+        let addr = match metrics_listen.parse() {
+            Ok(addr) => addr,
+            Err(_) => {
+                println!("invalid --metrics-listen, use an address such as 127.0.0.1:9900");
+                exit(1);
+            }
+        };
+        if let Err(e) = metrics::serve(addr) {
+            println!("{}", e);
+            exit(1);
+        }
+    }
+
+    // load the payloads and the wordlist, both can be a path or a http(s) url.
+    let list_client = wordlist::list_client();
+    let refresh_lists = matches.is_present("refresh-lists");
+    let payloads = match wordlist::load_list(&list_client, payloads_path, refresh_lists).await {
+        Ok(payloads) => payloads,
+        Err(e) => {
+            println!("{}", e);
+            exit(1);
+        }
+    };
+    let (wordlist, wordlist_summary) =
+        match wordlist::load_wordlist(&list_client, &wordlist_sources, refresh_lists).await {
+            Ok(wordlist) => wordlist,
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        };
+    let mut wordlist = wordlist::apply_wordlist_manipulations(wordlist, &wordlist_options);
+    let extension_mode = if matches.is_present("force-extensions") {
+        wordlist::ExtensionMode::Force
+    } else if matches.is_present("dirsearch") {
+        wordlist::ExtensionMode::Placeholder
+    } else {
+        wordlist::ExtensionMode::Append
+    };
+    let mut extensions = matches
+        .value_of("extensions")
+        .map(wordlist::parse_extensions)
+        .unwrap_or_default();
+    if matches.is_present("extensions") {
+        wordlist = wordlist::apply_wordlist_extensions(wordlist, &extensions, extension_mode);
+    }
+
+    let mut urls = vec![];
+
+    let payloads = transform::clean_payloads(payloads);
+    let redundant = if silent {
+        vec![]
+    } else {
+        transform::redundant_payloads(&payloads, &transforms)
+    };
+    for (payload, family, source) in redundant {
+        println!(
+            "{}{}{} {} {} {} {}",
+            "[".bold().white(),
+            "WRN".bold().yellow(),
+            "]".bold().white(),
+            payload.bold().blue(),
+            format!("is generated by {} from", family).white(),
+            source.bold().blue(),
+            "anyway, it can be removed from the payloads file".white(),
+        );
+    }
+    let generated = transform::generate_payloads(&payloads, &transforms, max_per_family);
+    let family_stats = Arc::new(transform::FamilyStats::new(&generated));
+    let payloads: Vec<String> = generated.into_iter().map(|(payload, _)| payload).collect();
+
+    // read the vhost wordlist if specified and append each line to an array.
+    let mut vhosts = vec![];
+    if let Some(vhost_wordlist_path) = matches.value_of("vhost-wordlist") {
+        let vhost_handle = match File::open(vhost_wordlist_path).await {
+            Ok(vhost_handle) => vhost_handle,
+            Err(e) => {
+                println!("failed to open input file: {:?}", e);
+                exit(1);
+            }
+        };
+        let mut vhost_lines = BufReader::new(vhost_handle).lines();
+        while let Ok(Some(vhost)) = vhost_lines.next_line().await {
+            let vhost = vhost.trim().to_string();
+            if !vhost.is_empty() {
+                vhosts.push(vhost);
+            }
+        }
+    }
+
+    // the urls piped in are scanned as they arrive, unless the scan needs the
+    // whole list up front: the estimate, the --routes under each target, the
+    // --sni and --connect-to routing and the login cookies stored per target.
+    let stream_stdin = urls_path == "-"
+        && agent_urls.is_none()
+        && matches.subcommand_matches("estimate").is_none()
+        && routes.is_empty()
+        && sni.is_empty()
+        && connect_to.is_none()
+        && !matches.is_present("login-request");
+
+    // read the hosts file if specified and append each line to an array.
+    if let Some(agent_urls) = agent_urls {
+        urls = agent_urls;
+    } else if urls_path == "-" && !stream_stdin {
+        // the scan starts once the pipe is closed, the targets are profiled together.
+        let mut urls_lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Ok(Some(url)) = urls_lines.next_line().await {
+            if !url.trim().is_empty() {
+                urls.push(url);
+            }
+        }
+    } else if !urls_path.is_empty() {
+        let urls_handle = match File::open(urls_path).await {
+            Ok(urls_handle) => urls_handle,
+            Err(e) => {
+                println!("failed to open input file: {:?}", e);
+                exit(1);
+            }
+        };
+        let urls_buf = BufReader::new(urls_handle);
+        let mut urls_lines = urls_buf.lines();
+        while let Ok(Some(url)) = urls_lines.next_line().await {
+            urls.push(url);
+        }
+    }
+    // the urls browsed through burp or recorded in a har file are scanned with
+    // the headers and cookies they were sent with, so the authenticated routes
+    // are tested too.
+    if matches.is_present("import-burp") || matches.is_present("import-har") {
+        let mut imported = import::Import::default();
+        if let Some(burp_path) = matches.value_of("import-burp") {
+            if let Err(e) = imported.burp(burp_path) {
+                println!("{}", e);
+                exit(1);
+            }
+        }
+        if let Some(har_path) = matches.value_of("import-har") {
+            if let Err(e) = imported.har(har_path) {
+                println!("{}", e);
+                exit(1);
+            }
+        }
+        for url in imported.urls {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+        for (origin, cookies) in &imported.cookies {
+            client::add_cookies(
+                &client_settings.cookie_jar,
+                cookies,
+                std::slice::from_ref(origin),
+            );
+        }
+        client_settings.target_headers = Arc::new(imported.headers);
+    }
+    let mut anchors: HashMap<String, Vec<auth::RawRequest>> = HashMap::new();
+    for raw_request in raw_requests {
+        let url = raw_request.url.to_string();
+        if !urls.contains(&url) {
+            urls.push(url.clone());
+        }
+        anchors.entry(url).or_default().push(raw_request);
+    }
+    let anchors = Arc::new(anchors);
+    // a target can be followed by its tags, `url tag1,tag2`, or be a json line
+    // with its url and tags, the findings are reported with the tags.
+    // the -json lines of httpx are targets too, with what httpx learned about them.
+    let target_tags = tags::TargetTags::default();
+    let mut httpx_targets = HashMap::new();
+    for url in urls.iter_mut() {
+        let httpx_target = fingerprint::parse_httpx(url);
+        let (target, tags) = tags::parse_target(url);
+        if let Some(httpx_target) = httpx_target {
+            httpx_targets.insert(target.clone(), httpx_target);
+        }
+        target_tags.insert(&target, tags);
+        *url = target;
+    }
+    let target_tags = Arc::new(target_tags);
+    // the routes of the --routes file are targets of their own under each target,
+    // the findings on them carry the tags of the target.
+    let mut route_methods = HashMap::new();
+    if !routes.is_empty() {
+        let mut targets: HashSet<String> = urls.iter().cloned().collect();
+        for (url, methods) in routes::expand(&urls, &routes) {
+            if targets.insert(url.clone()) {
+                urls.push(url.clone());
+            }
+            route_methods.insert(url, methods);
+        }
+    }
+    let route_methods = Arc::new(route_methods);
+    // the targets out of scope are dropped before anything is sent to them.
+    let targets = urls.len();
+    urls.retain(|url| scope.allows(url));
+    if urls.len() < targets && !silent {
+        println!(
+            "{}{}{} {}",
+            "[".bold().white(),
+            "WRN".bold().yellow(),
+            "]".bold().white(),
+            format!(
+                "{} targets are out of scope and skipped",
+                targets - urls.len()
+            )
+            .white(),
+        );
+    }
+
+    // the estimate subcommand counts the requests of each stage and exits before
+    // any target is resolved or contacted.
+    if matches.subcommand_matches("estimate").is_some() {
+        let mut traversal_requests = 0;
+        for url in &urls {
+            let url_methods = match (anchors.get(url), route_methods.get(url)) {
+                (Some(raw_requests), _) => raw_requests.len(),
+                (None, Some(route_methods)) if !route_methods.is_empty() => route_methods.len(),
+                _ => methods.len(),
+            };
+            traversal_requests += detector::max_traversal_requests(
+                url,
+                inject_at,
+                skip_validation,
+                payloads.len(),
+                url_methods,
+                wordlist.len(),
+            );
+        }
+        // the target itself, the four probes and the favicon.
+        let profiling_requests = if matches.is_present("skip-profiling") {
+            0
+        } else {
+            urls.len() * 6
+        };
+        let total = profiling_requests + traversal_requests;
+        let per_finding = if skip_dir {
+            0
+        } else {
+            wordlist.len() * if head_first { 5 } else { 3 }
+                + if calibration.is_some() {
+                    bruteforcer::CALIBRATION_SAMPLES
+                } else {
+                    0
+                }
+        } + if vhosts.is_empty() {
+            0
+        } else {
+            vhosts.len() + 1
+        };
+
+        let stages = [
+            ("profiling", profiling_requests),
+            ("traversals", traversal_requests),
+            ("total", total),
+            ("per finding", per_finding),
+        ];
+        for (stage, requests) in stages {
+            println!(
+                "{} {} {}",
+                format!("{:<12}::", stage).bold().green(),
+                requests.to_string().bold().cyan(),
+                "requests at most".white(),
+            );
+        }
+        let seconds = total as u64 / rate.max(1) as u64;
+        println!(
+            "{} {} {}",
+            format!("{:<12}::", "duration").bold().green(),
+            format!(
+                "{}h {}m {}s",
+                seconds / 3600,
+                seconds % 3600 / 60,
+                seconds % 60
+            )
+            .bold()
+            .cyan(),
+            format!("at {} requests per second", rate).white(),
+        );
+        return Ok(());
+    }
+
+    // resolve the target hostnames once so the workers reuse the cached addresses.
+    let resolved_hosts = dns_resolver.preresolve(&urls).await;
+    if !sni.is_empty() || connect_to.is_some() {
+        urls = dns::route_targets(
+            urls,
+            &sni,
+            connect_to,
+            &dns_resolver,
+            &mut client_settings.resolve_overrides,
+        );
+    }
+    if let Some(cookies) = matches.value_of("cookie") {
+        client::add_cookies(&client_settings.cookie_jar, cookies, &urls);
+    }
+    if matches.is_present("shared-pool") {
+        client_settings.client_factory = Some(client::shared_factory(
+            &ProgressBar::hidden(),
+            &client_settings,
+        ));
+    }
+    if client_settings.session.login_request.is_some() {
+        let login_client = client::build_client(&ProgressBar::hidden(), &client_settings, 10);
+        match auth::login(&login_client, &client_settings.session, &urls).await {
+            Ok(status) if !silent => println!(
+                "{} {}",
+                "login request completed with status".bold().white(),
+                status.to_string().bold().cyan()
+            ),
+            Ok(_) => {}
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        }
+    }
+
+    // profile how the front-end of each target normalizes paths
+    // so the payload families it refuses can be pruned.
+    let fingerprints = if matches.is_present("skip-profiling") {
+        HashMap::new()
+    } else {
+        let profile_client = client::build_client(&ProgressBar::hidden(), &client_settings, 10);
+        fingerprint::profile_targets(&profile_client, &urls, &httpx_targets, concurrency as usize)
+            .await
+    };
+
+    // without --extensions the extensions are detected from the links of the targets.
+    let extensions_detected =
+        !matches.is_present("extensions") && !matches.is_present("skip-profiling");
+    if extensions_detected {
+        let detect_client = client::build_client(&ProgressBar::hidden(), &client_settings, 10);
+        extensions = wordlist::detect_extensions(&detect_client, &urls, concurrency as usize).await;
+    }
+    if !matches.is_present("extensions") {
+        wordlist = wordlist::apply_wordlist_extensions(wordlist, &extensions, extension_mode);
+    }
+
+    // the scan is echoed unless only the findings are printed.
+    if !silent {
+        // the certificate names often point at the real backend, so show them for scoping.
+        let mut certificates: Vec<_> = fingerprints
+            .iter()
+            .filter_map(|(url, fingerprint)| Some((url, fingerprint.certificate.as_ref()?)))
+            .collect();
+        certificates.sort_by(|a, b| a.0.cmp(b.0));
+        for (url, certificate) in certificates {
+            println!(
+                "{} {} {}{}{} {}{}{} {}{}{}",
+                "certificate ::".bold().green(),
+                url.bold().blue(),
+                "[".bold().white(),
+                certificate.subject.bold().purple(),
+                "]".bold().white(),
+                "[".bold().white(),
+                certificate.issuer.bold().cyan(),
+                "]".bold().white(),
+                "[".bold().white(),
+                certificate.sans.join(", ").bold().purple(),
+                "]".bold().white(),
+            );
+        }
+
+        // a traversal the cdn refuses can still work against the origin, so point out the
+        // input urls that look like the origin of a target served from a cdn.
+        for hint in fingerprint::origin_hints(&fingerprints) {
+            println!(
+                "{} {} {}{}{} {} {}",
+                "origin hint ::".bold().green(),
+                hint.url.bold().blue(),
+                "[".bold().white(),
+                hint.cdn.to_string().bold().purple(),
+                "]".bold().white(),
+                "retry the traversals against".bold().white(),
+                hint.origin.bold().blue(),
+            );
+        }
+
+        // set the message
+        println!(
+            "{}",
+            "----------------------------------------------------------"
+                .bold()
+                .white()
+        );
+        println!(
+            "{}  {}      {} {}\n{}  {}          {} {}\n{}  {}      {} {}\n{}  {}  {} {}\n{}  {}  {} {}\n{}  {}   {} {}\n{}  {}       {} {}",
+            ">".bold().green(),
+            "Payloads".bold().white(),
+            ":".bold().white(),
+            payloads.len().to_string().bold().cyan(),
+            ">".bold().green(),
+            "Urls".bold().white(),
+            ":".bold().white(),
+            urls.len().to_string().bold().cyan(),
+            ">".bold().green(),
+            "Resolved".bold().white(),
+            ":".bold().white(),
+            resolved_hosts.to_string().bold().cyan(),
+            ">".bold().green(),
+            "Int Matchers".bold().white(),
+            ":".bold().white(),
+            int_status.to_string().bold().cyan(),
+            ">".bold().green(),
+            "Pub Matchers".bold().white(),
+            ":".bold().white(),
+            pub_status.to_string().bold().cyan(),
+            ">".bold().green(),
+            "Concurrency".bold().white(),
+            ":".bold().white(),
+            concurrency.to_string().bold().cyan(),
+            ">".bold().green(),
+            "Workers".bold().white(),
+            ":".bold().white(),
+            w.to_string().bold().cyan(),
+        );
+        for (source, loaded, added) in &wordlist_summary {
+            println!(
+                "{}  {}      {} {} {}",
+                ">".bold().green(),
+                "Wordlist".bold().white(),
+                ":".bold().white(),
+                source.bold().cyan(),
+                format!("({} words, {} new)", loaded, added).white(),
+            );
+        }
+        if !extensions.is_empty() || extensions_detected {
+            let extensions_list = if extensions.is_empty() {
+                "none".to_string()
+            } else {
+                extensions.join(", ")
+            };
+            let origin = if extensions_detected {
+                "(detected)"
+            } else {
+                "(given)"
+            };
+            println!(
+                "{}  {}    {} {} {}",
+                ">".bold().green(),
+                "Extensions".bold().white(),
+                ":".bold().white(),
+                extensions_list.bold().cyan(),
+                origin.white(),
+            );
+        }
+        println!(
+            "{}",
+            "----------------------------------------------------------"
+                .bold()
+                .white()
+        );
+        println!("");
+    }
+
+    // the job settings
+    let escalation = Arc::new(Escalation::default());
+    let mut loaded_plugins = vec![];
+    for plugin_path in matches.values_of("plugin").unwrap_or_default() {
+        match plugin::load(plugin_path) {
+            Ok(plugin) => loaded_plugins.push(plugin),
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        }
+    }
+    let plugins = Arc::new(loaded_plugins);
+    let script = match matches.value_of("script") {
+        Some(script_path) => match script::load(script_path) {
+            Ok(script) => Some(Arc::new(script)),
+            Err(e) => {
+                println!("{}", e);
+                exit(1);
+            }
+        },
+        None => None,
+    };
+    let word_learner = if matches.is_present("learn-words") || matches.is_present("iis-shortname") {
+        Some(Arc::new(
+            wordlist::WordLearner::new(&wordlist).with_tokenize(matches.is_present("learn-words")),
+        ))
+    } else {
+        None
+    };
+    let fingerprints = Arc::new(fingerprint::Fingerprints::new(fingerprints));
+    let job_settings = JobSettings {
+        int_status,
+        pub_status,
+        drop_after_fail,
+        skip_validation,
+        rewrite_headers: matches.is_present("rewrite-headers"),
+        spoof_ip: matches.is_present("spoof-ip"),
+        method_override: matches.is_present("method-override"),
+        body,
+        content_type: matches.value_of("content-type").unwrap().to_string(),
+        inject_at,
+        traversal_strategy,
+        stop_on_root: matches.is_present("stop-on-root"),
+        cache_deception: matches.is_present("cache-deception"),
+        segment_suffixes: transforms.contains(&transform::Transform::SegmentSuffix),
+        nginx_alias: matches.is_present("nginx-alias"),
+        iis_shortname: matches.is_present("iis-shortname"),
+        fingerprints: fingerprints.clone(),
+        escalation: escalation.clone(),
+        family_stats: family_stats.clone(),
+        word_learner: word_learner.clone(),
+        notifier: notifier.clone(),
+        shard,
+        plugins: plugins.clone(),
+        script: script.clone(),
+        scope: scope.clone(),
+        tags: target_tags.clone(),
+        raw_requests: anchors.clone(),
+        route_methods: route_methods.clone(),
+        ..Default::default()
+    };
+
+    // the bar counts the jobs of the stage the runner is at.
+    let pb = ProgressBar::new(0);
+    if ci || silent || json_progress {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+    } else {
+        pb.set_draw_target(ProgressDrawTarget::stderr());
+        pb.enable_steady_tick(Duration::from_millis(200));
+    }
+    pb.set_style(
+        ProgressStyle::with_template("{spinner:.blue} ({eta}) {elapsed} ({len}) {pos} {msg}")
+            .unwrap()
+            .progress_chars(r#"#>-"#),
+    );
+
+    // ctrl-c stops the dispatch of the jobs, the partial results are still saved.
+    let cancel_token = CancellationToken::new();
+    cancel::cancel_on_ctrl_c(pb.clone(), cancel_token.clone());
+
+    let outfile_path = match matches.value_of("out") {
+        Some(outfile_path) => outfile_path,
+        None => {
+            println!("{}", "invalid output file path");
+            exit(1);
+        }
+    };
+
+    // the urls piped in are tagged, profiled and scanned as they arrive.
+    let url_stream = if stream_stdin {
+        let (url_tx, url_rx) = mpsc::unbounded_channel::<String>();
+        let mut seen: HashSet<String> = urls.iter().cloned().collect();
+        let stream_scope = scope.clone();
+        let stream_tags = target_tags.clone();
+        let stream_fingerprints = fingerprints.clone();
+        let stream_resolver = dns_resolver.clone();
+        let cookie_jar = client_settings.cookie_jar.clone();
+        let cookies = matches.value_of("cookie").map(str::to_string);
+        let profile_client = (!matches.is_present("skip-profiling"))
+            .then(|| client::build_client(&ProgressBar::hidden(), &client_settings, 10));
+        tokio::spawn(async move {
+            let lines = BufReader::new(tokio::io::stdin()).lines();
+            futures::stream::unfold(lines, |mut lines| async move {
+                Some((lines.next_line().await.ok()??, lines))
+            })
+            .filter_map(|line| {
+                let httpx_target = fingerprint::parse_httpx(&line);
+                let (url, tags) = tags::parse_target(&line);
+                if url.is_empty() || !seen.insert(url.clone()) {
+                    return ready(None);
+                }
+                if !stream_scope.allows(&url) {
+                    tracing::info!(url = %url, "the target is out of scope and skipped");
+                    return ready(None);
+                }
+                stream_tags.insert(&url, tags);
+                ready(Some((url, httpx_target)))
+            })
+            .map(|(url, httpx_target)| {
+                let stream_resolver = &stream_resolver;
+                let stream_fingerprints = &stream_fingerprints;
+                let cookie_jar = &cookie_jar;
+                let cookies = &cookies;
+                let profile_client = &profile_client;
+                async move {
+                    stream_resolver.preresolve(std::slice::from_ref(&url)).await;
+                    if let Some(cookies) = cookies {
+                        client::add_cookies(cookie_jar, cookies, std::slice::from_ref(&url));
+                    }
+                    if let Some(profile_client) = profile_client {
+                        let fingerprint = fingerprint::profile_target(
+                            profile_client,
+                            &url,
+                            httpx_target.as_ref(),
+                        )
+                        .await;
+                        stream_fingerprints.insert(&url, fingerprint);
+                    }
+                    url
+                }
+            })
+            .buffer_unordered(concurrency.max(1) as usize)
+            .for_each(|url| {
+                let _ = url_tx.send(url);
+                ready(())
+            })
+            .await;
+        });
+        Some(url_rx)
+    } else {
+        None
+    };
+    let options = match Options::builder()
+        .with_urls(urls)
+        .with_payloads(payloads)
+        .with_methods(methods)
+        .with_wordlist(wordlist)
+        .with_vhosts(vhosts)
+        .with_rate(rate)
+        .with_concurrency(concurrency)
+        .with_workers(w)
+        .with_skip_brute(skip_dir)
+        .with_head_first(head_first)
+        .with_calibration(calibration)
+        .with_job_settings(job_settings)
+        .with_client_settings(client_settings)
+        .with_discoveries_out(Some("discovered-routes.txt".to_string()))
+        .with_silent(silent.then_some(silent_format))
+        .with_json_progress(json_progress)
+        .with_progress(pb.clone())
+        .build()
+    {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{}", e);
+            exit(1);
+        }
+    };
+    let mut runner = Runner::new(options);
+    if let Some(url_stream) = url_stream {
+        runner = runner.with_url_stream(url_stream);
+    }
+    let scan = match runner.run(cancel_token).await {
+        Ok(scan) => scan,
+        Err(e) => {
+            println!("{}", e);
+            exit(1);
+        }
+    };
+
+    // the follow up findings are reported even when no internal doc root was found.
+    let mut triage_findings: Vec<output::triage::TriageFinding> = vec![];
+    for finding in &scan.follow_ups {
+        triage_findings.push(output::triage::TriageFinding {
+            kind: finding.kind.name().to_string(),
+            url: finding.url.clone(),
+            family: finding.family.clone(),
+            status: finding.status.to_string(),
+            detail: finding.payload.clone(),
+            page: String::new(),
+            evidence: finding.evidence.clone(),
+            decision: Default::default(),
+            tags: finding.tags.clone(),
+        });
+    }
+    let mut internal_roots: Vec<String> = vec![];
+    for result in &scan.internal_roots {
+        internal_roots.push(result.data.clone());
+        triage_findings.push(output::triage::TriageFinding {
+            kind: "internal_root".to_string(),
+            url: result.data.clone(),
+            family: result.family.clone(),
+            status: result.status.clone(),
+            detail: String::new(),
+            page: result.meta.summary(),
+            evidence: String::new(),
+            decision: Default::default(),
+            tags: target_tags.tags(&result.data),
+        });
+        let outfile_handle_traversal = match OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(outfile_path)
+            .await
+        {
+            Ok(outfile_handle_traversal) => outfile_handle_traversal,
+            Err(e) => {
+                println!("failed to open output file: {:?}", e);
+                exit(1);
+            }
+        };
+        detector::save_traversals(pb.clone(), outfile_handle_traversal, result.data.clone()).await;
+    }
+    let scan_urls = scan.urls;
+    let follow_ups = scan.follow_ups;
+    let brute_results = scan.discoveries;
+    let vhost_results = scan.vhosts;
+    let family_summary = scan.families;
+    let scan_cancelled = scan.cancelled;
+
+    events::close();
+    if let Some(events_writer) = events_writer {
+        let _ = events_writer.await;
+    }
+    notifier.close();
+    let _ = notifier_task.await;
+
+    // add the scan to the results database.
+    if let Some(db_path) = matches.value_of("db") {
+        let record = output::db::ScanRecord {
+            started_at,
+            finished_at: utils::unix_time(),
+            urls: &scan_urls,
+            fingerprints: &fingerprints.all(),
+            internal_roots: &internal_roots,
+            follow_ups: &follow_ups,
+            discoveries: &brute_results,
+            vhosts: &vhost_results,
+            families: &family_summary,
+            cancelled: scan_cancelled,
+        };
+        if let Err(e) = output::db::write_scan(db_path, &record) {
+            println!("{}", e);
+        }
+    }
+
+    // the findings reported as annotations, with --diff the known ones are left out.
+    let mut ci_findings = vec![];
+    if ci {
+        let findings = internal_roots
+            .iter()
+            .map(|url| (output::ci::Severity::High, "internal doc root", url))
+            .chain(
+                follow_ups
+                    .iter()
+                    .map(|finding| (finding.kind.severity(), finding.kind.name(), &finding.url)),
+            )
+            .chain(
+                vhost_results
+                    .iter()
+                    .map(|result| (output::ci::Severity::Medium, "virtual host", &result.url)),
+            )
+            .chain(
+                brute_results
+                    .keys()
+                    .map(|url| (output::ci::Severity::Low, "discovery", url)),
+            );
+        for (severity, title, url) in findings {
+            if baseline
+                .as_ref()
+                .is_some_and(|baseline| baseline.contains(url))
+            {
+                continue;
+            }
+            ci_findings.push(output::ci::CiFinding {
+                severity,
+                title: title.to_string(),
+                url: url.clone(),
+            });
+        }
+    }
+
+    // classify the findings against the previous scan.
+    let changes = match &baseline {
+        Some(baseline) => {
+            let mut findings = internal_roots.clone();
+            findings.extend(brute_results.keys().cloned());
+            output::diff::diff(baseline, &findings)
+        }
+        None => vec![],
+    };
+
+    // the discoveries and virtual hosts join the internal doc roots for the
+    // triage.
+    let triage = matches.is_present("triage");
+    if triage {
+        for (url, content_length) in &brute_results {
+            triage_findings.push(output::triage::TriageFinding {
+                kind: "discovery".to_string(),
+                url: url.clone(),
+                family: String::new(),
+                status: "200".to_string(),
+                detail: content_length.clone(),
+                page: String::new(),
+                evidence: String::new(),
+                decision: Default::default(),
+                tags: target_tags.tags(url),
+            });
+        }
+        for result in &vhost_results {
+            triage_findings.push(output::triage::TriageFinding {
+                kind: "vhost".to_string(),
+                url: result.url.clone(),
+                family: String::new(),
+                status: result.status.clone(),
+                detail: result.host.clone(),
+                page: String::new(),
+                evidence: String::new(),
+                decision: Default::default(),
+                tags: target_tags.tags(&result.url),
+            });
+        }
+    }
+    // the agent streamed the findings, the virtual hosts among them.
+    if let Some(agent) = agent {
+        match agent.await {
+            Ok(agent) => agent.close().await,
+            Err(e) => println!("failed to stream the findings to the controller: {}", e),
+        }
+    }
+
+    // with --silent the notifier printed the findings as they were found.
+    if !silent {
+        // print out the discoveries, with --diff only the new ones.
+        println!("\n\n");
+        println!("{}", "Discovered:".bold().green());
+        println!("{}", "===========".bold().green());
+        for result in brute_results {
+            let known = baseline
+                .as_ref()
+                .is_some_and(|baseline| baseline.contains(&result.0));
+            if known && !diff_all {
+                continue;
+            }
+            println!(
+                "{} {} {} {}",
+                "::".bold().green(),
+                result.0.bold().white(),
+                "::".bold().green(),
+                result.1.bold().white()
+            );
+        }
+
+        if baseline.is_some() {
+            let count = |change: output::diff::Change| {
+                changes
+                    .iter()
+                    .filter(|(finding_change, _)| *finding_change == change)
+                    .count()
+            };
+            println!("\n");
+            println!("{}", "Diff:".bold().green());
+            println!("{}", "=====".bold().green());
+            println!(
+                "{} {} {} {}",
+                "::".bold().green(),
+                format!("{} new", count(output::diff::Change::New))
+                    .bold()
+                    .cyan(),
+                format!("{} known", count(output::diff::Change::Known)).white(),
+                format!("{} resolved", count(output::diff::Change::Resolved))
+                    .bold()
+                    .purple(),
+            );
+            for (change, finding) in &changes {
+                if *change != output::diff::Change::New && !diff_all {
+                    continue;
+                }
+                println!(
+                    "{} {} {} {}",
+                    "::".bold().green(),
+                    change.to_string().bold().cyan(),
+                    "::".bold().green(),
+                    finding.bold().white(),
+                );
+            }
+        }
+
+        let bypass_rates = escalation.bypass_rates();
+        if !bypass_rates.is_empty() {
+            println!("\n");
+            println!("{}", "WAF Bypass Rates:".bold().green());
+            println!("{}", "=================".bold().green());
+            for (host, waf_match, passed, total) in bypass_rates {
+                let waf_name = match waf_match {
+                    Some(waf_match) => waf_match.to_string(),
+                    None => "Unknown".to_string(),
+                };
+                println!(
+                    "{} {} {} {} {} {} {}",
+                    "::".bold().green(),
+                    host.bold().white(),
+                    "::".bold().green(),
+                    waf_name.bold().purple(),
+                    "::".bold().green(),
+                    format!("{}%", passed * 100 / total).bold().cyan(),
+                    format!("({}/{})", passed, total).white(),
+                );
+            }
+        }
+
+        if !family_summary.is_empty() {
+            println!("\n");
+            println!("{}", "Payload Families:".bold().green());
+            println!("{}", "=================".bold().green());
+            for outcome in family_summary {
+                println!(
+                    "{} {} {} {} {} {} {} {}",
+                    "::".bold().green(),
+                    outcome.family.bold().white(),
+                    "::".bold().green(),
+                    outcome.waf.bold().purple(),
+                    "::".bold().green(),
+                    format!("{} attempts", outcome.counts.attempts).white(),
+                    format!("{} blocked", outcome.counts.blocked).bold().red(),
+                    format!("{} bypassed", outcome.counts.bypassed)
+                        .bold()
+                        .cyan(),
+                );
+            }
+        }
+
+        if !vhost_results.is_empty() {
+            println!("\n");
+            println!("{}", "Virtual Hosts:".bold().green());
+            println!("{}", "==============".bold().green());
+            for result in vhost_results {
+                println!(
+                    "{} {} {} {} {} {}",
+                    "::".bold().green(),
+                    result.url.bold().white(),
+                    "::".bold().green(),
+                    result.host.bold().purple(),
+                    "::".bold().green(),
+                    result.status.bold().white()
+                );
+            }
+        }
+
+        let elapsed_time = now.elapsed();
+
+        let status = if scan_cancelled {
+            "Cancelled, the results are partial!".bold().yellow()
+        } else {
+            "Completed!".bold().green()
+        };
+        println!("\n\n");
+        println!(
+            "{}, {} {}{}",
+            status,
+            "scan took".bold().white(),
+            elapsed_time.as_secs().to_string().bold().white(),
+            "s".bold().white()
+        );
+        println!(
+            "{} {}",
+            "results are saved in".bold().white(),
+            outfile_path.bold().cyan(),
+        );
+
+        if triage && !triage_findings.is_empty() {
+            let triage_path = output::triage::triage_path(outfile_path);
+            output::triage::load_decisions(&triage_path, &mut triage_findings);
+            if let Err(e) = output::triage::run(&mut triage_findings, &triage_path) {
+                println!("{}", e);
+            }
+        }
+    }
+
+    if ci {
+        for finding in &ci_findings {
+            println!("{}", output::ci::annotation(finding));
+        }
+        if ci_findings
+            .iter()
+            .any(|finding| finding.severity >= fail_on)
+        {
+            exit(output::ci::FINDINGS_EXIT_CODE);
+        }
+    }
+    if scan_cancelled {
+        exit(cancel::CANCELLED_EXIT_CODE);
+    }
 
     Ok(())
 }
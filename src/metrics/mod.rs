@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+
+use crate::observer::ScanObserver;
+
+// the Metrics struct counts what the scan did, the counters are always kept
+// and only served with --metrics-listen
+#[derive(Debug)]
+pub struct Metrics {
+    requests: AtomicU64,
+    errors: AtomicU64,
+    statuses: Mutex<BTreeMap<u16, u64>>,
+    findings: Mutex<BTreeMap<&'static str, u64>>,
+    // the requests sent during the last second, sampled by the server
+    rps: AtomicU64,
+}
+
+static METRICS: Metrics = Metrics {
+    requests: AtomicU64::new(0),
+    errors: AtomicU64::new(0),
+    statuses: Mutex::new(BTreeMap::new()),
+    findings: Mutex::new(BTreeMap::new()),
+    rps: AtomicU64::new(0),
+};
+
+// counts a request and its status, or the error it failed with.
+pub fn record_response(status: Option<u16>) {
+    METRICS.requests.fetch_add(1, Ordering::Relaxed);
+    match status {
+        Some(status) => *METRICS.statuses.lock().unwrap().entry(status).or_default() += 1,
+        None => {
+            METRICS.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+// counts a finding of the kind, eg `internal_root` or `discovery`.
+pub fn record_finding(kind: &'static str) {
+    *METRICS.findings.lock().unwrap().entry(kind).or_default() += 1;
+}
+
+// the MetricsObserver struct feeds the counters from the observed requests
+// and findings, it is always registered
+#[derive(Debug)]
+pub struct MetricsObserver;
+
+impl ScanObserver for MetricsObserver {
+    fn on_response(&self, _url: &str, status: reqwest::StatusCode) {
+        record_response(Some(status.as_u16()));
+    }
+
+    fn on_finding(&self, kind: &'static str, _url: &str) {
+        record_finding(kind);
+    }
+
+    fn on_error(&self, _url: &str, _error: &str) {
+        record_response(None);
+    }
+}
+
+// the requests sent so far.
+pub fn requests() -> u64 {
+    METRICS.requests.load(Ordering::Relaxed)
+}
+
+// the requests that failed without a response so far.
+pub fn errors() -> u64 {
+    METRICS.errors.load(Ordering::Relaxed)
+}
+
+// renders the counters in the prometheus text format.
+pub fn render() -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "# HELP pathbuster_requests_total The requests sent to the targets."
+    );
+    let _ = writeln!(out, "# TYPE pathbuster_requests_total counter");
+    let _ = writeln!(
+        out,
+        "pathbuster_requests_total {}",
+        METRICS.requests.load(Ordering::Relaxed)
+    );
+    let _ = writeln!(
+        out,
+        "# HELP pathbuster_request_errors_total The requests that failed without a response."
+    );
+    let _ = writeln!(out, "# TYPE pathbuster_request_errors_total counter");
+    let _ = writeln!(
+        out,
+        "pathbuster_request_errors_total {}",
+        METRICS.errors.load(Ordering::Relaxed)
+    );
+    let _ = writeln!(
+        out,
+        "# HELP pathbuster_responses_total The responses by status code."
+    );
+    let _ = writeln!(out, "# TYPE pathbuster_responses_total counter");
+    for (status, count) in METRICS.statuses.lock().unwrap().iter() {
+        let _ = writeln!(
+            out,
+            "pathbuster_responses_total{{status=\"{}\"}} {}",
+            status, count
+        );
+    }
+    let _ = writeln!(
+        out,
+        "# HELP pathbuster_findings_total The findings by kind."
+    );
+    let _ = writeln!(out, "# TYPE pathbuster_findings_total counter");
+    for (kind, count) in METRICS.findings.lock().unwrap().iter() {
+        let _ = writeln!(
+            out,
+            "pathbuster_findings_total{{kind=\"{}\"}} {}",
+            kind, count
+        );
+    }
+    let _ = writeln!(
+        out,
+        "# HELP pathbuster_requests_per_second The requests sent during the last second."
+    );
+    let _ = writeln!(out, "# TYPE pathbuster_requests_per_second gauge");
+    let _ = writeln!(
+        out,
+        "pathbuster_requests_per_second {}",
+        METRICS.rps.load(Ordering::Relaxed)
+    );
+    out
+}
+
+async fn handle(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = if req.uri().path() == "/metrics" {
+        Response::builder()
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(Body::from(render()))
+    } else {
+        Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+    };
+    Ok(response.unwrap_or_default())
+}
+
+// serves the counters on /metrics of the address for the rest of the scan.
+pub fn serve(addr: SocketAddr) -> Result<(), String> {
+    let builder = match Server::try_bind(&addr) {
+        Ok(builder) => builder,
+        Err(e) => return Err(format!("failed to listen for metrics on {}: {}", addr, e)),
+    };
+    let make_service = make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(handle)) });
+    let server = builder.serve(make_service);
+    tokio::spawn(async move {
+        let _ = server.await;
+    });
+
+    // sample the request rate every second.
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        let mut previous = 0;
+        loop {
+            interval.tick().await;
+            let requests = METRICS.requests.load(Ordering::Relaxed);
+            METRICS.rps.store(requests - previous, Ordering::Relaxed);
+            previous = requests;
+        }
+    });
+    Ok(())
+}
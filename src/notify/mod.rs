@@ -0,0 +1,465 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::output::ci::Severity;
+use crate::output::silent::LineFormat;
+use crate::utils;
+
+// the FindingKind enum holds what was found, an internal doc root or what the
+// follow up stages found behind it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    InternalRoot,
+    // a route served from the cache under a static looking suffix
+    CacheDeception,
+    // a spring boot actuator exposed behind the internal doc root
+    SpringActuator,
+    // a 401/403 path served once a segment_suffix suffix was added to a segment
+    SegmentSuffix,
+    // the web root routed to the traversal of an X-Original-URL style header
+    HeaderRewrite,
+    // a 403 target served once an ip spoofing header claimed a local client
+    IpSpoof,
+    // a 403/405 target served once the method was overridden
+    MethodOverride,
+    // a `location /prefix` alias without a trailing slash stepped out of with `/prefix../`
+    NginxAlias,
+    // a tomcat manager app reached through `..;/`
+    TomcatManager,
+    // the 8.3 short name of a directory behind an iis internal doc root
+    IisShortname,
+    // a stage prefix of a managed api gateway routed differently by the backend
+    GatewayConfusion,
+    // a traversal in the value of a query parameter reaching an internal status
+    QueryTraversal,
+    // what a --plugin module flagged in a response
+    Plugin,
+    // what the on_response hook of the --script flagged in a response
+    Script,
+    // a host of the --vhost-wordlist routed differently than a bogus one
+    Vhost,
+}
+
+impl FindingKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FindingKind::InternalRoot => "internal_root",
+            FindingKind::CacheDeception => "cache_deception",
+            FindingKind::SpringActuator => "spring_actuator",
+            FindingKind::SegmentSuffix => "segment_suffix",
+            FindingKind::HeaderRewrite => "header_rewrite",
+            FindingKind::IpSpoof => "ip_spoof",
+            FindingKind::MethodOverride => "method_override",
+            FindingKind::NginxAlias => "nginx_alias",
+            FindingKind::QueryTraversal => "query_traversal",
+            FindingKind::TomcatManager => "tomcat_manager",
+            FindingKind::IisShortname => "iis_shortname",
+            FindingKind::GatewayConfusion => "gateway_confusion",
+            FindingKind::Plugin => "plugin",
+            FindingKind::Script => "script",
+            FindingKind::Vhost => "vhost",
+        }
+    }
+
+    // how the finding reads in the messages, eg "found an internal doc root".
+    pub fn title(&self) -> &'static str {
+        match self {
+            FindingKind::InternalRoot => "an internal doc root",
+            FindingKind::CacheDeception => "a route vulnerable to web cache deception",
+            FindingKind::SpringActuator => "an exposed spring actuator",
+            FindingKind::SegmentSuffix => "an acl bypassed with a segment suffix",
+            FindingKind::HeaderRewrite => "a traversal routed through a rewrite header",
+            FindingKind::IpSpoof => "an acl bypassed with an ip spoofing header",
+            FindingKind::MethodOverride => "an acl bypassed with a method override",
+            FindingKind::NginxAlias => "an nginx alias traversal",
+            FindingKind::QueryTraversal => "a traversal through a query parameter",
+            FindingKind::TomcatManager => "an exposed tomcat manager app",
+            FindingKind::IisShortname => "an iis short name",
+            FindingKind::GatewayConfusion => "a gateway path confusion",
+            FindingKind::Plugin => "a plugin finding",
+            FindingKind::Script => "a script finding",
+            FindingKind::Vhost => "a virtual host",
+        }
+    }
+
+    // a cache deception only leaks the route once a victim requests the
+    // cached url, so it ranks below a direct traversal. an actuator leaks the
+    // environment or the heap to anyone, so does an alias serving the files
+    // around its directory or a tomcat manager deploying wars. a query
+    // traversal is confirmed like an internal doc root. the acl bypasses and
+    // the header rewrites and the gateway confusions are only judged on the
+    // status, the content isn't compared. a short name only hints at a directory to bruteforce.
+    // what a plugin or a script flags isn't known to us, it ranks with the unconfirmed bypasses.
+    // a virtual host only shows the host is routed to another site.
+    pub fn severity(&self) -> Severity {
+        match self {
+            FindingKind::InternalRoot
+            | FindingKind::SpringActuator
+            | FindingKind::NginxAlias
+            | FindingKind::QueryTraversal
+            | FindingKind::TomcatManager => Severity::High,
+            FindingKind::CacheDeception
+            | FindingKind::SegmentSuffix
+            | FindingKind::HeaderRewrite
+            | FindingKind::IpSpoof
+            | FindingKind::MethodOverride
+            | FindingKind::GatewayConfusion
+            | FindingKind::Plugin
+            | FindingKind::Script
+            | FindingKind::Vhost => Severity::Medium,
+            FindingKind::IisShortname => Severity::Low,
+        }
+    }
+}
+
+// the Finding struct holds a confirmed finding sent to the notification sinks
+#[derive(Clone, Debug)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub url: String,
+    pub payload: String,
+    pub status: u16,
+    // the size, words and lines of the body, counted as ffuf does
+    pub size: utils::ResponseSize,
+    // the tags of the target the finding was found on
+    pub tags: Vec<String>,
+    // the requests the finding was judged on besides its own, eg the control
+    // request it was compared with. empty when its response alone decided
+    pub evidence: String,
+    // the payload family the finding was reached with, empty when none applies
+    pub family: String,
+    // the Content-Encoding its response was served with, empty without one
+    pub content_encoding: String,
+}
+
+// the WebhookFormat enum selects the body posted to the webhook
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebhookFormat {
+    // a json object with the fields of the finding
+    Generic,
+    // a slack incoming webhook message
+    Slack,
+    // a discord webhook message
+    Discord,
+}
+
+impl WebhookFormat {
+    // parses the --notify-format value, auto picks the format from the webhook url.
+    pub fn parse(value: &str, url: &str) -> Option<WebhookFormat> {
+        match value {
+            "generic" => Some(WebhookFormat::Generic),
+            "slack" => Some(WebhookFormat::Slack),
+            "discord" => Some(WebhookFormat::Discord),
+            "auto" if url.contains("hooks.slack.com") => Some(WebhookFormat::Slack),
+            "auto"
+                if url.contains("discord.com/api/webhooks")
+                    || url.contains("discordapp.com/api/webhooks") =>
+            {
+                Some(WebhookFormat::Discord)
+            }
+            "auto" => Some(WebhookFormat::Generic),
+            _ => None,
+        }
+    }
+
+    // the json body of the finding in the format.
+    pub fn body(&self, finding: &Finding) -> serde_json::Value {
+        let mut text = format!(
+            "pathbuster found {}: {} (payload: {}, status: {}, severity: {})",
+            finding.kind.title(),
+            finding.url,
+            finding.payload,
+            finding.status,
+            finding.kind.severity()
+        );
+        if !finding.evidence.is_empty() {
+            text.push_str(&format!(" ({})", finding.evidence));
+        }
+        if !finding.tags.is_empty() {
+            text.push_str(&format!(" [{}]", finding.tags.join(", ")));
+        }
+        match self {
+            WebhookFormat::Generic => json!({
+                "event": "finding",
+                "kind": finding.kind,
+                "severity": finding.kind.severity(),
+                "url": finding.url,
+                "payload": finding.payload,
+                "status": finding.status,
+                "size": finding.size.size,
+                "words": finding.size.words,
+                "lines": finding.size.lines,
+                "tags": finding.tags,
+                "evidence": finding.evidence,
+                "family": finding.family,
+                "content_encoding": finding.content_encoding,
+            }),
+            WebhookFormat::Slack => json!({ "text": text }),
+            WebhookFormat::Discord => json!({ "content": text }),
+        }
+    }
+}
+
+// the SyslogTransport enum selects how the messages reach the syslog server
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyslogTransport {
+    Udp,
+    // each message is framed with its length as in rfc 6587
+    Tcp,
+}
+
+// the facility and severity of the messages, user-level and warning
+const SYSLOG_PRIORITY: u8 = 8 + 4;
+
+// the SD-ID of the structured data when --syslog-sd-id isn't given, 32473 is
+// the example enterprise number of rfc 5612, a SIEM expecting the private
+// enterprise number of its organisation needs --syslog-sd-id.
+pub const DEFAULT_SYSLOG_SD_ID: &str = "finding@32473";
+
+// checks the --syslog-sd-id value, a name and a private enterprise number
+// (eg finding@12345) without the characters rfc 5424 forbids in a SD-ID.
+pub fn valid_sd_id(value: &str) -> bool {
+    let (name, number) = match value.split_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    value.len() <= 32
+        && !name.is_empty()
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"' | '@'))
+}
+
+// parses the --syslog value, udp://host:port or tcp://host:port, the port
+// defaults to 514.
+pub fn parse_syslog(value: &str) -> Option<(SyslogTransport, String)> {
+    let (transport, address) = match value.split_once("://") {
+        Some(("udp", address)) => (SyslogTransport::Udp, address),
+        Some(("tcp", address)) => (SyslogTransport::Tcp, address),
+        Some(_) => return None,
+        None => (SyslogTransport::Udp, value),
+    };
+    if address.is_empty() {
+        return None;
+    }
+    let has_port = match address.rsplit_once(':') {
+        Some((_, port)) => !address.ends_with(']') && port.parse::<u16>().is_ok(),
+        None => false,
+    };
+    if has_port {
+        Some((transport, address.to_string()))
+    } else {
+        Some((transport, format!("{}:514", address)))
+    }
+}
+
+// escapes the characters rfc 5424 reserves in a structured data value.
+fn escape_param(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]")
+}
+
+// formats the finding as a rfc 5424 message, the fields of the finding are
+// in the structured data so a siem doesn't have to parse the text.
+pub fn syslog_message(sd_id: &str, finding: &Finding) -> String {
+    let timestamp = format!(
+        "{}Z",
+        utils::format_time(utils::unix_time()).replacen(' ', "T", 1)
+    );
+    let evidence = match finding.evidence.as_str() {
+        "" => String::new(),
+        evidence => format!(" evidence=\"{}\"", escape_param(evidence)),
+    };
+    format!(
+        "<{}>1 {} - pathbuster {} finding [{} kind=\"{}\" severity=\"{}\" url=\"{}\" payload=\"{}\" status=\"{}\"{}] found {}: {}",
+        SYSLOG_PRIORITY,
+        timestamp,
+        std::process::id(),
+        sd_id,
+        finding.kind.name(),
+        finding.kind.severity(),
+        escape_param(&finding.url),
+        escape_param(&finding.payload),
+        finding.status,
+        evidence,
+        finding.kind.title(),
+        finding.url,
+    )
+}
+
+// the Notifier struct sends the confirmed findings to the configured sinks as
+// soon as the workers find them, shared by all the workers. the workers only
+// queue the findings, a task delivers them so a slow sink never holds a worker.
+#[derive(Clone, Debug)]
+pub struct Notifier {
+    webhook: Option<(String, WebhookFormat)>,
+    syslog: Option<(SyslogTransport, String)>,
+    syslog_sd_id: String,
+    stdout: Option<LineFormat>,
+    client: reqwest::Client,
+    queue: Arc<Mutex<Option<mpsc::UnboundedSender<Finding>>>>,
+}
+
+impl Default for Notifier {
+    fn default() -> Notifier {
+        Notifier::new()
+    }
+}
+
+impl Notifier {
+    pub fn new() -> Notifier {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        Notifier {
+            webhook: None,
+            syslog: None,
+            syslog_sd_id: DEFAULT_SYSLOG_SD_ID.to_string(),
+            stdout: None,
+            client,
+            queue: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    // posts the findings to the webhook url.
+    pub fn with_webhook(mut self, url: &str, format: WebhookFormat) -> Notifier {
+        self.webhook = Some((url.to_string(), format));
+        self
+    }
+
+    // sends the findings to the syslog server.
+    pub fn with_syslog(mut self, transport: SyslogTransport, address: &str) -> Notifier {
+        self.syslog = Some((transport, address.to_string()));
+        self
+    }
+
+    // the SD-ID of the structured data of the syslog messages.
+    pub fn with_syslog_sd_id(mut self, sd_id: &str) -> Notifier {
+        self.syslog_sd_id = sd_id.to_string();
+        self
+    }
+
+    // prints the findings on stdout for --silent.
+    pub fn with_stdout(mut self, format: LineFormat) -> Notifier {
+        self.stdout = Some(format);
+        self
+    }
+
+    // starts the task delivering the queued findings, it ends once the
+    // notifier is closed and the findings queued until then are delivered.
+    pub fn start(&self) -> JoinHandle<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Finding>();
+        *self.queue.lock().unwrap() = Some(tx);
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            while let Some(finding) = rx.recv().await {
+                for e in notifier.deliver(&finding).await {
+                    tracing::warn!(url = %finding.url, error = %e, "failed to notify the finding");
+                }
+            }
+        })
+    }
+
+    // stops queueing the findings, the delivery task drains the queue and ends.
+    pub fn close(&self) {
+        self.queue.lock().unwrap().take();
+    }
+
+    // queues the finding for the delivery task, the findings of a notifier
+    // that wasn't started are dropped.
+    pub fn notify(&self, finding: &Finding) {
+        if let Some(tx) = self.queue.lock().unwrap().as_ref() {
+            let _ = tx.send(finding.clone());
+        }
+    }
+
+    // sends the finding to every sink, a failing sink doesn't keep the finding
+    // from the others and the errors of all of them are returned.
+    async fn deliver(&self, finding: &Finding) -> Vec<String> {
+        let mut errors = vec![];
+        if let Some(format) = &self.stdout {
+            let mut details = json!({
+                "severity": finding.kind.severity(),
+                "payload": finding.payload,
+                "status": finding.status,
+                "size": finding.size.size,
+                "words": finding.size.words,
+                "lines": finding.size.lines,
+            });
+            if !finding.evidence.is_empty() {
+                details["evidence"] = json!(finding.evidence);
+            }
+            if !finding.family.is_empty() {
+                details["family"] = json!(finding.family);
+            }
+            if !finding.content_encoding.is_empty() {
+                details["content_encoding"] = json!(finding.content_encoding);
+            }
+            println!(
+                "{}",
+                format.line(finding.kind.name(), &finding.url, details, &finding.tags)
+            );
+        }
+        if let Some((url, format)) = &self.webhook {
+            let req = self
+                .client
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(format.body(finding).to_string());
+            match req.send().await {
+                Ok(resp) if !resp.status().is_success() => {
+                    errors.push(format!("the webhook answered {}", resp.status()))
+                }
+                Ok(_) => {}
+                Err(e) => errors.push(format!("failed to post to the webhook: {}", e)),
+            }
+        }
+        if let Some((transport, address)) = &self.syslog {
+            let message = syslog_message(&self.syslog_sd_id, finding);
+            if let Err(e) = send_syslog(*transport, address, &message).await {
+                errors.push(format!("failed to send to syslog {}: {}", address, e));
+            }
+        }
+        errors
+    }
+}
+
+// sends the message over a new socket, the findings are rare enough that
+// keeping a connection open isn't worth it.
+async fn send_syslog(
+    transport: SyslogTransport,
+    address: &str,
+    message: &str,
+) -> std::io::Result<()> {
+    match transport {
+        SyslogTransport::Udp => {
+            let bind = if address.starts_with('[') {
+                "[::]:0"
+            } else {
+                "0.0.0.0:0"
+            };
+            let socket = UdpSocket::bind(bind).await?;
+            socket.send_to(message.as_bytes(), address).await?;
+        }
+        SyslogTransport::Tcp => {
+            let mut stream = TcpStream::connect(address).await?;
+            let frame = format!("{} {}", message.len(), message);
+            stream.write_all(frame.as_bytes()).await?;
+            stream.shutdown().await?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use std::sync::{Arc, RwLock};
+
+use reqwest::{Method, StatusCode};
+
+// the ScanObserver trait is called for every request, response, finding and
+// failed request of the scan, to plug custom logging, persistence or alerting
+// in without touching the workers. the methods do nothing by default.
+pub trait ScanObserver: Send + Sync {
+    fn on_request(&self, _method: &Method, _url: &str) {}
+    fn on_response(&self, _url: &str, _status: StatusCode) {}
+    // kind is `internal_root` or `discovery`
+    fn on_finding(&self, _kind: &'static str, _url: &str) {}
+    fn on_error(&self, _url: &str, _error: &str) {}
+}
+
+static OBSERVERS: RwLock<Vec<Arc<dyn ScanObserver>>> = RwLock::new(Vec::new());
+
+// adds the observer, it is called from then on.
+pub fn register(observer: Arc<dyn ScanObserver>) {
+    OBSERVERS.write().unwrap().push(observer);
+}
+
+// removes the observer, it is not called anymore.
+pub fn unregister(observer: &Arc<dyn ScanObserver>) {
+    OBSERVERS
+        .write()
+        .unwrap()
+        .retain(|registered| !Arc::ptr_eq(registered, observer));
+}
+
+pub fn request(method: &Method, url: &str) {
+    for observer in OBSERVERS.read().unwrap().iter() {
+        observer.on_request(method, url);
+    }
+}
+
+pub fn response(url: &str, status: StatusCode) {
+    for observer in OBSERVERS.read().unwrap().iter() {
+        observer.on_response(url, status);
+    }
+}
+
+pub fn finding(kind: &'static str, url: &str) {
+    for observer in OBSERVERS.read().unwrap().iter() {
+        observer.on_finding(kind, url);
+    }
+}
+
+pub fn error(url: &str, error: &str) {
+    for observer in OBSERVERS.read().unwrap().iter() {
+        observer.on_error(url, error);
+    }
+}
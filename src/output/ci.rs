@@ -0,0 +1,72 @@
+use std::fmt;
+
+use serde::Serialize;
+
+// the Severity enum ranks the findings, --fail-on fails the pipeline on the
+// findings at or above the severity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    // a path found by directory bruteforcing, an iis short name
+    Low,
+    // a virtual host served by an internal doc root, a route vulnerable to
+    // web cache deception, an acl bypassed with a segment suffix
+    Medium,
+    // a traversal reaching an internal doc root, an exposed spring actuator
+    High,
+}
+
+impl Severity {
+    pub fn parse(value: &str) -> Option<Severity> {
+        match value {
+            "low" => Some(Severity::Low),
+            "medium" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            _ => None,
+        }
+    }
+
+    // the level of the github actions annotation, the other ci systems show
+    // the workflow commands as plain lines.
+    fn annotation_level(&self) -> &'static str {
+        match self {
+            Severity::Low => "notice",
+            Severity::Medium => "warning",
+            Severity::High => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// the CiFinding struct holds a finding reported as an annotation
+#[derive(Clone, Debug)]
+pub struct CiFinding {
+    pub severity: Severity,
+    pub title: String,
+    pub url: String,
+}
+
+// the exit code of a --ci scan with findings at or above --fail-on, the
+// errors keep exiting with 1
+pub const FINDINGS_EXIT_CODE: i32 = 2;
+
+// formats the finding as a github actions workflow command.
+pub fn annotation(finding: &CiFinding) -> String {
+    format!(
+        "::{} title=pathbuster {} ({})::{}",
+        finding.severity.annotation_level(),
+        finding.title,
+        finding.severity,
+        finding.url.replace('%', "%25")
+    )
+}
@@ -0,0 +1,356 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use rusqlite::{params, Connection};
+
+use crate::fingerprint::TargetFingerprint;
+use crate::notify::Finding;
+use crate::transform::FamilyOutcome;
+use crate::vhost::VhostResult;
+
+// the tables of the results database, every scan appends its own rows so the
+// findings of many scans can be queried together
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER NOT NULL,
+    cancelled INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS targets (
+    id INTEGER PRIMARY KEY,
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    url TEXT NOT NULL,
+    host TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS fingerprints (
+    target_id INTEGER PRIMARY KEY REFERENCES targets(id),
+    tech TEXT,
+    product TEXT,
+    cdn TEXT,
+    certificate_subject TEXT,
+    certificate_issuer TEXT
+);
+CREATE TABLE IF NOT EXISTS certificate_names (
+    target_id INTEGER NOT NULL REFERENCES targets(id),
+    name TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS normalizations (
+    target_id INTEGER NOT NULL REFERENCES targets(id),
+    probe TEXT NOT NULL,
+    result TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS findings (
+    id INTEGER PRIMARY KEY,
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    host TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    url TEXT NOT NULL,
+    detail TEXT
+);
+CREATE TABLE IF NOT EXISTS families (
+    id INTEGER PRIMARY KEY,
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    family TEXT NOT NULL,
+    waf TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    blocked INTEGER NOT NULL,
+    bypassed INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS discoveries (
+    id INTEGER PRIMARY KEY,
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    host TEXT NOT NULL,
+    url TEXT NOT NULL,
+    content_length TEXT
+);
+CREATE INDEX IF NOT EXISTS targets_host ON targets(host);
+CREATE INDEX IF NOT EXISTS findings_host ON findings(host);
+CREATE INDEX IF NOT EXISTS discoveries_host ON discoveries(host);
+";
+
+// the kinds of the findings rows
+pub const INTERNAL_ROOT: &str = "internal_root";
+pub const VHOST: &str = "vhost";
+
+// the ScanRecord struct holds what a scan found, written to the database at the end
+pub struct ScanRecord<'a> {
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub urls: &'a [String],
+    pub fingerprints: &'a HashMap<String, TargetFingerprint>,
+    pub internal_roots: &'a [String],
+    // what the follow up stages found behind the internal doc roots
+    pub follow_ups: &'a [Finding],
+    pub discoveries: &'a HashMap<String, String>,
+    pub vhosts: &'a [VhostResult],
+    // the outcomes of the payload families per waf
+    pub families: &'a [FamilyOutcome],
+    // a cancelled scan only holds partial results, the history leaves it out
+    pub cancelled: bool,
+}
+
+// the host of the url, the url itself when it can't be parsed.
+fn host_of(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => parsed.host_str().unwrap_or(url).to_string(),
+        Err(_) => url.to_string(),
+    }
+}
+
+// opens the database and creates the tables it doesn't have yet.
+pub fn open(path: &str) -> Result<Connection, String> {
+    let conn = match Connection::open(path) {
+        Ok(conn) => conn,
+        Err(e) => return Err(format!("failed to open results database: {}", e)),
+    };
+    if let Err(e) = conn.execute_batch(SCHEMA) {
+        return Err(format!("failed to create results database: {}", e));
+    }
+    // the databases written before scans could be cancelled lack the column.
+    if conn.prepare("SELECT cancelled FROM scans LIMIT 0").is_err() {
+        if let Err(e) =
+            conn.execute_batch("ALTER TABLE scans ADD COLUMN cancelled INTEGER NOT NULL DEFAULT 0")
+        {
+            return Err(format!("failed to upgrade results database: {}", e));
+        }
+    }
+    Ok(conn)
+}
+
+// writes the scan in a single transaction and returns its id.
+pub fn write_scan(path: &str, record: &ScanRecord) -> Result<i64, String> {
+    let mut conn = open(path)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let scan_id = insert_scan(&tx, record).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(scan_id)
+}
+
+fn insert_scan(conn: &Connection, record: &ScanRecord) -> rusqlite::Result<i64> {
+    conn.execute(
+        "INSERT INTO scans (started_at, finished_at, cancelled) VALUES (?1, ?2, ?3)",
+        params![
+            record.started_at as i64,
+            record.finished_at as i64,
+            record.cancelled
+        ],
+    )?;
+    let scan_id = conn.last_insert_rowid();
+
+    for url in record.urls {
+        conn.execute(
+            "INSERT INTO targets (scan_id, url, host) VALUES (?1, ?2, ?3)",
+            params![scan_id, url, host_of(url)],
+        )?;
+        let target_id = conn.last_insert_rowid();
+        let fingerprint = match record.fingerprints.get(url) {
+            Some(fingerprint) => fingerprint,
+            None => continue,
+        };
+        let certificate = fingerprint.certificate.as_ref();
+        conn.execute(
+            "INSERT INTO fingerprints (target_id, tech, product, cdn, certificate_subject, certificate_issuer)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                target_id,
+                fingerprint.tech.map(|tech| format!("{:?}", tech)),
+                fingerprint.product,
+                fingerprint.cdn.map(|cdn| cdn.to_string()),
+                certificate.map(|certificate| certificate.subject.clone()),
+                certificate.map(|certificate| certificate.issuer.clone()),
+            ],
+        )?;
+        for name in certificate
+            .map(|certificate| certificate.sans.as_slice())
+            .unwrap_or_default()
+        {
+            conn.execute(
+                "INSERT INTO certificate_names (target_id, name) VALUES (?1, ?2)",
+                params![target_id, name],
+            )?;
+        }
+        for (probe, normalization) in &fingerprint.normalization {
+            conn.execute(
+                "INSERT INTO normalizations (target_id, probe, result) VALUES (?1, ?2, ?3)",
+                params![
+                    target_id,
+                    format!("{:?}", probe),
+                    format!("{:?}", normalization)
+                ],
+            )?;
+        }
+    }
+
+    for url in record.internal_roots {
+        conn.execute(
+            "INSERT INTO findings (scan_id, host, kind, url) VALUES (?1, ?2, ?3, ?4)",
+            params![scan_id, host_of(url), INTERNAL_ROOT, url],
+        )?;
+    }
+    for finding in record.follow_ups {
+        conn.execute(
+            "INSERT INTO findings (scan_id, host, kind, url, detail) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                scan_id,
+                host_of(&finding.url),
+                finding.kind.name(),
+                finding.url,
+                [
+                    finding.payload.as_str(),
+                    &finding.status.to_string(),
+                    &finding.family,
+                    &finding.evidence,
+                    &finding.content_encoding,
+                ]
+                .iter()
+                .filter(|detail| !detail.is_empty())
+                .map(|detail| detail.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+            ],
+        )?;
+    }
+    for vhost in record.vhosts {
+        conn.execute(
+            "INSERT INTO findings (scan_id, host, kind, url, detail) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                scan_id,
+                host_of(&vhost.url),
+                VHOST,
+                vhost.url,
+                format!("{} {}", vhost.host, vhost.status)
+            ],
+        )?;
+    }
+    for (url, content_length) in record.discoveries {
+        conn.execute(
+            "INSERT INTO discoveries (scan_id, host, url, content_length) VALUES (?1, ?2, ?3, ?4)",
+            params![scan_id, host_of(url), url, content_length],
+        )?;
+    }
+    for outcome in record.families {
+        conn.execute(
+            "INSERT INTO families (scan_id, family, waf, attempts, blocked, bypassed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                scan_id,
+                outcome.family,
+                outcome.waf,
+                outcome.counts.attempts,
+                outcome.counts.blocked,
+                outcome.counts.bypassed
+            ],
+        )?;
+    }
+    Ok(scan_id)
+}
+
+// the FindingHistory struct holds when a finding of a host was seen, the
+// changes are the scans of the host where it appeared or went away
+#[derive(Clone, Debug)]
+pub struct FindingHistory {
+    pub kind: String,
+    pub url: String,
+    pub first_seen: u64,
+    pub last_seen: u64,
+    pub changes: Vec<(u64, Status)>,
+}
+
+// the Status enum holds the state a finding changed to in a scan
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Found,
+    Resolved,
+    // found again after it was resolved
+    Regressed,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Status::Found => "found",
+            Status::Resolved => "resolved",
+            Status::Regressed => "regressed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// returns the history of the findings and discoveries of the host over the
+// scans that targeted it, sorted by when they were first seen.
+pub fn history(path: &str, host: &str) -> Result<Vec<FindingHistory>, String> {
+    if !std::path::Path::new(path).exists() {
+        return Err(format!("results database not found: {}", path));
+    }
+    let conn = open(path)?;
+    query_history(&conn, host).map_err(|e| format!("failed to read results database: {}", e))
+}
+
+fn query_history(conn: &Connection, host: &str) -> rusqlite::Result<Vec<FindingHistory>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT scans.id, scans.started_at FROM scans
+         JOIN targets ON targets.scan_id = scans.id
+         WHERE targets.host = ?1 AND scans.cancelled = 0
+         ORDER BY scans.started_at, scans.id",
+    )?;
+    let scans = stmt
+        .query_map(params![host], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)? as u64))
+        })?
+        .collect::<rusqlite::Result<Vec<(i64, u64)>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT scan_id, kind, url FROM findings WHERE host = ?1
+         UNION SELECT scan_id, 'discovery', url FROM discoveries WHERE host = ?1",
+    )?;
+    let mut seen: BTreeMap<(String, String), HashSet<i64>> = BTreeMap::new();
+    let rows = stmt.query_map(params![host], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+    for row in rows {
+        let (scan_id, kind, url) = row?;
+        seen.entry((kind, url)).or_default().insert(scan_id);
+    }
+
+    let mut histories = vec![];
+    for ((kind, url), scan_ids) in seen {
+        let mut history = FindingHistory {
+            kind,
+            url,
+            first_seen: 0,
+            last_seen: 0,
+            changes: vec![],
+        };
+        let mut present = false;
+        for (scan_id, started_at) in &scans {
+            let found = scan_ids.contains(scan_id);
+            let status = match (present, found) {
+                (false, true) if history.changes.is_empty() => Some(Status::Found),
+                (false, true) => Some(Status::Regressed),
+                (true, false) => Some(Status::Resolved),
+                _ => None,
+            };
+            if let Some(status) = status {
+                history.changes.push((*started_at, status));
+            }
+            if found {
+                if history.first_seen == 0 {
+                    history.first_seen = *started_at;
+                }
+                history.last_seen = *started_at;
+            }
+            present = found;
+        }
+        // only seen by cancelled scans.
+        if history.changes.is_empty() {
+            continue;
+        }
+        histories.push(history);
+    }
+    histories.sort_by_key(|history| history.first_seen);
+    Ok(histories)
+}
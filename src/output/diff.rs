@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+use std::fmt;
+
+// the Change enum classifies a finding against the findings of a previous scan
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    // found by this scan only
+    New,
+    // found by both scans
+    Known,
+    // found by the previous scan only
+    Resolved,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Change::New => "new",
+            Change::Known => "known",
+            Change::Resolved => "resolved",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// loads the findings of a previous scan: the --out and discovered-routes.txt
+// files with one url per line, or a json array of urls or of objects with a `url`.
+pub fn load_baseline(path: &str) -> Result<HashSet<String>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("failed to open previous scan: {:?}", e)),
+    };
+    if contents.trim_start().starts_with('[') {
+        let values: Vec<serde_json::Value> = match serde_json::from_str(&contents) {
+            Ok(values) => values,
+            Err(e) => return Err(format!("invalid previous scan: {}", e)),
+        };
+        let urls = values
+            .iter()
+            .filter_map(|value| match value {
+                serde_json::Value::String(url) => Some(url.clone()),
+                _ => value.get("url")?.as_str().map(|url| url.to_string()),
+            })
+            .collect();
+        return Ok(urls);
+    }
+    Ok(contents
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+// classifies the findings of the scan against the baseline, the resolved ones
+// come last and sorted.
+pub fn diff(baseline: &HashSet<String>, findings: &[String]) -> Vec<(Change, String)> {
+    let mut changes = vec![];
+    let mut seen = HashSet::new();
+    for finding in findings {
+        if !seen.insert(finding.as_str()) {
+            continue;
+        }
+        let change = if baseline.contains(finding) {
+            Change::Known
+        } else {
+            Change::New
+        };
+        changes.push((change, finding.clone()));
+    }
+    let mut resolved: Vec<&String> = baseline
+        .iter()
+        .filter(|finding| !seen.contains(finding.as_str()))
+        .collect();
+    resolved.sort();
+    for finding in resolved {
+        changes.push((Change::Resolved, finding.clone()));
+    }
+    changes
+}
@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+
+use serde_json::{json, Value};
+
+// reads the findings of an output file: the --silent --format jsonl lines, a
+// json array like the triage file, or the --out file with one url per line
+// which become `{"url": ...}` objects.
+fn read_findings(path: &str) -> Result<Vec<Value>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("failed to open {}: {:?}", path, e)),
+    };
+    if contents.trim_start().starts_with('[') {
+        return match serde_json::from_str(&contents) {
+            Ok(findings) => Ok(findings),
+            Err(e) => Err(format!("invalid json in {}: {}", path, e)),
+        };
+    }
+    let mut findings = vec![];
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('{') {
+            match serde_json::from_str(line) {
+                Ok(finding) => findings.push(finding),
+                Err(e) => return Err(format!("invalid json line in {}: {}", path, e)),
+            }
+        } else {
+            findings.push(json!({ "url": line }));
+        }
+    }
+    Ok(findings)
+}
+
+// merges the outputs of the shards of a scan in the order of the files, the
+// findings more than one shard reported are only kept once.
+pub fn merge(paths: &[&str]) -> Result<Vec<Value>, String> {
+    let mut seen = HashSet::new();
+    let mut merged = vec![];
+    for path in paths {
+        for finding in read_findings(path)? {
+            if seen.insert(finding.to_string()) {
+                merged.push(finding);
+            }
+        }
+    }
+    Ok(merged)
+}
@@ -0,0 +1,6 @@
+pub mod ci;
+pub mod db;
+pub mod diff;
+pub mod merge;
+pub mod silent;
+pub mod triage;
@@ -0,0 +1,47 @@
+use serde_json::json;
+
+// the LineFormat enum selects how --silent prints the findings on stdout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineFormat {
+    // only the url of the finding
+    Url,
+    // a json object per line
+    Jsonl,
+}
+
+impl LineFormat {
+    pub fn parse(value: &str) -> Option<LineFormat> {
+        match value {
+            "url" => Some(LineFormat::Url),
+            "jsonl" => Some(LineFormat::Jsonl),
+            _ => None,
+        }
+    }
+
+    // formats the finding of the kind, the details and the tags of its target
+    // are only part of the json lines, eg
+    // `{"kind":"discovery","url":"...","content_length":"1337","tags":["acme"]}`.
+    pub fn line(
+        &self,
+        kind: &str,
+        url: &str,
+        details: serde_json::Value,
+        tags: &[String],
+    ) -> String {
+        match self {
+            LineFormat::Url => url.to_string(),
+            LineFormat::Jsonl => {
+                let mut line = json!({ "kind": kind, "url": url });
+                if let (Some(line), serde_json::Value::Object(details)) =
+                    (line.as_object_mut(), details)
+                {
+                    line.extend(details);
+                    if !tags.is_empty() {
+                        line.insert("tags".to_string(), json!(tags));
+                    }
+                }
+                line.to_string()
+            }
+        }
+    }
+}
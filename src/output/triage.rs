@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+// the Decision enum holds what the finding was marked as during the triage
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    #[default]
+    Pending,
+    Interesting,
+    FalsePositive,
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Decision::Pending => "pending",
+            Decision::Interesting => "interesting",
+            Decision::FalsePositive => "false_positive",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// the TriageFinding struct is a finding of the scan with its decision, the
+// triage file holds a json array of them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TriageFinding {
+    pub kind: String,
+    pub url: String,
+    // the payload family of the internal doc roots, empty for the other kinds
+    pub family: String,
+    pub status: String,
+    // the content length of the discoveries, the Host header of the virtual hosts,
+    // the probed suffix or path of the follow up findings
+    #[serde(default)]
+    pub detail: String,
+    // the title, h1 and generator of the page of the internal doc roots
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub page: String,
+    // the requests a follow up finding was judged on, see notify::Finding
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub evidence: String,
+    #[serde(default)]
+    pub decision: Decision,
+    // the tags of the target the finding was found on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+// the Filter struct narrows the listed findings, an empty field matches everything
+#[derive(Clone, Debug, Default)]
+struct Filter {
+    kind: String,
+    family: String,
+    status: String,
+    tag: String,
+}
+
+impl Filter {
+    fn matches(&self, finding: &TriageFinding) -> bool {
+        (self.kind.is_empty() || finding.kind == self.kind)
+            && (self.family.is_empty() || finding.family == self.family)
+            && (self.status.is_empty() || finding.status == self.status)
+            && (self.tag.is_empty() || finding.tags.contains(&self.tag))
+    }
+}
+
+// the file the decisions are saved to, next to the --out report.
+pub fn triage_path(outfile_path: &str) -> String {
+    format!("{}.triage.json", outfile_path)
+}
+
+// applies the decisions of a previous triage of the same report to the findings.
+pub fn load_decisions(path: &str, findings: &mut [TriageFinding]) {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return,
+    };
+    let previous: Vec<TriageFinding> = match serde_json::from_str(&contents) {
+        Ok(previous) => previous,
+        Err(_) => return,
+    };
+    let decisions: HashMap<(String, String, String), Decision> = previous
+        .into_iter()
+        .map(|finding| {
+            (
+                (finding.kind, finding.url, finding.detail),
+                finding.decision,
+            )
+        })
+        .collect();
+    for finding in findings.iter_mut() {
+        let key = (
+            finding.kind.clone(),
+            finding.url.clone(),
+            finding.detail.clone(),
+        );
+        if let Some(decision) = decisions.get(&key) {
+            finding.decision = *decision;
+        }
+    }
+}
+
+pub fn save_decisions(path: &str, findings: &[TriageFinding]) -> Result<(), String> {
+    let contents = match serde_json::to_string_pretty(findings) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("failed to encode the triage: {}", e)),
+    };
+    match std::fs::write(path, contents) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("failed to save the triage: {:?}", e)),
+    }
+}
+
+// writes the urls of the findings that weren't marked as false positives, one per line.
+fn export(path: &str, findings: &[TriageFinding]) -> Result<usize, String> {
+    let mut kept: Vec<&str> = vec![];
+    for finding in findings {
+        if finding.decision != Decision::FalsePositive && !kept.contains(&finding.url.as_str()) {
+            kept.push(finding.url.as_str());
+        }
+    }
+    let mut contents = kept.join("\n");
+    contents.push('\n');
+    match std::fs::write(path, contents) {
+        Ok(_) => Ok(kept.len()),
+        Err(e) => Err(format!("failed to export the findings: {:?}", e)),
+    }
+}
+
+const HELP: &str = "commands:
+  l                          list the findings matching the filter
+  i <n>...                   mark the findings as interesting
+  f <n>...                   mark the findings as false positives
+  u <n>...                   reset the findings to pending
+  filter kind|family|status|tag=<value>  only list the matching findings, `filter` alone clears it
+  e <path>                   export the urls of the findings that aren't false positives
+  q                          save the decisions and quit";
+
+fn list(findings: &[TriageFinding], filter: &Filter) {
+    for (index, finding) in findings.iter().enumerate() {
+        if !filter.matches(finding) {
+            continue;
+        }
+        let decision = match finding.decision {
+            Decision::Pending => finding.decision.to_string().white(),
+            Decision::Interesting => finding.decision.to_string().bold().cyan(),
+            Decision::FalsePositive => finding.decision.to_string().bold().red(),
+        };
+        let tags = finding.tags.join(",");
+        let details = [
+            &finding.family,
+            &finding.status,
+            &finding.detail,
+            &finding.page,
+            &finding.evidence,
+            &tags,
+        ]
+        .iter()
+        .filter(|detail| !detail.is_empty())
+        .map(|detail| detail.as_str())
+        .collect::<Vec<&str>>()
+        .join(" ");
+        println!(
+            "{} {} {} {} {} {} {}",
+            format!("{:>3}", index + 1).bold().white(),
+            "::".bold().green(),
+            finding.kind.bold().purple(),
+            finding.url.bold().white(),
+            "::".bold().green(),
+            details.white(),
+            decision,
+        );
+    }
+}
+
+// sets the decision of the numbered findings, eg `f 1 3 4`.
+fn mark(findings: &mut [TriageFinding], numbers: &[&str], decision: Decision) {
+    for number in numbers {
+        match number.parse::<usize>() {
+            Ok(number) if number >= 1 && number <= findings.len() => {
+                findings[number - 1].decision = decision;
+            }
+            _ => println!("no finding numbered {}", number),
+        }
+    }
+}
+
+// reads the triage commands on stdin until `q` or the end of the input, the
+// decisions are saved to the triage file on the way out.
+pub fn run(findings: &mut [TriageFinding], path: &str) -> Result<(), String> {
+    let mut filter = Filter::default();
+    println!("\n");
+    println!("{}", "Triage:".bold().green());
+    println!("{}", "=======".bold().green());
+    list(findings, &filter);
+    println!("{}", HELP.white());
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("{} ", "triage >".bold().cyan());
+        let _ = io::stdout().flush();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => continue,
+        };
+        let args: Vec<&str> = words.collect();
+        match command {
+            "l" | "list" => list(findings, &filter),
+            "i" | "interesting" => mark(findings, &args, Decision::Interesting),
+            "f" | "false-positive" => mark(findings, &args, Decision::FalsePositive),
+            "u" | "undo" => mark(findings, &args, Decision::Pending),
+            "filter" => {
+                if args.is_empty() {
+                    filter = Filter::default();
+                }
+                for arg in args {
+                    match arg.split_once('=') {
+                        Some(("kind", value)) => filter.kind = value.to_string(),
+                        Some(("family", value)) => filter.family = value.to_string(),
+                        Some(("status", value)) => filter.status = value.to_string(),
+                        Some(("tag", value)) => filter.tag = value.to_string(),
+                        _ => println!(
+                            "invalid filter {}, use kind=, family=, status= or tag=",
+                            arg
+                        ),
+                    }
+                }
+                list(findings, &filter);
+            }
+            "e" | "export" => match args.first() {
+                Some(export_path) => match export(export_path, findings) {
+                    Ok(kept) => println!("{} findings exported to {}", kept, export_path),
+                    Err(e) => println!("{}", e),
+                },
+                None => println!("missing the path to export the findings to"),
+            },
+            "q" | "quit" => break,
+            _ => println!("{}", HELP.white()),
+        }
+    }
+    save_decisions(path, findings)?;
+    println!(
+        "{} {}",
+        "triage decisions are saved in".bold().white(),
+        path.bold().cyan(),
+    );
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use wasmi::{Config, Engine, Linker, Memory, Module, Store, TypedFunc};
+
+// the instructions a plugin can run while inspecting a response, a plugin that
+// loops forever runs out of fuel instead of stalling the worker
+const FUEL_PER_INSPECTION: u64 = 50_000_000;
+
+// the Plugin struct holds a compiled --plugin module, the workers instantiate
+// it on their first job. the module exports its `memory`, `alloc(len) -> ptr`
+// and `inspect(ptr, len) -> i64` which reads the json of the response and
+// returns the offset of its json output in the high 32 bits and its length in
+// the low 32 bits, 0 for no output.
+#[derive(Clone, Debug)]
+pub struct Plugin {
+    pub name: String,
+    engine: Engine,
+    module: Arc<Module>,
+}
+
+// the Inspection struct is the json a plugin inspects
+#[derive(Debug, Serialize)]
+pub struct Inspection<'a> {
+    pub url: &'a str,
+    pub status: u16,
+    pub headers: BTreeMap<String, String>,
+    pub body: &'a str,
+}
+
+// the PluginOutput struct is the json a plugin returns
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PluginOutput {
+    pub findings: Vec<PluginFinding>,
+    // the urls to fetch and inspect as well
+    pub jobs: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PluginFinding {
+    pub title: String,
+    // the url of the inspected response when empty
+    #[serde(default)]
+    pub url: String,
+}
+
+// the PluginInstance struct is the instance of a plugin a worker calls
+#[derive(Debug)]
+pub struct PluginInstance {
+    pub name: String,
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    inspect: TypedFunc<(i32, i32), i64>,
+    fuel_added: u64,
+}
+
+// compiles the plugin and checks it can be instantiated.
+pub fn load(path: &str) -> Result<Plugin, String> {
+    let wasm = match std::fs::read(path) {
+        Ok(wasm) => wasm,
+        Err(e) => return Err(format!("failed to open plugin {}: {:?}", path, e)),
+    };
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config);
+    let module = match Module::new(&engine, &wasm[..]) {
+        Ok(module) => module,
+        Err(e) => return Err(format!("invalid plugin {}: {}", path, e)),
+    };
+    let name = std::path::Path::new(path)
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string());
+    let plugin = Plugin {
+        name,
+        engine,
+        module: Arc::new(module),
+    };
+    plugin.instantiate()?;
+    Ok(plugin)
+}
+
+impl Plugin {
+    pub fn instantiate(&self) -> Result<PluginInstance, String> {
+        let fail = |e: &dyn std::fmt::Display| format!("invalid plugin {}: {}", self.name, e);
+        let mut store = Store::new(&self.engine, ());
+        let linker = <Linker<()>>::new(&self.engine);
+        let instance = linker
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| fail(&e))?;
+        let memory = match instance.get_memory(&store, "memory") {
+            Some(memory) => memory,
+            None => return Err(fail(&"the memory isn't exported")),
+        };
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(|e| fail(&e))?;
+        let inspect = instance
+            .get_typed_func::<(i32, i32), i64>(&store, "inspect")
+            .map_err(|e| fail(&e))?;
+        Ok(PluginInstance {
+            name: self.name.clone(),
+            store,
+            memory,
+            alloc,
+            inspect,
+            fuel_added: 0,
+        })
+    }
+}
+
+impl PluginInstance {
+    pub fn inspect(&mut self, inspection: &Inspection) -> Result<PluginOutput, String> {
+        // top the fuel back up to the budget of an inspection.
+        let consumed = self.store.fuel_consumed().unwrap_or(0);
+        let refill = FUEL_PER_INSPECTION - (self.fuel_added - consumed).min(FUEL_PER_INSPECTION);
+        self.store.add_fuel(refill).map_err(|e| e.to_string())?;
+        self.fuel_added += refill;
+
+        let input = match serde_json::to_vec(inspection) {
+            Ok(input) => input,
+            Err(e) => return Err(format!("failed to encode the response: {}", e)),
+        };
+        let ptr = self
+            .alloc
+            .call(&mut self.store, input.len() as i32)
+            .map_err(|e| e.to_string())?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, &input)
+            .map_err(|e| e.to_string())?;
+        let packed = self
+            .inspect
+            .call(&mut self.store, (ptr, input.len() as i32))
+            .map_err(|e| e.to_string())?;
+        if packed == 0 {
+            return Ok(PluginOutput::default());
+        }
+        let offset = (packed as u64 >> 32) as usize;
+        let len = (packed as u64 & 0xffff_ffff) as usize;
+        // the output is parsed in place, a length past the memory is refused.
+        let memory = self.memory.data(&self.store);
+        let output = match offset
+            .checked_add(len)
+            .and_then(|end| memory.get(offset..end))
+        {
+            Some(output) => output,
+            None => {
+                return Err(format!(
+                    "the output at {} of {} bytes is out of the memory",
+                    offset, len
+                ))
+            }
+        };
+        serde_json::from_slice(output).map_err(|e| format!("invalid plugin output: {}", e))
+    }
+}
+
+// the headers of the response as the plugins see them, lowercased.
+fn headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.as_str().to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        })
+        .collect()
+}
+
+// the inspection of the response, the body is read by the caller.
+pub fn inspection<'a>(
+    url: &'a str,
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: &'a str,
+) -> Inspection<'a> {
+    Inspection {
+        url,
+        status: status.as_u16(),
+        headers: self::headers(headers),
+        body,
+    }
+}
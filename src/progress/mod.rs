@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use indicatif::ProgressBar;
+
+use crate::events::{self, ScanEvent};
+use crate::metrics;
+
+// the JsonProgress struct emits the state of the progress bar as json lines
+// on stderr with --json-progress, for the wrappers that can't read the bar,
+// and as progress events to the --events subscribers
+#[derive(Debug)]
+pub struct JsonProgress {
+    pb: ProgressBar,
+    stage: Mutex<&'static str>,
+    // whether the lines are printed on stderr
+    print: bool,
+    finished: AtomicBool,
+}
+
+impl JsonProgress {
+    // starts emitting a line every second until the scan ends.
+    pub fn start(pb: ProgressBar, print: bool) -> Arc<JsonProgress> {
+        let progress = Arc::new(JsonProgress {
+            pb,
+            stage: Mutex::new("detection"),
+            print,
+            finished: AtomicBool::new(false),
+        });
+        let reporter = progress.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            let mut previous = (metrics::requests(), 0);
+            loop {
+                interval.tick().await;
+                if reporter.finished.load(Ordering::Relaxed) {
+                    return;
+                }
+                let requests = metrics::requests();
+                let done = reporter.pb.position();
+                // the position goes back to zero when the next stage starts.
+                reporter.emit(requests - previous.0, done.saturating_sub(previous.1));
+                previous = (requests, done);
+            }
+        });
+        progress
+    }
+
+    // the stage the bar counts the jobs of: detection, bruteforcing or vhost.
+    pub fn set_stage(&self, stage: &'static str) {
+        *self.stage.lock().unwrap() = stage;
+    }
+
+    // emits the last line once every stage is over, or the scan was cancelled,
+    // and stops the reporting.
+    pub fn finish(&self, cancelled: bool) {
+        self.finished.store(true, Ordering::Relaxed);
+        self.set_stage(if cancelled { "cancelled" } else { "done" });
+        self.emit(0, 0);
+    }
+
+    // the eta is the seconds the remaining jobs take at the rate of the last second.
+    fn emit(&self, rps: u64, jobs_per_second: u64) {
+        let done = self.pb.position();
+        let total = self.pb.length().unwrap_or(0);
+        let eta = if done < total && jobs_per_second > 0 {
+            (total - done) / jobs_per_second
+        } else {
+            0
+        };
+        let stage = *self.stage.lock().unwrap();
+        let errors = metrics::errors();
+        if self.print {
+            let line = serde_json::json!({
+                "stage": stage,
+                "done": done,
+                "total": total,
+                "rps": rps,
+                "eta": eta,
+                "errors": errors,
+            });
+            eprintln!("{}", line);
+        }
+        events::publish(ScanEvent::Progress {
+            stage: stage.to_string(),
+            done,
+            total,
+            rps,
+            eta,
+            errors,
+        });
+    }
+}
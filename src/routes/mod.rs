@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use reqwest::Method;
+
+// the Route struct is a line of the --routes file, eg `POST /api/orders` or
+// `GET|HEAD /users/{id}` from a framework route dump. no methods means any,
+// the route is then sent with --methods.
+#[derive(Clone, Debug)]
+pub struct Route {
+    pub methods: Vec<Method>,
+    pub path: String,
+}
+
+// parses a route, the methods can be separated by | or , and ANY or * stand
+// for any method. the lines without a path are skipped.
+pub fn parse_route(line: &str) -> Result<Option<Route>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (methods, path) = match (parts.next(), parts.next()) {
+        (Some(methods), Some(path)) => (methods, path),
+        _ => return Ok(None),
+    };
+    if !path.starts_with('/') && !path.starts_with("http://") && !path.starts_with("https://") {
+        return Ok(None);
+    }
+    let mut route = Route {
+        methods: vec![],
+        path: path.to_string(),
+    };
+    if methods == "*" || methods.eq_ignore_ascii_case("any") {
+        return Ok(Some(route));
+    }
+    for method in methods
+        .split(['|', ','])
+        .filter(|method| !method.is_empty())
+    {
+        let method = match Method::from_bytes(method.to_uppercase().as_bytes()) {
+            Ok(method) => method,
+            Err(_) => return Err(format!("invalid route method: {}", method)),
+        };
+        if !route.methods.contains(&method) {
+            route.methods.push(method);
+        }
+    }
+    Ok(Some(route))
+}
+
+pub fn load(path: &str) -> Result<Vec<Route>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("failed to open routes file: {:?}", e)),
+    };
+    let mut routes = vec![];
+    for (number, line) in contents.lines().enumerate() {
+        match parse_route(line) {
+            Ok(Some(route)) => routes.push(route),
+            Ok(None) => {}
+            Err(e) => return Err(format!("{}:{}: {}", path, number + 1, e)),
+        }
+    }
+    if routes.is_empty() {
+        return Err(format!("no routes found in {}", path));
+    }
+    Ok(routes)
+}
+
+// the parameters of a route, `{id}`, `:id` or `<id>`, are given a value so the
+// route can be requested.
+fn fill_params(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            let param = (segment.starts_with('{') && segment.ends_with('}'))
+                || (segment.starts_with('<') && segment.ends_with('>'))
+                || (segment.starts_with(':') && segment.len() > 1);
+            if param {
+                "1"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+// the urls of the routes under each target, the path of the target is kept in
+// front of the route. a route given as a url is its own target.
+pub fn expand(targets: &[String], routes: &[Route]) -> Vec<(String, Vec<Method>)> {
+    let mut expanded: Vec<(String, Vec<Method>)> = vec![];
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut add = |url: String, methods: &[Method]| {
+        let index = match seen.get(&url) {
+            Some(index) => *index,
+            None => {
+                seen.insert(url.clone(), expanded.len());
+                expanded.push((url, methods.to_vec()));
+                return;
+            }
+        };
+        let seen_methods = &mut expanded[index].1;
+        // a route for any method stays one.
+        if methods.is_empty() || seen_methods.is_empty() {
+            seen_methods.clear();
+            return;
+        }
+        for method in methods {
+            if !seen_methods.contains(method) {
+                seen_methods.push(method.clone());
+            }
+        }
+    };
+    let bases: Vec<String> = targets
+        .iter()
+        .filter_map(|target| reqwest::Url::parse(target).ok())
+        .map(|mut base| {
+            base.set_query(None);
+            base.set_fragment(None);
+            base.to_string().trim_end_matches('/').to_string()
+        })
+        .collect();
+    for route in routes {
+        let path = fill_params(&route.path);
+        if path.starts_with("http://") || path.starts_with("https://") {
+            add(path, &route.methods);
+            continue;
+        }
+        for base in &bases {
+            add(format!("{}{}", base, path), &route.methods);
+        }
+    }
+    expanded
+}
@@ -0,0 +1,653 @@
+use std::collections::HashMap;
+use std::future::{ready, Future};
+use std::sync::{Arc, Mutex};
+
+use colored::Colorize;
+use futures::stream::FuturesUnordered;
+use futures::{Stream, StreamExt};
+use indicatif::ProgressBar;
+use itertools::iproduct;
+use reqwest::header::HeaderValue;
+use reqwest::Method;
+use tokio::fs::{File, OpenOptions};
+use tokio::runtime::{Builder, Runtime};
+use tokio::sync::mpsc;
+use tokio::task;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use crate::bruteforcer::{self, BruteJob, BruteResult, Calibration};
+use crate::client::{ClientFactory, ClientSettings};
+use crate::detector::{self, Job, JobResult, JobSettings};
+use crate::events::{self, ScanEvent};
+use crate::notify::Finding;
+use crate::observer::{self, ScanObserver};
+use crate::output::silent::LineFormat;
+use crate::progress::JsonProgress;
+use crate::transform::FamilyOutcome;
+use crate::vhost::{self, VhostJob, VhostResult};
+
+// the Options struct holds what the runner scans and how: the targets and
+// lists, the detection jobs and the http clients. it is built with
+// Options::builder(), the fields keep growing.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Options {
+    pub urls: Vec<String>,
+    pub payloads: Vec<String>,
+    pub methods: Vec<Method>,
+    pub wordlist: Vec<String>,
+    // the Host headers fuzzed against the internal doc roots
+    pub vhosts: Vec<String>,
+    // the requests per second of each stage
+    pub rate: u32,
+    // the workers of each stage
+    pub concurrency: u32,
+    // the threads the jobs are dispatched from
+    pub workers: usize,
+    pub skip_brute: bool,
+    pub head_first: bool,
+    pub calibration: Option<Arc<Calibration>>,
+    pub job_settings: JobSettings,
+    pub client_settings: ClientSettings,
+    // the file the discovered routes are appended to as they are found
+    pub discoveries_out: Option<String>,
+    // the discovered routes are printed on stdout in the format
+    pub silent: Option<LineFormat>,
+    // the progress is printed on stderr as json lines
+    pub json_progress: bool,
+    // the bar counting the jobs of each stage, hidden by default
+    pub progress: ProgressBar,
+}
+
+impl Options {
+    // starts from the defaults of the cli flags.
+    pub fn builder() -> OptionsBuilder {
+        OptionsBuilder {
+            options: Options {
+                urls: vec![],
+                payloads: vec![],
+                methods: vec![Method::GET],
+                wordlist: vec![],
+                vhosts: vec![],
+                rate: 1000,
+                concurrency: 1000,
+                workers: 10,
+                skip_brute: false,
+                head_first: false,
+                calibration: None,
+                job_settings: JobSettings::default(),
+                client_settings: ClientSettings::default(),
+                discoveries_out: None,
+                silent: None,
+                json_progress: false,
+                progress: ProgressBar::hidden(),
+            },
+        }
+    }
+}
+
+// the OptionsBuilder struct builds the Options, build() rejects the values the
+// runner can't scan with
+#[derive(Clone, Debug)]
+pub struct OptionsBuilder {
+    options: Options,
+}
+
+impl OptionsBuilder {
+    pub fn with_urls(mut self, urls: Vec<String>) -> OptionsBuilder {
+        self.options.urls = urls;
+        self
+    }
+
+    pub fn with_payloads(mut self, payloads: Vec<String>) -> OptionsBuilder {
+        self.options.payloads = payloads;
+        self
+    }
+
+    pub fn with_methods(mut self, methods: Vec<Method>) -> OptionsBuilder {
+        self.options.methods = methods;
+        self
+    }
+
+    pub fn with_wordlist(mut self, wordlist: Vec<String>) -> OptionsBuilder {
+        self.options.wordlist = wordlist;
+        self
+    }
+
+    pub fn with_vhosts(mut self, vhosts: Vec<String>) -> OptionsBuilder {
+        self.options.vhosts = vhosts;
+        self
+    }
+
+    pub fn with_rate(mut self, rate: u32) -> OptionsBuilder {
+        self.options.rate = rate;
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: u32) -> OptionsBuilder {
+        self.options.concurrency = concurrency;
+        self
+    }
+
+    pub fn with_workers(mut self, workers: usize) -> OptionsBuilder {
+        self.options.workers = workers;
+        self
+    }
+
+    pub fn with_skip_brute(mut self, skip_brute: bool) -> OptionsBuilder {
+        self.options.skip_brute = skip_brute;
+        self
+    }
+
+    pub fn with_head_first(mut self, head_first: bool) -> OptionsBuilder {
+        self.options.head_first = head_first;
+        self
+    }
+
+    pub fn with_calibration(mut self, calibration: Option<Arc<Calibration>>) -> OptionsBuilder {
+        self.options.calibration = calibration;
+        self
+    }
+
+    pub fn with_job_settings(mut self, job_settings: JobSettings) -> OptionsBuilder {
+        self.options.job_settings = job_settings;
+        self
+    }
+
+    pub fn with_client_settings(mut self, client_settings: ClientSettings) -> OptionsBuilder {
+        self.options.client_settings = client_settings;
+        self
+    }
+
+    pub fn with_discoveries_out(mut self, discoveries_out: Option<String>) -> OptionsBuilder {
+        self.options.discoveries_out = discoveries_out;
+        self
+    }
+
+    pub fn with_silent(mut self, silent: Option<LineFormat>) -> OptionsBuilder {
+        self.options.silent = silent;
+        self
+    }
+
+    pub fn with_json_progress(mut self, json_progress: bool) -> OptionsBuilder {
+        self.options.json_progress = json_progress;
+        self
+    }
+
+    pub fn with_progress(mut self, progress: ProgressBar) -> OptionsBuilder {
+        self.options.progress = progress;
+        self
+    }
+
+    pub fn build(self) -> Result<Options, String> {
+        let options = self.options;
+        if options.rate == 0 {
+            return Err("invalid rate, at least one request per second".to_string());
+        }
+        if options.workers == 0 {
+            return Err("invalid workers, at least one thread".to_string());
+        }
+        if options.methods.is_empty() {
+            return Err("no method to send the payloads with".to_string());
+        }
+        let content_type = &options.job_settings.content_type;
+        if HeaderValue::from_str(content_type).is_err() {
+            return Err(format!("invalid content type: {}", content_type));
+        }
+        if let Some(shard) = options.job_settings.shard {
+            if shard.index == 0 || shard.index > shard.total {
+                return Err(format!(
+                    "invalid shard {}/{}, the index goes from 1 to the total",
+                    shard.index, shard.total
+                ));
+            }
+        }
+        Ok(options)
+    }
+}
+
+// the ScanResult struct holds what the stages of the scan found
+#[derive(Clone, Debug, Default)]
+pub struct ScanResult {
+    // the targets scanned, the ones streamed in included
+    pub urls: Vec<String>,
+    pub internal_roots: Vec<JobResult>,
+    // what the follow up stages found, behind an internal doc root or not
+    pub follow_ups: Vec<Finding>,
+    // the discovered routes and their content length
+    pub discoveries: HashMap<String, String>,
+    pub vhosts: Vec<VhostResult>,
+    // the attempts, blocks and bypasses of the payload families per waf
+    pub families: Vec<FamilyOutcome>,
+    // the scan was cancelled, the results are partial
+    pub cancelled: bool,
+}
+
+// the Runner struct runs the detection, the bruteforcing of the internal doc
+// roots and the virtual host fuzzing of a scan
+pub struct Runner {
+    options: Options,
+    // called for the requests, responses, findings and failed requests of the scan
+    observers: Vec<Arc<dyn ScanObserver>>,
+    // the urls scanned as they arrive, after the ones of the options
+    url_stream: Mutex<Option<mpsc::UnboundedReceiver<String>>>,
+}
+
+// the runtime the jobs are dispatched from, shut down in the background once
+// the runner is done or dropped so it never blocks the caller.
+struct DispatchRuntime(Option<Runtime>);
+
+impl DispatchRuntime {
+    fn spawn<F>(&self, future: F)
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        if let Some(rt) = &self.0 {
+            rt.spawn(future);
+        }
+    }
+}
+
+impl Drop for DispatchRuntime {
+    fn drop(&mut self) {
+        if let Some(rt) = self.0.take() {
+            rt.shutdown_background();
+        }
+    }
+}
+
+// the observers of a run, unregistered once the run is done or dropped.
+struct RunObservers(Vec<Arc<dyn ScanObserver>>);
+
+impl RunObservers {
+    fn register(observers: &[Arc<dyn ScanObserver>]) -> RunObservers {
+        for observer in observers {
+            observer::register(observer.clone());
+        }
+        RunObservers(observers.to_vec())
+    }
+}
+
+impl Drop for RunObservers {
+    fn drop(&mut self) {
+        for observer in &self.0 {
+            observer::unregister(observer);
+        }
+    }
+}
+
+impl Runner {
+    pub fn new(options: Options) -> Runner {
+        Runner {
+            options,
+            observers: vec![],
+            url_stream: Mutex::new(None),
+        }
+    }
+
+    // adds an observer called while this runner runs. the observers
+    // registered with observer::register are called for every scan, the
+    // observers of concurrent runs see the requests of each other.
+    pub fn with_observer(mut self, observer: Arc<dyn ScanObserver>) -> Runner {
+        self.observers.push(observer);
+        self
+    }
+
+    // the workers of this runner take their reqwest clients from the factory,
+    // eg a pre-configured client with its own tls stack or connection pool.
+    pub fn with_client_factory(mut self, client_factory: ClientFactory) -> Runner {
+        self.options.client_settings.client_factory = Some(client_factory);
+        self
+    }
+
+    // the urls sent on the channel are scanned as they arrive, the detection
+    // lasts until it is closed. profile them and put their fingerprint in the
+    // fingerprints of the job settings before sending them.
+    pub fn with_url_stream(self, urls: mpsc::UnboundedReceiver<String>) -> Runner {
+        *self.url_stream.lock().unwrap() = Some(urls);
+        self
+    }
+
+    // runs the scan and returns the events as they happen: the findings, the
+    // discovered routes and the progress. the stream ends with the
+    // scan_completed event, cancel the token to end it early. the fingerprints
+    // are published while profiling, subscribe with events::subscribe before
+    // fingerprint::profile_targets to get them. the events are process wide,
+    // stream one scan at a time.
+    pub fn run_stream(self, cancel: CancellationToken) -> impl Stream<Item = ScanEvent> {
+        let events = events::subscribe();
+        tokio::spawn(async move {
+            if let Err(e) = self.run(cancel).await {
+                tracing::warn!(error = %e, "the scan failed");
+                events::publish(ScanEvent::ScanCompleted { cancelled: false });
+            }
+        });
+        events.scan(false, |completed, event| {
+            if *completed {
+                return ready(None);
+            }
+            *completed = matches!(event, ScanEvent::ScanCompleted { .. });
+            ready(Some(event))
+        })
+    }
+
+    // runs the scan and returns what it found once every stage is over. once
+    // the token is cancelled no job is dispatched anymore, the workers finish
+    // the queued ones and the partial results are returned.
+    pub async fn run(&self, cancel: CancellationToken) -> Result<ScanResult, String> {
+        let options = &self.options;
+        let pb = options.progress.clone();
+        let rt = Builder::new_multi_thread()
+            .enable_all()
+            .worker_threads(options.workers.max(1))
+            .build()
+            .map_err(|e| format!("failed to start the workers: {}", e))?;
+        let rt = DispatchRuntime(Some(rt));
+        let outfile = match &options.discoveries_out {
+            Some(path) => Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map_err(|e| format!("failed to open output file: {:?}", e))?,
+            ),
+            None => None,
+        };
+        let _observers = RunObservers::register(&self.observers);
+        let progress = JsonProgress::start(pb.clone(), options.json_progress);
+
+        let mut result = self.detect(&rt, &pb, &cancel).await;
+        let internal_roots: Vec<String> = result
+            .internal_roots
+            .iter()
+            .map(|root| root.data.clone())
+            .collect();
+
+        // the words learned from the internal doc roots are bruteforced after the wordlist.
+        let mut brute_wordlist = options.wordlist.clone();
+        if let Some(word_learner) = &options.job_settings.word_learner {
+            let learned = word_learner.words();
+            if !learned.is_empty() {
+                pb.println(format!(
+                    "{} {} {}",
+                    "learned ::".bold().green(),
+                    learned.len().to_string().bold().cyan(),
+                    "new words from the internal doc roots".bold().white(),
+                ));
+            }
+            brute_wordlist.extend(learned);
+        }
+
+        if !options.skip_brute {
+            progress.set_stage("bruteforcing");
+            result.discoveries = self
+                .bruteforce(
+                    &rt,
+                    &pb,
+                    &cancel,
+                    outfile,
+                    internal_roots.clone(),
+                    brute_wordlist,
+                )
+                .await;
+        }
+
+        // fuzz the Host header of the internal doc roots.
+        if !options.vhosts.is_empty() && !internal_roots.is_empty() {
+            progress.set_stage("vhost");
+            result.vhosts = self.fuzz_vhosts(&rt, &pb, &cancel, internal_roots).await;
+        }
+
+        let job_settings = &options.job_settings;
+        result.families = job_settings
+            .family_stats
+            .summary(|host| job_settings.escalation.waf(host).map(|waf| waf.name));
+        if !result.families.is_empty() {
+            events::publish(ScanEvent::FamilySummary {
+                families: result.families.clone(),
+            });
+        }
+
+        result.cancelled = cancel.is_cancelled();
+        progress.finish(result.cancelled);
+        events::publish(ScanEvent::ScanCompleted {
+            cancelled: result.cancelled,
+        });
+        Ok(result)
+    }
+
+    async fn detect(
+        &self,
+        rt: &DispatchRuntime,
+        pb: &ProgressBar,
+        cancel: &CancellationToken,
+    ) -> ScanResult {
+        let options = &self.options;
+        let bar_length = options.urls.iter().map(|url| url_jobs(options, url)).sum();
+        pb.set_length(bar_length);
+        pb.set_position(0);
+
+        let (url_tx, urls) = mpsc::unbounded_channel::<String>();
+        for url in &options.urls {
+            let _ = url_tx.send(url.clone());
+        }
+        // the urls streamed in follow, the bar grows with their jobs.
+        let streamed = self.url_stream.lock().unwrap().take();
+        let forwarder = streamed.map(|mut streamed| {
+            let options = options.clone();
+            let pb = pb.clone();
+            let cancel = cancel.clone();
+            task::spawn(async move {
+                let mut urls = vec![];
+                loop {
+                    let url = tokio::select! {
+                        url = streamed.recv() => url,
+                        _ = cancel.cancelled() => None,
+                    };
+                    let url = match url {
+                        Some(url) => url,
+                        None => return urls,
+                    };
+                    pb.inc_length(url_jobs(&options, &url));
+                    urls.push(url.clone());
+                    if url_tx.send(url).is_err() {
+                        return urls;
+                    }
+                }
+            })
+        });
+
+        let (job_tx, job_rx) = spmc::channel::<Job>();
+        let (result_tx, _result_rx) = mpsc::channel::<JobResult>(options.workers.max(1));
+        let payloads = options.payloads.clone();
+        let methods = options.methods.clone();
+        let wordlist = options.wordlist.clone();
+        let rate = options.rate;
+        let mut job_settings = options.job_settings.clone();
+        job_settings.cancel = cancel.clone();
+        rt.spawn(async move {
+            detector::send_url(
+                job_tx,
+                urls,
+                payloads,
+                methods,
+                wordlist,
+                rate,
+                job_settings,
+            )
+            .await
+        });
+
+        let workers = FuturesUnordered::new();
+        tracing::info!(jobs = bar_length, "detection started");
+        for worker in 0..options.concurrency {
+            let client_settings = options.client_settings.clone();
+            let jrx = job_rx.clone();
+            let jtx = result_tx.clone();
+            let jpb = pb.clone();
+            workers.push(task::spawn(
+                async move { detector::run_tester(jpb, jrx, jtx, client_settings).await }
+                    .instrument(tracing::info_span!("detector", worker)),
+            ));
+        }
+
+        let mut result = ScanResult {
+            urls: options.urls.clone(),
+            ..Default::default()
+        };
+        let worker_results: Vec<_> = workers.collect().await;
+        if let Some(forwarder) = forwarder {
+            if let Ok(streamed) = forwarder.await {
+                result.urls.extend(streamed);
+            }
+        }
+        for worker_result in worker_results.into_iter().flatten() {
+            // the follow up findings are reported even when the worker found
+            // no internal doc root.
+            result
+                .follow_ups
+                .extend(worker_result.follow_ups.iter().cloned());
+            if !worker_result.data.is_empty() {
+                result.internal_roots.push(worker_result);
+            }
+        }
+        result
+    }
+
+    async fn bruteforce(
+        &self,
+        rt: &DispatchRuntime,
+        pb: &ProgressBar,
+        cancel: &CancellationToken,
+        outfile: Option<File>,
+        internal_roots: Vec<String>,
+        wordlist: Vec<String>,
+    ) -> HashMap<String, String> {
+        let options = &self.options;
+        let scope = options.job_settings.scope.clone();
+        let bar_length = iproduct!(&internal_roots, &wordlist)
+            .filter(|(url, word)| bruteforcer::in_scope(&scope, url, word))
+            .count() as u64;
+        pb.set_length(bar_length);
+        pb.set_position(0);
+
+        let (brute_job_tx, brute_job_rx) = spmc::channel::<BruteJob>();
+        let (brute_result_tx, brute_result_rx) =
+            mpsc::channel::<BruteResult>(options.workers.max(1));
+        let rate = options.rate;
+        let cancel = cancel.clone();
+        rt.spawn(async move {
+            bruteforcer::send_word_to_url(
+                brute_job_tx,
+                internal_roots,
+                wordlist,
+                rate,
+                scope,
+                cancel,
+            )
+            .await
+        });
+        let save_pb = pb.clone();
+        let silent = options.silent;
+        let tags = options.job_settings.tags.clone();
+        rt.spawn(async move {
+            bruteforcer::save_discoveries(save_pb, outfile, brute_result_rx, silent, tags).await
+        });
+
+        let workers = FuturesUnordered::new();
+        tracing::info!(jobs = bar_length, "bruteforcing started");
+        for worker in 0..options.concurrency {
+            let client_settings = options.client_settings.clone();
+            let calibration = options.calibration.clone();
+            let head_first = options.head_first;
+            let brx = brute_job_rx.clone();
+            let btx = brute_result_tx.clone();
+            let bpb = pb.clone();
+            workers.push(task::spawn(
+                async move {
+                    bruteforcer::run_bruteforcer(
+                        bpb,
+                        brx,
+                        btx,
+                        client_settings,
+                        head_first,
+                        calibration,
+                    )
+                    .await
+                }
+                .instrument(tracing::info_span!("bruteforcer", worker)),
+            ));
+        }
+
+        let mut discoveries = HashMap::new();
+        let worker_results: Vec<_> = workers.collect().await;
+        for worker_result in worker_results.into_iter().flatten() {
+            if !worker_result.data.is_empty() {
+                discoveries.insert(worker_result.data, worker_result.rs);
+            }
+        }
+        discoveries
+    }
+
+    async fn fuzz_vhosts(
+        &self,
+        rt: &DispatchRuntime,
+        pb: &ProgressBar,
+        cancel: &CancellationToken,
+        internal_roots: Vec<String>,
+    ) -> Vec<VhostResult> {
+        let options = &self.options;
+        let bar_length = (internal_roots.len() * options.vhosts.len()) as u64;
+        pb.set_length(bar_length);
+        pb.set_position(0);
+
+        let (vhost_job_tx, vhost_job_rx) = spmc::channel::<VhostJob>();
+        let hosts = options.vhosts.clone();
+        let rate = options.rate;
+        let cancel = cancel.clone();
+        rt.spawn(async move {
+            vhost::send_host_to_url(vhost_job_tx, internal_roots, hosts, rate, cancel).await
+        });
+
+        let workers = FuturesUnordered::new();
+        let baselines = Arc::new(vhost::Baselines::default());
+        tracing::info!(jobs = bar_length, "virtual host fuzzing started");
+        for worker in 0..options.concurrency {
+            let client_settings = options.client_settings.clone();
+            let job_settings = options.job_settings.clone();
+            let baselines = baselines.clone();
+            let vrx = vhost_job_rx.clone();
+            let vpb = pb.clone();
+            workers.push(task::spawn(
+                async move {
+                    vhost::run_vhost_fuzzer(vpb, vrx, client_settings, job_settings, baselines)
+                        .await
+                }
+                .instrument(tracing::info_span!("vhost", worker)),
+            ));
+        }
+        let worker_results: Vec<_> = workers.collect().await;
+        worker_results.into_iter().flatten().flatten().collect()
+    }
+}
+
+// every injection point and query parameter of a url is tested as a separate
+// job with each payload its profile allows.
+fn url_jobs(options: &Options, url: &str) -> u64 {
+    let job_settings = &options.job_settings;
+    let mut targets = detector::url_injection_points(job_settings, url).len();
+    if !job_settings.skip_validation {
+        targets += detector::query_params(url).len();
+    }
+    let url_payloads = detector::allowed_payloads(job_settings, url, &options.payloads);
+    let jobs = targets
+        * url_payloads
+            .iter()
+            .filter(|payload| detector::owns_job(job_settings, url, payload, ""))
+            .count()
+        * detector::url_methods(job_settings, url, &options.methods).len();
+    jobs as u64
+}
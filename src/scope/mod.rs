@@ -0,0 +1,85 @@
+use regex::Regex;
+
+// the Scope struct holds the --scope domains and the --exclude-path and
+// --exclude-regex rules, a url it doesn't allow is never requested
+#[derive(Clone, Debug, Default)]
+pub struct Scope {
+    // the hosts of a domain are in scope along with the domain itself
+    domains: Vec<String>,
+    exclude_paths: Vec<String>,
+    exclude_regexes: Vec<Regex>,
+}
+
+impl Scope {
+    pub fn new(
+        domains: &[&str],
+        exclude_paths: &[&str],
+        exclude_regexes: &[&str],
+    ) -> Result<Scope, String> {
+        let domains = domains
+            .iter()
+            .map(|domain| domain.trim().trim_start_matches("*.").trim_end_matches('.'))
+            .filter(|domain| !domain.is_empty())
+            .map(|domain| domain.to_lowercase())
+            .collect();
+        let exclude_paths = exclude_paths
+            .iter()
+            .map(|path| path.trim().trim_end_matches('/'))
+            .filter(|path| !path.is_empty())
+            .map(|path| {
+                if path.starts_with('/') {
+                    path.to_string()
+                } else {
+                    format!("/{}", path)
+                }
+            })
+            .collect();
+        let mut regexes = vec![];
+        for exclude_regex in exclude_regexes {
+            match Regex::new(exclude_regex) {
+                Ok(regex) => regexes.push(regex),
+                Err(e) => return Err(format!("invalid --exclude-regex {}: {}", exclude_regex, e)),
+            }
+        }
+        Ok(Scope {
+            domains,
+            exclude_paths,
+            exclude_regexes: regexes,
+        })
+    }
+
+    // whether any rule was given, the scope allows everything otherwise.
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty() && self.exclude_paths.is_empty() && self.exclude_regexes.is_empty()
+    }
+
+    // whether the url may be requested. an excluded path matches whole segments
+    // anywhere in the path, so /logout also rules out /app/..;/logout/now.
+    pub fn allows(&self, url: &str) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        if self.exclude_regexes.iter().any(|regex| regex.is_match(url)) {
+            return false;
+        }
+        let parsed = match reqwest::Url::parse(url) {
+            Ok(parsed) => parsed,
+            Err(_) => return false,
+        };
+        if !self.domains.is_empty() {
+            let host = parsed.host_str().unwrap_or("").to_lowercase();
+            let in_scope = self
+                .domains
+                .iter()
+                .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)));
+            if !in_scope {
+                return false;
+            }
+        }
+        let path = format!("{}/", parsed.path());
+        !self
+            .exclude_paths
+            .iter()
+            .any(|excluded| path.contains(&format!("{}/", excluded)))
+    }
+}
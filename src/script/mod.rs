@@ -0,0 +1,143 @@
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+
+// the operations a hook can run per call, a script that loops forever fails
+// the call instead of stalling the worker
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+// the Script struct holds a compiled --script, its `on_request(req)` hook runs
+// before each job is sent and its `on_response(resp)` hook on the responses
+// the detector reads
+#[derive(Debug)]
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    on_request: bool,
+    on_response: bool,
+}
+
+// the ScriptRequest enum holds what on_request decided for a job
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptRequest {
+    // the job is sent with the headers
+    Send(Vec<(String, String)>),
+    Skip,
+}
+
+pub fn load(path: &str) -> Result<Script, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("failed to open script: {:?}", e)),
+    };
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    let ast = match engine.compile(&contents) {
+        Ok(ast) => ast,
+        Err(e) => return Err(format!("invalid script {}: {}", path, e)),
+    };
+    let defines = |name: &str| {
+        ast.iter_functions()
+            .any(|function| function.name == name && function.params.len() == 1)
+    };
+    let on_request = defines("on_request");
+    let on_response = defines("on_response");
+    if !on_request && !on_response {
+        return Err(format!(
+            "script {} defines neither on_request(req) nor on_response(resp)",
+            path
+        ));
+    }
+    Ok(Script {
+        engine,
+        ast,
+        on_request,
+        on_response,
+    })
+}
+
+fn headers_map(headers: &HeaderMap) -> Map {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.as_str().into(),
+                String::from_utf8_lossy(value.as_bytes()).to_string().into(),
+            )
+        })
+        .collect()
+}
+
+impl Script {
+    fn call(&self, hook: &str, arg: Map) -> Option<Dynamic> {
+        let mut scope = Scope::new();
+        match self
+            .engine
+            .call_fn::<Dynamic>(&mut scope, &self.ast, hook, (arg,))
+        {
+            Ok(result) => Some(result),
+            Err(e) => {
+                tracing::warn!(hook, error = %e, "script hook failed");
+                None
+            }
+        }
+    }
+
+    // calls on_request with `#{method, url, payload, headers}`. the hook returns
+    // the map with the headers to send, false to skip the job, or nothing.
+    pub fn on_request(&self, method: &Method, url: &str, payload: &str) -> ScriptRequest {
+        if !self.on_request {
+            return ScriptRequest::Send(vec![]);
+        }
+        let mut req = Map::new();
+        req.insert("method".into(), method.as_str().into());
+        req.insert("url".into(), url.into());
+        req.insert("payload".into(), payload.into());
+        req.insert("headers".into(), Map::new().into());
+        let result = match self.call("on_request", req) {
+            Some(result) => result,
+            None => return ScriptRequest::Send(vec![]),
+        };
+        if result.as_bool() == Ok(false) {
+            return ScriptRequest::Skip;
+        }
+        let headers = result
+            .try_cast::<Map>()
+            .and_then(|req| req.get("headers").cloned())
+            .and_then(|headers| headers.try_cast::<Map>())
+            .unwrap_or_default();
+        ScriptRequest::Send(
+            headers
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    // calls on_response with `#{url, status, headers, body}`. the hook flags a
+    // finding by returning its title, or true for an untitled one.
+    pub fn on_response(
+        &self,
+        url: &str,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &str,
+    ) -> Option<String> {
+        if !self.on_response {
+            return None;
+        }
+        let mut resp = Map::new();
+        resp.insert("url".into(), url.into());
+        resp.insert("status".into(), (status.as_u16() as i64).into());
+        resp.insert("headers".into(), headers_map(headers).into());
+        resp.insert("body".into(), body.into());
+        let result = self.call("on_response", resp)?;
+        if result.as_bool() == Ok(true) {
+            return Some("flagged by script".to_string());
+        }
+        match result.into_string() {
+            Ok(title) if !title.is_empty() => Some(title),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use serde::Deserialize;
+
+use crate::fingerprint::{self, Tech, TechRule};
+use crate::waf::{self, WafSignature};
+
+// the SignatureFile struct is the layout of a --signatures yaml file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SignatureFile {
+    waf: Vec<WafEntry>,
+    tech: Vec<TechEntry>,
+}
+
+// the HeaderEntry struct matches a substring of a header value, an empty value
+// only needs the header
+#[derive(Debug, Deserialize)]
+struct HeaderEntry {
+    name: String,
+    #[serde(default)]
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WafEntry {
+    name: String,
+    #[serde(default)]
+    headers: Vec<HeaderEntry>,
+    #[serde(default)]
+    cookies: Vec<String>,
+    // the headers only sent on a block, the others only name the waf
+    #[serde(default)]
+    block_headers: Vec<HeaderEntry>,
+    #[serde(default)]
+    body: Vec<String>,
+    #[serde(default)]
+    statuses: Vec<u16>,
+    #[serde(default)]
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TechEntry {
+    product: String,
+    // the built-in backend whose traversal quirks the product shares
+    tech: Option<Tech>,
+    #[serde(default)]
+    headers: Vec<HeaderEntry>,
+    #[serde(default)]
+    cookies: Vec<String>,
+}
+
+// the signatures live for the whole run, so the strings are leaked to fit
+// next to the built-in static ones.
+fn leak(value: String) -> &'static str {
+    Box::leak(value.into_boxed_str())
+}
+
+fn leak_all(values: Vec<String>) -> &'static [&'static str] {
+    Box::leak(
+        values
+            .into_iter()
+            .map(leak)
+            .collect::<Vec<_>>()
+            .into_boxed_slice(),
+    )
+}
+
+// the header names and the values are matched lowercased like the built-in ones.
+fn leak_headers(headers: Vec<HeaderEntry>) -> &'static [(&'static str, &'static str)] {
+    let headers: Vec<(&'static str, &'static str)> = headers
+        .into_iter()
+        .map(|header| {
+            (
+                leak(header.name.to_lowercase()),
+                leak(header.value.to_lowercase()),
+            )
+        })
+        .collect();
+    Box::leak(headers.into_boxed_slice())
+}
+
+// loads the waf signatures and tech rules of the yaml file and merges them
+// with the built-in ones.
+pub fn load(path: &str) -> Result<(), String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("failed to open signatures file: {:?}", e)),
+    };
+    let file: SignatureFile = match serde_yaml::from_str(&contents) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("invalid signatures file: {}", e)),
+    };
+
+    let wafs = file
+        .waf
+        .into_iter()
+        .map(|entry| WafSignature {
+            name: leak(entry.name),
+            headers: leak_headers(entry.headers),
+            cookies: leak_all(entry.cookies),
+            block_headers: leak_headers(entry.block_headers),
+            body: leak_all(entry.body.iter().map(|body| body.to_lowercase()).collect()),
+            statuses: Box::leak(entry.statuses.into_boxed_slice()),
+            versions: leak_all(
+                entry
+                    .versions
+                    .iter()
+                    .map(|version| version.to_lowercase())
+                    .collect(),
+            ),
+        })
+        .collect();
+    let rules = file
+        .tech
+        .into_iter()
+        .map(|entry| TechRule {
+            product: leak(entry.product),
+            tech: entry.tech,
+            headers: leak_headers(entry.headers),
+            cookies: leak_all(entry.cookies),
+        })
+        .collect();
+    waf::add_signatures(wafs);
+    fingerprint::add_tech_rules(rules);
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use serde::Deserialize;
+
+// the TargetTags struct holds the tags of the targets given as `url tag1,tag2`
+// or as json lines, the findings carry the tags of the target they were found on
+#[derive(Debug, Default)]
+pub struct TargetTags {
+    // the targets piped in are tagged as they arrive
+    tags: RwLock<HashMap<String, Vec<String>>>,
+}
+
+// the JsonTarget struct is a target given as a json line, eg
+// `{"url": "https://example.com/", "tags": ["acme", "prod"]}`
+#[derive(Debug, Deserialize)]
+struct JsonTarget {
+    url: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+// parses a line of the urls file into the url and its tags.
+pub fn parse_target(line: &str) -> (String, Vec<String>) {
+    let line = line.trim();
+    if line.starts_with('{') {
+        if let Ok(target) = serde_json::from_str::<JsonTarget>(line) {
+            return (target.url, clean_tags(target.tags));
+        }
+    }
+    match line.split_once(char::is_whitespace) {
+        Some((url, tags)) => (
+            url.to_string(),
+            clean_tags(tags.split(',').map(str::to_string).collect()),
+        ),
+        None => (line.to_string(), vec![]),
+    }
+}
+
+fn clean_tags(tags: Vec<String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = vec![];
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !cleaned.iter().any(|seen| seen == tag) {
+            cleaned.push(tag.to_string());
+        }
+    }
+    cleaned
+}
+
+fn host(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+}
+
+impl TargetTags {
+    pub fn insert(&self, url: &str, tags: Vec<String>) {
+        if !tags.is_empty() {
+            self.tags.write().unwrap().insert(url.to_string(), tags);
+        }
+    }
+
+    // the tags of the target the url was found on, the longest target the url
+    // starts with or else every target on the same host.
+    pub fn tags(&self, url: &str) -> Vec<String> {
+        let tags = self.tags.read().unwrap();
+        if tags.is_empty() {
+            return vec![];
+        }
+        let target = tags
+            .keys()
+            .filter(|target| url.starts_with(target.as_str()))
+            .max_by_key(|target| target.len());
+        if let Some(target) = target {
+            return tags[target].clone();
+        }
+        let host = match host(url) {
+            Some(host) => host,
+            None => return vec![],
+        };
+        let mut targets: Vec<&String> = tags
+            .keys()
+            .filter(|target| self::host(target).as_deref() == Some(host.as_str()))
+            .collect();
+        targets.sort();
+        clean_tags(
+            targets
+                .into_iter()
+                .flat_map(|target| tags[target].clone())
+                .collect(),
+        )
+    }
+}
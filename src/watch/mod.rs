@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::process::Stdio;
+use std::time::Duration;
+
+use colored::Colorize;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::process::Command;
+
+use crate::tags;
+
+// how often the urls file is checked for new lines
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+// the arguments of the scans of the new urls: the arguments pathbuster was
+// started with, without --watch and --urls since the urls are piped in.
+fn scan_args(args: impl Iterator<Item = OsString>) -> Vec<OsString> {
+    let mut scan_args: Vec<OsString> = vec!["--urls".into(), "-".into()];
+    let mut skip_value = false;
+    for arg in args {
+        if skip_value {
+            skip_value = false;
+            continue;
+        }
+        let text = arg.to_string_lossy();
+        if text == "--watch" || text.starts_with("--urls=") {
+            continue;
+        }
+        if text == "--urls" || text == "-u" {
+            skip_value = true;
+            continue;
+        }
+        // -ucrawls.txt
+        if text.starts_with("-u") && !text.starts_with("--") {
+            continue;
+        }
+        scan_args.push(arg);
+    }
+    scan_args
+}
+
+// reads the lines appended to the file since the offset, a line still being
+// written is left for the next read. a file that shrank was rotated and is
+// read from its start again.
+async fn read_new_lines(path: &str, offset: &mut u64) -> Result<Vec<String>, String> {
+    let mut file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(e) => return Err(format!("failed to open input file: {:?}", e)),
+    };
+    let len = match file.metadata().await {
+        Ok(metadata) => metadata.len(),
+        Err(e) => return Err(format!("failed to read input file: {:?}", e)),
+    };
+    if len < *offset {
+        *offset = 0;
+    }
+    if let Err(e) = file.seek(std::io::SeekFrom::Start(*offset)).await {
+        return Err(format!("failed to read input file: {:?}", e));
+    }
+    let mut appended = vec![];
+    if let Err(e) = file.read_to_end(&mut appended).await {
+        return Err(format!("failed to read input file: {:?}", e));
+    }
+    let complete = match appended.iter().rposition(|byte| *byte == b'\n') {
+        Some(last_newline) => last_newline + 1,
+        None => return Ok(vec![]),
+    };
+    *offset += complete as u64;
+    Ok(String::from_utf8_lossy(&appended[..complete])
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+// scans the urls with a pathbuster of their own and waits for it, a ctrl-c
+// reaches the scan too, which then stops the way a scan does.
+async fn scan(args: &[OsString], urls: &[String]) -> Result<bool, String> {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => return Err(format!("failed to find the pathbuster executable: {}", e)),
+    };
+    let mut child = match Command::new(exe)
+        .args(args)
+        .env_remove("PATHBUSTER_WATCH")
+        .env_remove("PATHBUSTER_URLS")
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => return Err(format!("failed to start the scan: {}", e)),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let mut input = urls.join("\n");
+        input.push('\n');
+        if let Err(e) = stdin.write_all(input.as_bytes()).await {
+            return Err(format!("failed to pass the urls to the scan: {}", e));
+        }
+    }
+    let mut interrupted = false;
+    let status = tokio::select! {
+        status = child.wait() => status,
+        _ = tokio::signal::ctrl_c() => {
+            interrupted = true;
+            child.wait().await
+        }
+    };
+    if let Err(e) = status {
+        return Err(format!("the scan failed: {}", e));
+    }
+    Ok(interrupted)
+}
+
+// follows the urls file and scans the urls appended to it as they come, the
+// lines already in the file are scanned first. runs until ctrl-c.
+pub async fn run(path: &str, silent: bool) -> Result<(), String> {
+    let args = scan_args(std::env::args_os().skip(1));
+    let mut offset = 0;
+    let mut seen = HashSet::new();
+    loop {
+        let lines = read_new_lines(path, &mut offset).await?;
+        // a url appended twice is only scanned once.
+        let urls: Vec<String> = lines
+            .into_iter()
+            .filter(|line| seen.insert(tags::parse_target(line).0))
+            .collect();
+        if !urls.is_empty() {
+            if !silent {
+                println!(
+                    "{} {} {}",
+                    "watch ::".bold().green(),
+                    urls.len().to_string().bold().cyan(),
+                    format!("new urls in {}", path).white(),
+                );
+            }
+            if scan(&args, &urls).await? {
+                return Ok(());
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(WATCH_INTERVAL) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,488 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures::StreamExt;
+use regex::Regex;
+
+use crate::client::Client;
+
+// returns true when the list is given as a http(s) url instead of a path.
+fn is_remote(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+// the raw files of the SecLists repository the `seclists:` lists are downloaded from
+const SECLISTS_RAW_URL: &str = "https://raw.githubusercontent.com/danielmiessler/SecLists/master";
+
+// the client the remote lists are downloaded with. unlike the scan client it
+// sends none of the headers, credentials or cookies of the targets, which must
+// not reach the host serving the list.
+pub fn list_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_default()
+}
+
+// the directory pathbuster keeps its state in, ~/.pathbuster
+fn pathbuster_dir() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".pathbuster"))
+}
+
+// the directory the remote lists are cached in, ~/.pathbuster/cache
+fn cache_dir() -> Option<PathBuf> {
+    Some(pathbuster_dir()?.join("cache"))
+}
+
+// resolves `seclists:Discovery/Web-Content/common.txt` against the SecLists checkout
+// in ~/.pathbuster/seclists, a list missing from it is downloaded into it.
+async fn fetch_seclists(client: &reqwest::Client, list: &str) -> Result<String, String> {
+    let list = list.trim_start_matches('/');
+    if list.split('/').any(|segment| segment == "..") {
+        return Err(format!("invalid seclists list: {}", list));
+    }
+    let checkout = match pathbuster_dir() {
+        Some(dir) => dir.join("seclists"),
+        None => {
+            return Err("could not find the home directory for the seclists checkout".to_string())
+        }
+    };
+    let list_path = checkout.join(list);
+    if let Ok(contents) = tokio::fs::read_to_string(&list_path).await {
+        return Ok(contents);
+    }
+
+    let url = format!("{}/{}", SECLISTS_RAW_URL, list);
+    let contents = download(client, &url).await?;
+    if let Some(dir) = list_path.parent() {
+        if tokio::fs::create_dir_all(dir).await.is_ok() {
+            let _ = tokio::fs::write(&list_path, &contents).await;
+        }
+    }
+    Ok(contents)
+}
+
+// downloads the body of the url with the plain list client.
+async fn download(client: &reqwest::Client, url: &str) -> Result<String, String> {
+    let resp = match client.get(url).send().await {
+        Ok(resp) => resp,
+        Err(e) => return Err(format!("failed to download {}: {:?}", url, e)),
+    };
+    if !resp.status().is_success() {
+        return Err(format!("failed to download {}: {}", url, resp.status()));
+    }
+    match resp.text().await {
+        Ok(contents) => Ok(contents),
+        Err(e) => Err(format!("failed to download {}: {:?}", url, e)),
+    }
+}
+
+// the name of the cached copy of the url, the characters that aren't safe in a
+// file name are replaced so the cache stays readable.
+fn cache_name(url: &str) -> String {
+    url.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// downloads the list once, later runs read the cached copy so a team can share
+// canonical lists without copying files around. --refresh-lists downloads them again.
+async fn fetch_remote(
+    client: &reqwest::Client,
+    url: &str,
+    refresh: bool,
+) -> Result<String, String> {
+    let cache_path = cache_dir().map(|dir| dir.join(cache_name(url)));
+    if let Some(cache_path) = &cache_path {
+        if !refresh {
+            if let Ok(contents) = tokio::fs::read_to_string(cache_path).await {
+                return Ok(contents);
+            }
+        }
+    }
+
+    let contents = download(client, url).await?;
+
+    // a cache that can't be written only costs a download next time.
+    if let Some(cache_path) = &cache_path {
+        if let Some(dir) = cache_path.parent() {
+            if tokio::fs::create_dir_all(dir).await.is_ok() {
+                let _ = tokio::fs::write(cache_path, &contents).await;
+            }
+        }
+    }
+    Ok(contents)
+}
+
+// loads the lines of a wordlist or payloads file, from a path, a http(s) url
+// or a `seclists:` list, refresh skips the cached copy of the urls.
+pub async fn load_list(
+    client: &reqwest::Client,
+    source: &str,
+    refresh: bool,
+) -> Result<Vec<String>, String> {
+    let contents = if let Some(list) = source.strip_prefix("seclists:") {
+        fetch_seclists(client, list).await?
+    } else if is_remote(source) {
+        fetch_remote(client, source, refresh).await?
+    } else {
+        match tokio::fs::read_to_string(source).await {
+            Ok(contents) => contents,
+            Err(e) => return Err(format!("failed to open input file: {:?}", e)),
+        }
+    };
+    Ok(contents.lines().map(|line| line.to_string()).collect())
+}
+
+// returns the files of the wordlist directory, sorted so the merge order is stable.
+pub fn list_dir(dir: &str) -> Result<Vec<String>, String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => return Err(format!("failed to open wordlist directory: {:?}", e)),
+    };
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+// loads and merges the wordlists in order without the duplicates, the summary
+// holds the words each source had and how many of them were new.
+pub async fn load_wordlist(
+    client: &reqwest::Client,
+    sources: &[String],
+    refresh: bool,
+) -> Result<(Vec<String>, Vec<(String, usize, usize)>), String> {
+    let mut seen = HashSet::new();
+    let mut wordlist = vec![];
+    let mut summary = vec![];
+    for source in sources {
+        let words = load_list(client, source, refresh).await?;
+        let loaded = words.len();
+        let mut added = 0;
+        for word in words {
+            if seen.insert(word.clone()) {
+                wordlist.push(word);
+                added += 1;
+            }
+        }
+        summary.push((source.clone(), loaded, added));
+    }
+    Ok((wordlist, summary))
+}
+
+// the characters a word can hold without being encoded in a path, the rest
+// of them can't make a valid request
+const PATH_CHARS: &str = "-._~!$&'()*+,;=:@/%";
+
+// the WordlistOptions struct holds the clean ups applied to the merged wordlist
+#[derive(Clone, Debug, Default)]
+pub struct WordlistOptions {
+    pub lowercase: bool,
+    pub strip_slashes: bool,
+    pub drop_invalid: bool,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+}
+
+// applies the clean ups to the wordlist, the words they make equal are only kept once.
+pub fn apply_wordlist_manipulations(words: Vec<String>, options: &WordlistOptions) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut manipulated = vec![];
+    for word in words {
+        let mut word = word.trim().to_string();
+        if options.lowercase {
+            word = word.to_lowercase();
+        }
+        if options.strip_slashes {
+            word = word.trim_start_matches('/').to_string();
+        }
+        if options.drop_invalid
+            && !word
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || PATH_CHARS.contains(c))
+        {
+            continue;
+        }
+        let length = word.chars().count();
+        if options
+            .min_length
+            .is_some_and(|min_length| length < min_length)
+            || options
+                .max_length
+                .is_some_and(|max_length| length > max_length)
+        {
+            continue;
+        }
+        if seen.insert(word.clone()) {
+            manipulated.push(word);
+        }
+    }
+    manipulated
+}
+
+// the WordLearner struct collects the path tokens of the responses seen during
+// validation and the names the follow up stages found, the ones missing from
+// the wordlist are bruteforced as well
+#[derive(Debug, Default)]
+pub struct WordLearner {
+    known: Mutex<HashSet<String>>,
+    learned: Mutex<Vec<String>>,
+    tokenize: bool,
+}
+
+impl WordLearner {
+    pub fn new(wordlist: &[String]) -> WordLearner {
+        WordLearner {
+            known: Mutex::new(wordlist.iter().cloned().collect()),
+            learned: Mutex::new(vec![]),
+            tokenize: true,
+        }
+    }
+
+    // the responses are only tokenized with --learn-words, the names of the
+    // follow up stages are added either way.
+    pub fn with_tokenize(mut self, tokenize: bool) -> WordLearner {
+        self.tokenize = tokenize;
+        self
+    }
+
+    // tokenizes the response and keeps the tokens that weren't seen yet.
+    pub fn learn(&self, content: &str) {
+        if !self.tokenize {
+            return;
+        }
+        let tokens = tokenize(content);
+        let mut known = self.known.lock().unwrap();
+        let mut learned = self.learned.lock().unwrap();
+        for token in tokens {
+            if known.insert(token.clone()) {
+                learned.push(token);
+            }
+        }
+    }
+
+    // keeps the word when it wasn't seen yet, eg an iis short name.
+    pub fn add(&self, word: &str) {
+        if self.known.lock().unwrap().insert(word.to_string()) {
+            self.learned.lock().unwrap().push(word.to_string());
+        }
+    }
+
+    // returns the learned words in the order they were seen.
+    pub fn words(&self) -> Vec<String> {
+        self.learned.lock().unwrap().clone()
+    }
+}
+
+// returns true when the token can be bruteforced as a path segment.
+fn is_path_token(token: &str) -> bool {
+    (2..=64).contains(&token.len())
+        && !token.chars().all(|c| c == '.')
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-._~".contains(c))
+}
+
+// returns the path of a link without its query, the links to other sites only
+// give their path.
+fn link_path(link: &str) -> &str {
+    let path = match link.split_once("://") {
+        Some((_, rest)) => match rest.find('/') {
+            Some(index) => &rest[index..],
+            None => "",
+        },
+        None => link,
+    };
+    path.split(['?', '#']).next().unwrap_or("")
+}
+
+// returns the segments of the path of a link.
+fn link_segments(link: &str) -> Vec<String> {
+    link_path(link)
+        .split('/')
+        .filter(|segment| is_path_token(segment))
+        .map(|segment| segment.to_string())
+        .collect()
+}
+
+// matches the links of a html page
+fn link_regex() -> Regex {
+    Regex::new(r#"(?i)(?:href|src|action)\s*=\s*["']([^"']+)["']"#).unwrap()
+}
+
+// extracts the path segments of the links of a html page and the keys and
+// values of a json document, eg `<a href="/api/v2/users">` gives `api`, `v2`
+// and `users`.
+pub fn tokenize(content: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    for cap in link_regex().captures_iter(content) {
+        tokens.extend(link_segments(&cap[1]));
+    }
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        let strings = Regex::new(r#""([^"\\\s]{1,256})""#).unwrap();
+        for cap in strings.captures_iter(content) {
+            tokens.extend(link_segments(&cap[1]));
+        }
+    }
+    tokens
+}
+
+// the server side extensions detected from the links of the targets, the static
+// ones say nothing about the backend
+const DYNAMIC_EXTENSIONS: [&str; 8] = ["php", "asp", "aspx", "jsp", "jspx", "do", "action", "cfm"];
+
+// parses the comma separated --extensions value, the leading dots are optional.
+pub fn parse_extensions(value: &str) -> Vec<String> {
+    let mut extensions: Vec<String> = vec![];
+    for extension in value.split(',') {
+        let extension = extension.trim().trim_start_matches('.').to_lowercase();
+        if !extension.is_empty() && !extensions.contains(&extension) {
+            extensions.push(extension);
+        }
+    }
+    extensions
+}
+
+// the placeholder of the dirsearch wordlists replaced by each extension
+const EXT_PLACEHOLDER: &str = "%ext%";
+
+// the ExtensionMode enum selects the words the extensions are applied to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExtensionMode {
+    // the extensions are appended to the words that don't have one
+    #[default]
+    Append,
+    // like dirsearch only the `%EXT%` placeholders are replaced
+    Placeholder,
+    // like dirsearch -f the extensions and a slash are appended to the words
+    // that don't have an extension, on top of the placeholders
+    Force,
+}
+
+// replaces every `%EXT%` of the word with each extension, the placeholder
+// matches in any case so lowercased lists keep working.
+fn expand_placeholder(word: &str, extensions: &[String]) -> Vec<String> {
+    extensions
+        .iter()
+        .map(|extension| {
+            let mut expanded = String::new();
+            let mut rest = word;
+            while let Some(index) = rest.to_ascii_lowercase().find(EXT_PLACEHOLDER) {
+                expanded.push_str(&rest[..index]);
+                expanded.push_str(extension);
+                rest = &rest[index + EXT_PLACEHOLDER.len()..];
+            }
+            expanded.push_str(rest);
+            expanded
+        })
+        .collect()
+}
+
+// applies the extensions to the words, eg `admin` gives `admin`, `admin.php`
+// and `admin.aspx` and `index.%EXT%` gives `index.php` and `index.aspx`. the
+// words with a placeholder are dropped when there is no extension.
+pub fn apply_wordlist_extensions(
+    words: Vec<String>,
+    extensions: &[String],
+    mode: ExtensionMode,
+) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut extended = vec![];
+    for word in words {
+        let variants = if word.to_ascii_lowercase().contains(EXT_PLACEHOLDER) {
+            expand_placeholder(&word, extensions)
+        } else {
+            let last_segment = word.rsplit('/').next().unwrap_or("");
+            let mut variants = vec![word.clone()];
+            if !last_segment.is_empty() && !last_segment.contains('.') {
+                if mode != ExtensionMode::Placeholder {
+                    for extension in extensions {
+                        variants.push(format!("{}.{}", word, extension));
+                    }
+                }
+                if mode == ExtensionMode::Force {
+                    variants.push(format!("{}/", word));
+                }
+            }
+            variants
+        };
+        for word in variants {
+            if seen.insert(word.clone()) {
+                extended.push(word);
+            }
+        }
+    }
+    extended
+}
+
+// fetches the targets and the root of their sites, and returns the server side
+// extensions their links use the most, at most three of them.
+pub async fn detect_extensions(
+    client: &Client,
+    urls: &[String],
+    concurrency: usize,
+) -> Vec<String> {
+    let mut pages: Vec<String> = vec![];
+    for url in urls {
+        let root = match reqwest::Url::parse(url) {
+            Ok(parsed) => format!("{}/", parsed.origin().ascii_serialization()),
+            Err(_) => continue,
+        };
+        for page in [url.clone(), root] {
+            if !pages.contains(&page) {
+                pages.push(page);
+            }
+        }
+    }
+
+    let bodies: Vec<String> = futures::stream::iter(pages)
+        .map(|page| async move {
+            let req = client.get(&page).build().ok()?;
+            let resp = client.execute(req).await.ok()?;
+            resp.text().await.ok()
+        })
+        .buffer_unordered(concurrency.max(1))
+        .filter_map(|body| async move { body })
+        .collect()
+        .await;
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let links = link_regex();
+    for body in &bodies {
+        for cap in links.captures_iter(body) {
+            let last_segment = link_path(&cap[1]).rsplit('/').next().unwrap_or("");
+            if let Some((_, extension)) = last_segment.rsplit_once('.') {
+                let extension = extension.to_lowercase();
+                if DYNAMIC_EXTENSIONS.contains(&extension.as_str()) {
+                    *counts.entry(extension).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    // the extensions seen a fifth as often as the most common one are kept.
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let top = counts.first().map(|(_, count)| *count).unwrap_or(0);
+    counts
+        .into_iter()
+        .filter(|(_, count)| count * 5 >= top)
+        .take(3)
+        .map(|(extension, _)| extension)
+        .collect()
+}